@@ -2,17 +2,32 @@ use std::mem;
 use std::fmt;
 use std::fmt::{Debug, Display};
 use bytemuck::Pod;
-
+use crate::cursor::{TydiBitReader, TydiBitWriter};
+use crate::error::TydiError;
+use crate::order::{TydiBitOrder, TydiEndianness, TydiFieldOrder};
+use crate::logical::TydiLogical;
+use crate::transfer::{index_from_binary, index_to_binary, index_width};
+
+/// A sequence of bits, along with the [TydiBitOrder] it is packed in.
+///
+/// Binaries are created in [TydiBitOrder::PORTABLE] unless they are packed in another order through [ToTydiBinary],
+/// and are split and unpacked in the order they were created in.
 #[derive(Clone, PartialEq, Eq)]
 pub struct TydiBinary {
     pub data: Vec<u8>,
     pub len: usize,
+    pub order: TydiBitOrder,
 }
 
 
 impl TydiBinary {
     pub fn empty() -> Self {
-        Self { data: Vec::new(), len: 0 }
+        Self { data: Vec::new(), len: 0, order: TydiBitOrder::PORTABLE }
+    }
+
+    /// Creates a TydiBinary of [len] bits that are all zero.
+    pub fn zeros(len: usize) -> Self {
+        Self { data: vec![0u8; len.div_ceil(8)], len, order: TydiBitOrder::PORTABLE }
     }
 
     /// Creates a new TydiBinary struct from a vector of bytes and a bit length.
@@ -20,111 +35,259 @@ impl TydiBinary {
         // Simple sanity check to ensure the length is not greater than
         // the capacity of the data vector.
         assert!(len <= data.len() * 8, "Length cannot exceed data capacity");
-        Self { data, len }
+        Self { data, len, order: TydiBitOrder::PORTABLE }
     }
 
-    /// Concatenates this TydiBinary with another one, returning a new TydiBinary.
-    pub fn concatenate(&self, other: &Self) -> Self {
-        // If this TydiBinary is empty, the result is simply a clone of the other.
-        if self.len == 0 {
-            return other.clone();
+    /// Like [TydiBinary::new], returning an error if [len] exceeds the bits in [data].
+    pub fn try_new(data: Vec<u8>, len: usize) -> Result<Self, TydiError> {
+        if len > data.len() * 8 {
+            return Err(TydiError::Truncated { needed: len, available: data.len() * 8 });
         }
+        Ok(Self { data, len, order: TydiBitOrder::PORTABLE })
+    }
 
-        // Calculate the total length of the new binary string.
-        let new_len = self.len + other.len;
+    /// Creates an empty TydiBinary with room for [bits] bits, to be filled through [TydiBinary::append].
+    pub fn with_capacity(bits: usize) -> Self {
+        Self { data: Vec::with_capacity(bits.div_ceil(8)), len: 0, order: TydiBitOrder::PORTABLE }
+    }
 
-        // Calculate the number of bits already in the last byte of `self`.
-        let self_tail_bits = self.len % 8;
+    /// The same bits, to be unpacked in [order]. Only reinterprets the bits, which is meant for binaries that do not
+    /// depend on the order, such as zeros, or that were packed elsewhere in [order].
+    pub fn in_order(self, order: TydiBitOrder) -> Self {
+        Self { order, ..self }
+    }
 
-        // If `self` is byte-aligned, we can simply extend its data with `other`'s data.
-        if self_tail_bits == 0 {
-            let mut new_data = self.data.clone();
-            new_data.extend_from_slice(&other.data);
-            return Self::new(new_data, new_len);
-        }
+    /// Concatenates this TydiBinary with another one, returning a new TydiBinary.
+    pub fn concatenate(&self, other: &Self) -> Self {
+        let mut result = Self::with_capacity(self.len + other.len);
+        result.append(self);
+        result.append(other);
+        result
+    }
 
-        // The number of bits needed to complete the last byte of `self`.
-        let tail_space = 8 - self_tail_bits;
+    /// Concatenates [fields], the first field first, moving the bits of every field once. Panics if the fields that
+    /// hold bits are packed in different orders.
+    pub fn concatenate_all(fields: &[TydiBinary]) -> Self {
+        let order = fields.iter().find(|field| field.len > 0).map_or(TydiBitOrder::PORTABLE, |field| field.order);
+        let mut result = Self::with_capacity(fields.iter().map(|field| field.len).sum()).in_order(order);
+        let mut push = |field: &TydiBinary| {
+            assert!(field.len == 0 || field.order == order,
+                    "cannot append a binary packed in {:?} to one packed in {:?}", field.order, order);
+            result.push_bits(field);
+        };
+        match order.field_order {
+            TydiFieldOrder::LsbFirst => fields.iter().for_each(&mut push),
+            // The last field ends up at the least significant bits
+            TydiFieldOrder::MsbFirst => fields.iter().rev().for_each(&mut push),
+        }
+        result
+    }
 
-        // Clone the data from the first binary to start building the new vector.
-        let mut new_data = self.data.clone();
+    /// Appends [other] as the field after the bits of this binary, placed according to the
+    /// [TydiFieldOrder] of the binaries. Does not reallocate if there is enough capacity and the first
+    /// field is at the least significant bits. With [TydiFieldOrder::MsbFirst], the bits so far move up to make room,
+    /// so build binaries of many fields with [TydiBinary::concatenate_all] or a [crate::cursor::TydiBitWriter].
+    ///
+    /// Panics if both binaries hold bits and are packed in different orders. An empty binary takes the order of
+    /// [other].
+    pub fn append(&mut self, other: &Self) {
+        if self.len == 0 {
+            self.order = other.order;
+        }
+        assert!(other.len == 0 || self.order == other.order,
+                "cannot append a binary packed in {:?} to one packed in {:?}", other.order, self.order);
+        match self.order.field_order {
+            TydiFieldOrder::LsbFirst => self.push_bits(other),
+            TydiFieldOrder::MsbFirst => {
+                // The fields so far move up to make room for [other] below them
+                let mut result = Self::with_capacity(self.len + other.len).in_order(self.order);
+                result.push_bits(other);
+                result.push_bits(self);
+                *self = result;
+            }
+        }
+    }
 
-        // Handle the last byte of `self` and its combination with the first bytes of `other`.
-        // This is the core of the non-byte-aligned concatenation.
-        for (i, &other_byte) in other.data.iter().enumerate() {
-            // Get a mutable reference to the last byte of `new_data`.
-            let last_byte = new_data.last_mut().unwrap();
+    /// Places [other] above the most significant bit of this binary.
+    ///
+    /// If this binary does not end at a byte boundary, [other] is shifted into place a word at a time.
+    fn push_bits(&mut self, other: &Self) {
+        let other_bytes = &other.data[..other.len.div_ceil(8)];
+        let shift = self.len % 8;
+        self.data.truncate(self.len.div_ceil(8));
+        self.data.reserve(other_bytes.len() + 1);
+
+        if shift == 0 {
+            self.data.extend_from_slice(other_bytes);
+        } else {
+            // The partially filled final byte, which the first bits of `other` complete
+            let mut carry = self.data.pop().unwrap() as u64 & ((1 << shift) - 1);
+            let mut words = other_bytes.chunks_exact(8);
+            for word in words.by_ref() {
+                let word = u64::from_le_bytes(word.try_into().unwrap());
+                self.data.extend_from_slice(&(carry | (word << shift)).to_le_bytes());
+                carry = word >> (64 - shift);
+            }
+            for &byte in words.remainder() {
+                self.data.push((carry | ((byte as u64) << shift)) as u8);
+                carry = (byte as u64) >> (8 - shift);
+            }
+            self.data.push(carry as u8);
+        }
 
-            // Fill the remaining space in the last byte of `self` with bits from `other_byte`.
-            let bits_from_other = other_byte << (8 - tail_space);
-            *last_byte |= bits_from_other;
+        self.len += other.len;
+        self.data.truncate(self.len.div_ceil(8));
+        self.mask_tail();
+    }
 
-            // If we're not at the end of the `other` data, push the carry-over bits
-            // as a new byte. The carry-over bits are the lower `tail_space` bits
-            // of the current `other` byte, shifted into a new byte.
-            if i < other.data.len() - 1 || (other.len - (i * 8) > tail_space) {
-                let carry_over = (other_byte >> tail_space);
-                new_data.push(carry_over);
-            }
+    /// Clears the bits of the final byte that lie beyond the length.
+    fn mask_tail(&mut self) {
+        let tail_bits = self.len % 8;
+        if tail_bits > 0 && let Some(last) = self.data.last_mut() {
+            *last &= 0xFF >> (8 - tail_bits);
         }
+    }
 
-        Self::new(new_data, new_len)
+    /// A borrowed view of all bits of this binary.
+    pub fn as_bits(&self) -> TydiBitSlice<'_> {
+        TydiBitSlice { data: &self.data, offset: 0, len: self.len, order: self.order }
     }
 
     /// Splits this TydiBinary into two new TydiBinary instances at the specified length.
-    /// Returns a tuple of (TydiBinary, TydiBinary).
+    /// Returns a tuple of (TydiBinary, TydiBinary), the first holding the first field of [len1] bits according to the
+    /// [TydiBitOrder] of this binary.
+    ///
+    /// Use [TydiBinary::as_bits] or [crate::cursor::TydiBitReader] to split without copying.
     pub fn split(&self, len1: usize) -> (Self, Self) {
-        let len2 = self.len - len1;
-
-        // Part 1: First TydiBinary
-        let mut data1 = Vec::new();
-        let full_bytes1 = len1 / 8;
-        let bit_offset = len1 % 8;
-        for i in 0..full_bytes1 {
-            data1.push(self.data[i]);
+        let (first, second) = self.as_bits().split_field(len1);
+        (first.to_binary(), second.to_binary())
+    }
+
+    /// Like [TydiBinary::split], returning an error if this binary holds fewer than [len1] bits.
+    pub fn try_split(&self, len1: usize) -> Result<(Self, Self), TydiError> {
+        if len1 > self.len || self.len > self.data.len() * 8 {
+            return Err(TydiError::Truncated { needed: len1.max(self.len), available: self.len.min(self.data.len() * 8) });
         }
-        // Add leftover bits that are not a full byte
-        if bit_offset > 0 {
-            let byte_to_push = self.data[full_bytes1] & (!0u8 >> (8 - bit_offset));
-            data1.push(byte_to_push);
+        Ok(self.split(len1))
+    }
+
+    /// Takes a [T] from the first field, reading its bytes as [T] is laid out in memory on this host, regardless of
+    /// the [TydiEndianness]. Use [FromTydiBinary] for a portable format.
+    pub fn split_for<T: Pod>(&self) -> (T, TydiBinary) {
+        self.try_split_for().expect("binary too short to split off the value")
+    }
+
+    /// Like [TydiBinary::split_for], returning an error if this binary is too short to hold a [T].
+    pub fn try_split_for<T: Pod>(&self) -> Result<(T, TydiBinary), TydiError> {
+        let (split1, split2) = self.try_split(size_of::<T>() * 8)?;
+        Ok((bytemuck::pod_read_unaligned(split1.data.as_slice()), split2))
+    }
+}
+
+/// A borrowed range of the bits of a [TydiBinary], which can be split and read without copying.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TydiBitSlice<'a> {
+    data: &'a [u8],
+    /// Index of the first bit of the slice in [data].
+    offset: usize,
+    len: usize,
+    /// Order of the binary the bits are taken from.
+    order: TydiBitOrder,
+}
+
+/// Reads up to eight bytes of [data] from [start] as a little-endian word, padded with zeros.
+fn load_word(data: &[u8], start: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    if start < data.len() {
+        let end = (start + 8).min(data.len());
+        bytes[..end - start].copy_from_slice(&data[start..end]);
+    }
+    u64::from_le_bytes(bytes)
+}
+
+impl<'a> TydiBitSlice<'a> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The bit at [index], counted from the start of the slice.
+    pub fn get(&self, index: usize) -> bool {
+        assert!(index < self.len, "bit {} out of range for a slice of {} bits", index, self.len);
+        let bit = self.offset + index;
+        (self.data[bit / 8] >> (bit % 8)) & 1 == 1
+    }
+
+    /// Reads [count] bits, at most 64, starting at [start] as an unsigned integer, the first bit being the least
+    /// significant.
+    pub fn read_bits(&self, start: usize, count: usize) -> u64 {
+        assert!(count <= 64, "cannot read more than 64 bits at once");
+        assert!(start + count <= self.len, "bits {}..{} out of range for a slice of {} bits", start, start + count, self.len);
+        if count == 0 {
+            return 0;
         }
-        let bin1 = TydiBinary::new(data1, len1);
-
-        // Part 2: Second TydiBinary
-        let mut data2 = Vec::new();
-        let full_bytes2 = len2 / 8;
-        let rem_bits2 = len2 % 8;
-
-        let bytes_to_add = full_bytes2 + if rem_bits2 > 0 { 1 } else { 0 };
-        // If the first binary ended at a clean border we need to subtract one to get the index of the last accessed byte
-        let start_index = full_bytes1 - if bit_offset > 0 { 0 } else { 1 };
-        for i in start_index..(start_index+bytes_to_add) {
-            let next_byte_index = i + 1;
-
-            let current_byte_original = self.data[i];
-            let next_byte_original = if next_byte_index < self.data.len() {
-                self.data[next_byte_index]
-            } else {
-                0
-            };
-
-            let new_byte = if (bit_offset == 0) {
-                next_byte_original
-            } else {
-                (current_byte_original >> bit_offset) | (next_byte_original << (8 - bit_offset))
-            };
-            data2.push(new_byte);
+        let bit = self.offset + start;
+        let shift = bit % 8;
+        let low = load_word(self.data, bit / 8) >> shift;
+        // Bits that did not fit in the first word after shifting
+        let value = if shift > 0 { low | (load_word(self.data, bit / 8 + 8) << (64 - shift)) } else { low };
+        if count == 64 { value } else { value & ((1 << count) - 1) }
+    }
+
+    /// The order of the binary the bits are taken from.
+    pub fn order(&self) -> TydiBitOrder {
+        self.order
+    }
+
+    /// Splits the first field of [count] bits off the slice, at the least or most significant end depending on
+    /// its [TydiFieldOrder]. Returns the field and the rest.
+    pub fn split_field(&self, count: usize) -> (Self, Self) {
+        assert!(count <= self.len, "cannot split {} bits off a slice of {} bits", count, self.len);
+        match self.order.field_order {
+            TydiFieldOrder::LsbFirst => self.split_at(count),
+            TydiFieldOrder::MsbFirst => {
+                let (rest, field) = self.split_at(self.len - count);
+                (field, rest)
+            }
         }
+    }
 
-        let bin2 = TydiBinary::new(data2, len2);
-        (bin1, bin2)
+    /// Splits the slice into the bits below index [mid] and the rest, regardless of the field order.
+    pub fn split_at(&self, mid: usize) -> (Self, Self) {
+        assert!(mid <= self.len, "cannot split {} bits off a slice of {} bits", mid, self.len);
+        (
+            Self { len: mid, ..*self },
+            Self { offset: self.offset + mid, len: self.len - mid, ..*self },
+        )
     }
 
-    pub fn split_for<T: Pod>(&self) -> (T, TydiBinary) {
-        let len = size_of::<T>() * 8;
-        let (split1, split2) = self.split(len);
-        let val: T = *bytemuck::from_bytes(split1.data.as_slice());
-        (val, split2)
+    /// The slice without its [count] least significant bits.
+    pub fn skip(&self, count: usize) -> Self {
+        self.split_at(count).1
+    }
+
+    /// Copies the bits of the slice into a new binary.
+    pub fn to_binary(&self) -> TydiBinary {
+        let start = self.offset / 8;
+        let shift = self.offset % 8;
+        let n_bytes = self.len.div_ceil(8);
+        let mut data = Vec::with_capacity(n_bytes);
+        if shift == 0 {
+            data.extend_from_slice(&self.data[start..start + n_bytes]);
+        } else {
+            for word in (0..n_bytes).step_by(8) {
+                let value = (load_word(self.data, start + word) >> shift)
+                    | (load_word(self.data, start + word + 8) << (64 - shift));
+                let take = (n_bytes - word).min(8);
+                data.extend_from_slice(&value.to_le_bytes()[..take]);
+            }
+        }
+        let mut result = TydiBinary { data, len: self.len, order: self.order };
+        result.mask_tail();
+        result
     }
 }
 
@@ -203,56 +366,208 @@ impl Debug for TydiBinary {
             .field("data", &self.data)
             .field("binary", &binary_string.trim())
             .field("hex", &hex_string.trim())
+            .field("order", &self.order)
             .finish()
     }
 }
 
+/// Values that can be packed in any [TydiBitOrder]. `From<T> for TydiBinary` packs in [TydiBitOrder::PORTABLE].
+pub trait ToTydiBinary: Sized {
+    /// Packs the value in [order].
+    fn to_tydi_binary(self, order: TydiBitOrder) -> TydiBinary;
+}
+
+/// A binary is already packed, and keeps the order it was packed in.
+impl ToTydiBinary for TydiBinary {
+    fn to_tydi_binary(self, _order: TydiBitOrder) -> TydiBinary {
+        self
+    }
+}
+
 macro_rules! impl_from_primitive {
     ($($t:ty),*) => {
         $(
+            impl ToTydiBinary for $t {
+                fn to_tydi_binary(self, order: TydiBitOrder) -> TydiBinary {
+                    let data = match order.endianness {
+                        TydiEndianness::Little => self.to_le_bytes(),
+                        TydiEndianness::Big => self.to_be_bytes(),
+                    };
+                    TydiBinary::new(data.to_vec(), mem::size_of::<$t>() * 8).in_order(order)
+                }
+            }
+
             impl From<$t> for TydiBinary {
                 fn from(value: $t) -> Self {
-                    TydiBinary {
-                        data: value.to_ne_bytes().to_vec(),
-                        len: mem::size_of::<$t>() * 8,
-                    }
+                    value.to_tydi_binary(TydiBitOrder::PORTABLE)
                 }
             }
 
             impl From<TydiBinary> for $t {
                 fn from(value: TydiBinary) -> Self {
                     let (int_bytes, _) = value.data.split_at(size_of::<$t>());
-                    <$t>::from_ne_bytes(int_bytes.try_into().unwrap())
+                    let int_bytes = int_bytes.try_into().unwrap();
+                    match value.order.endianness {
+                        TydiEndianness::Little => <$t>::from_le_bytes(int_bytes),
+                        TydiEndianness::Big => <$t>::from_be_bytes(int_bytes),
+                    }
                 }
             }
 
             impl FromTydiBinary for $t {
                 fn from_tydi_binary(value: TydiBinary) -> (Self, TydiBinary) {
                     let (bin1, bin2) = value.split(size_of::<$t>() * 8);
-                    let int_bytes = bin1.data.as_slice();
-                    (<$t>::from_ne_bytes(int_bytes.try_into().unwrap()), bin2)
+                    (bin1.into(), bin2)
+                }
+
+                fn try_from_tydi_binary(value: TydiBinary) -> Result<(Self, TydiBinary), TydiError> {
+                    let (bin1, bin2) = value.try_split(size_of::<$t>() * 8)?;
+                    Ok((bin1.into(), bin2))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_primitive!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+/// `usize` and `isize` are packed as 64 bits on every host, like [u64] and [i64].
+macro_rules! impl_from_pointer_sized {
+    ($($t:ty as $fixed:ty),*) => {
+        $(
+            impl ToTydiBinary for $t {
+                fn to_tydi_binary(self, order: TydiBitOrder) -> TydiBinary {
+                    (self as $fixed).to_tydi_binary(order)
+                }
+            }
+
+            impl From<$t> for TydiBinary {
+                fn from(value: $t) -> Self {
+                    value.to_tydi_binary(TydiBitOrder::PORTABLE)
+                }
+            }
+
+            impl From<TydiBinary> for $t {
+                fn from(value: TydiBinary) -> Self {
+                    <$fixed>::from(value) as $t
+                }
+            }
+
+            impl FromTydiBinary for $t {
+                fn from_tydi_binary(value: TydiBinary) -> (Self, TydiBinary) {
+                    let (value, res) = <$fixed>::from_tydi_binary(value);
+                    (value as $t, res)
+                }
+
+                fn try_from_tydi_binary(value: TydiBinary) -> Result<(Self, TydiBinary), TydiError> {
+                    let (value, res) = <$fixed>::try_from_tydi_binary(value)?;
+                    Ok((value as $t, res))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_pointer_sized!(usize as u64, isize as i64);
+
+/// Integers that can be packed into fewer bits than their native width, see `#[tydi(bits = ..)]`.
+///
+/// Fields of a whole number of bytes follow the [TydiEndianness] like primitives, so that a field as wide as its type
+/// packs the same as the type itself. The bits of other fields are in little-endian order regardless.
+pub trait TydiBitField: Sized {
+    /// Packs the value into [bits] bits in [order], in two's complement if [signed]. Panics if the value does not fit.
+    fn to_bit_field(self, bits: usize, signed: bool, order: TydiBitOrder) -> TydiBinary;
+    /// Takes a value of [bits] bits from [value], sign-extending it if [signed].
+    fn from_bit_field(value: TydiBinary, bits: usize, signed: bool) -> (Self, TydiBinary);
+}
+
+macro_rules! impl_bit_field {
+    ($($t:ty),*) => {
+        $(
+            impl TydiBitField for $t {
+                fn to_bit_field(self, bits: usize, signed: bool, order: TydiBitOrder) -> TydiBinary {
+                    assert!(bits <= <$t>::logical_type().data_width(), "{} bits do not fit in a {}", bits, stringify!($t));
+                    let fits = if signed {
+                        // All bits above the sign bit have to be equal to it
+                        i128::try_from(self).is_ok_and(|v| bits > 0 && (v >> (bits - 1) == 0 || v >> (bits - 1) == -1))
+                    } else {
+                        u128::try_from(self).is_ok_and(|v| bits >= 128 || v >> bits == 0)
+                    };
+                    assert!(fits, "{} does not fit in {} {} bits", self, bits, if signed { "signed" } else { "unsigned" });
+                    let field = TydiBinary::new((self as u128).to_le_bytes().to_vec(), 128).as_bits().split_at(bits).0.to_binary();
+                    let mut field = field.in_order(order);
+                    if bits.is_multiple_of(8) && order.endianness == TydiEndianness::Big {
+                        field.data.reverse();
+                    }
+                    field
+                }
+
+                fn from_bit_field(value: TydiBinary, bits: usize, signed: bool) -> (Self, TydiBinary) {
+                    let (mut field, res) = value.split(bits);
+                    if bits.is_multiple_of(8) && field.order.endianness == TydiEndianness::Big {
+                        field.data.reverse();
+                    }
+                    let mut bytes = [0u8; 16];
+                    bytes[..field.data.len()].copy_from_slice(&field.data);
+                    let mut result = u128::from_le_bytes(bytes);
+                    if signed && bits < 128 && (result >> (bits - 1)) & 1 == 1 {
+                        result |= u128::MAX << bits;
+                    }
+                    (result as $t, res)
                 }
             }
         )*
     };
 }
 
-impl_from_primitive!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+impl_bit_field!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
 
 pub trait FromTydiBinary where Self: Sized {
+    /// Takes a [Self] from the first field of [value], returning it and the remaining bits. Panics if [value] does not
+    /// hold a valid [Self].
     fn from_tydi_binary(value: TydiBinary) -> (Self, TydiBinary);
+
+    /// Like [FromTydiBinary::from_tydi_binary], returning an error if [value] is too short or its bits are not a valid
+    /// [Self], such as a union tag that selects no variant.
+    fn try_from_tydi_binary(value: TydiBinary) -> Result<(Self, TydiBinary), TydiError> {
+        Ok(Self::from_tydi_binary(value))
+    }
+}
+
+/// A `char` is packed as its 32-bit Unicode code point.
+impl ToTydiBinary for char {
+    fn to_tydi_binary(self, order: TydiBitOrder) -> TydiBinary {
+        (self as u32).to_tydi_binary(order)
+    }
 }
 
 impl From<char> for TydiBinary {
     fn from(value: char) -> Self {
-        let val: Vec<u8> = value.to_string().as_bytes().into();
-        Self { data: val, len: 8*4 }
+        value.to_tydi_binary(TydiBitOrder::PORTABLE)
+    }
+}
+
+impl FromTydiBinary for char {
+    fn from_tydi_binary(value: TydiBinary) -> (Self, TydiBinary) {
+        Self::try_from_tydi_binary(value).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_from_tydi_binary(value: TydiBinary) -> Result<(Self, TydiBinary), TydiError> {
+        let (code_point, res) = u32::try_from_tydi_binary(value)?;
+        let value = char::from_u32(code_point).ok_or(TydiError::InvalidChar(code_point))?;
+        Ok((value, res))
+    }
+}
+
+impl ToTydiBinary for bool {
+    fn to_tydi_binary(self, order: TydiBitOrder) -> TydiBinary {
+        TydiBinary::new(vec![self.into()], 1).in_order(order)
     }
 }
 
 impl From<bool> for TydiBinary {
     fn from(value: bool) -> Self {
-        Self { data: vec![value.into()], len: 1 }
+        value.to_tydi_binary(TydiBitOrder::PORTABLE)
     }
 }
 
@@ -262,6 +577,53 @@ impl FromTydiBinary for bool {
         let b_value: bool = bin1.data[0] != 0;
         (b_value, bin2)
     }
+
+    fn try_from_tydi_binary(value: TydiBinary) -> Result<(Self, TydiBinary), TydiError> {
+        let (bin1, bin2) = value.try_split(1)?;
+        Ok((bin1.data[0] != 0, bin2))
+    }
+}
+
+/// An option is packed as a union of nothing and [T]: a valid bit followed by the value of [T], which is zero-filled
+/// when there is no value.
+impl<T> ToTydiBinary for Option<T> where T: ToTydiBinary + TydiLogical {
+    fn to_tydi_binary(self, order: TydiBitOrder) -> TydiBinary {
+        let mut writer = TydiBitWriter::in_order(order);
+        writer.write(self.is_some());
+        match self {
+            Some(data) => writer.write(data),
+            None => writer.write_zeros(T::logical_type().data_width()),
+        };
+        writer.finish()
+    }
+}
+
+impl<T> From<Option<T>> for TydiBinary where T: ToTydiBinary + TydiLogical {
+    fn from(value: Option<T>) -> Self {
+        value.to_tydi_binary(TydiBitOrder::PORTABLE)
+    }
+}
+
+impl<T> FromTydiBinary for Option<T> where T: FromTydiBinary + TydiLogical {
+    fn from_tydi_binary(value: TydiBinary) -> (Self, TydiBinary) {
+        Self::try_from_tydi_binary(value).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// The bits of an absent value are not decoded, so they need not be a valid [T].
+    fn try_from_tydi_binary(value: TydiBinary) -> Result<(Self, TydiBinary), TydiError> {
+        let mut reader = TydiBitReader::new(&value);
+        let valid = reader.try_read::<bool>()?;
+        let data = reader.try_read_binary(T::logical_type().data_width())?;
+        let value = if valid { Some(T::try_from_tydi_binary(data)?.0) } else { None };
+        Ok((value, reader.finish()))
+    }
+}
+
+/// The bits are placed as given, the first at the least significant bit, in any order.
+impl ToTydiBinary for Vec<bool> {
+    fn to_tydi_binary(self, order: TydiBitOrder) -> TydiBinary {
+        TydiBinary::from(self).in_order(order)
+    }
 }
 
 impl From<Vec<bool>> for TydiBinary {
@@ -274,10 +636,9 @@ impl From<Vec<bool>> for TydiBinary {
         // Iterate over the boolean vector in chunks of 8.
         for chunk in value.chunks(8) {
             let mut byte: u8 = 0;
-            let mut bit_position: u8 = 0;
 
             // Iterate through each boolean in the current chunk.
-            for &value in chunk {
+            for (bit_position, &value) in chunk.iter().enumerate() {
                 if value {
                     // If the boolean is `true`, set the corresponding bit in the byte.
                     // We use a bitwise OR (`|=`) and left-shift a `1` to the
@@ -285,24 +646,116 @@ impl From<Vec<bool>> for TydiBinary {
                     // only a single bit set at the correct index.
                     byte |= 1 << bit_position;
                 }
-                // Move to the next bit position.
-                bit_position += 1;
             }
 
             // Push the completed byte to the result vector.
             packed_bytes.push(byte);
         }
-        TydiBinary { data: packed_bytes, len: bit_count }
+        TydiBinary::new(packed_bytes, bit_count)
+    }
+}
+
+impl<T: ToTydiBinary, const N: usize> ToTydiBinary for [T; N] {
+    fn to_tydi_binary(self, order: TydiBitOrder) -> TydiBinary {
+        let mut writer = TydiBitWriter::in_order(order);
+        self.into_iter().for_each(|e| { writer.write(e); });
+        writer.finish()
+    }
+}
+
+impl<T: ToTydiBinary, const N: usize> From<[T; N]> for TydiBinary {
+    fn from(value: [T; N]) -> Self {
+        value.to_tydi_binary(TydiBitOrder::PORTABLE)
     }
 }
 
-impl<T> FromTydiBinary for Vec<T> where T: FromTydiBinary {
+impl<T: FromTydiBinary + TydiLogical, const N: usize> FromTydiBinary for [T; N] {
     fn from_tydi_binary(value: TydiBinary) -> (Self, TydiBinary) {
-        let (val, bin2) = T::from_tydi_binary(value);
-        todo!();
+        Self::try_from_tydi_binary(value).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_from_tydi_binary(value: TydiBinary) -> Result<(Self, TydiBinary), TydiError> {
+        let mut reader = TydiBitReader::new(&value);
+        let items: Vec<T> = (0..N).map(|_| reader.try_read()).collect::<Result<_, _>>()?;
+        let items = items.try_into().unwrap_or_else(|_| unreachable!("exactly {} items were read", N));
+        Ok((items, reader.finish()))
     }
 }
 
+macro_rules! impl_tuple {
+    ($($t:ident $v:ident),+) => {
+        impl<$($t: ToTydiBinary),+> ToTydiBinary for ($($t,)+) {
+            fn to_tydi_binary(self, order: TydiBitOrder) -> TydiBinary {
+                let ($($v,)+) = self;
+                let mut writer = TydiBitWriter::in_order(order);
+                writer$(.write($v))+;
+                writer.finish()
+            }
+        }
+
+        impl<$($t: ToTydiBinary),+> From<($($t,)+)> for TydiBinary {
+            fn from(value: ($($t,)+)) -> Self {
+                value.to_tydi_binary(TydiBitOrder::PORTABLE)
+            }
+        }
+
+        impl<$($t: FromTydiBinary + TydiLogical),+> FromTydiBinary for ($($t,)+) {
+            fn from_tydi_binary(value: TydiBinary) -> (Self, TydiBinary) {
+                Self::try_from_tydi_binary(value).unwrap_or_else(|e| panic!("{}", e))
+            }
+
+            fn try_from_tydi_binary(value: TydiBinary) -> Result<(Self, TydiBinary), TydiError> {
+                let mut reader = TydiBitReader::new(&value);
+                Ok((($(reader.try_read::<$t>()?,)+), reader.finish()))
+            }
+        }
+    };
+}
+
+impl_tuple!(A a);
+impl_tuple!(A a, B b);
+impl_tuple!(A a, B b, C c);
+impl_tuple!(A a, B b, C c, D d);
+impl_tuple!(A a, B b, C c, D d, E e);
+impl_tuple!(A a, B b, C c, D d, E e, F f);
+impl_tuple!(A a, B b, C c, D d, E e, F f, G g);
+impl_tuple!(A a, B b, C c, D d, E e, F f, G g, H h);
+
+/// Number of bits of the length of a sequence of at most [max_len] items that is packed inline.
+pub fn bounded_length_width(max_len: usize) -> usize {
+    index_width(max_len + 1)
+}
+
+/// Packs [items] inline in [order], as their length followed by [max_len] items, of which those past the length are
+/// zero-filled. Panics if there are more than [max_len] items.
+///
+/// This is the only way a sequence is packed inline: a `Vec` does not implement [FromTydiBinary], as its bits do not
+/// tell where it ends, and is otherwise transferred over a stream of its own.
+pub fn pack_bounded<T>(items: Vec<T>, max_len: usize, order: TydiBitOrder) -> TydiBinary where T: ToTydiBinary + TydiLogical {
+    assert!(items.len() <= max_len, "{} items do not fit in a sequence of at most {}", items.len(), max_len);
+    let mut writer = TydiBitWriter::in_order(order);
+    writer.write(index_to_binary(items.len(), bounded_length_width(max_len)).in_order(order));
+    let padding = (max_len - items.len()) * T::logical_type().data_width();
+    items.into_iter().for_each(|e| { writer.write(e); });
+    writer.write_zeros(padding);
+    writer.finish()
+}
+
+/// Takes a sequence of at most [max_len] items that was packed by [pack_bounded] from [value].
+pub fn unpack_bounded<T>(value: TydiBinary, max_len: usize) -> (Vec<T>, TydiBinary) where T: FromTydiBinary + TydiLogical {
+    try_unpack_bounded(value, max_len).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Like [unpack_bounded], returning an error if [value] is too short or holds an item that is not a valid [T].
+pub fn try_unpack_bounded<T>(value: TydiBinary, max_len: usize) -> Result<(Vec<T>, TydiBinary), TydiError>
+    where T: FromTydiBinary + TydiLogical {
+    let mut reader = TydiBitReader::new(&value);
+    let length = index_from_binary(reader.try_read_binary(bounded_length_width(max_len))?).min(max_len);
+    let items = (0..length).map(|_| reader.try_read()).collect::<Result<_, _>>()?;
+    reader.try_skip((max_len - length) * T::logical_type().data_width())?;
+    Ok((items, reader.finish()))
+}
+
 impl From<TydiBinary> for Vec<bool> {
     fn from(value: TydiBinary) -> Self {
         let packed_bytes = &value.data;
@@ -336,26 +789,21 @@ impl From<TydiBinary> for Vec<bool> {
 
 #[cfg(test)]
 mod tests {
-    use crate::binary::TydiBinary;
+    use crate::binary::{pack_bounded, unpack_bounded, FromTydiBinary, ToTydiBinary, TydiBinary, TydiBitField};
+    use crate::order::{TydiBitOrder, TydiEndianness, TydiFieldOrder};
 
     #[test]
     fn test_binary_glue() {
-        let bin1 = TydiBinary {
-            data: vec![0b10101010, 0b11110000],
-            len: 16,
-        };
+        let bin1 = TydiBinary::new(vec![0b10101010, 0b11110000], 16);
         let string1 = bin1.to_string();
         assert_eq!(string1, "0b1111000010101010");
 
-        let bin2 = TydiBinary {
-            data: vec![0b10101010, 0b00001111], // 0xaa, 0x0f
-            len: 12,
-        };
+        let bin2 = TydiBinary::new(vec![0b10101010, 0b00001111], 12); // 0xaa, 0x0f
         let string2 = bin2.to_string();
         assert_eq!(string2, "0b111110101010");
 
-        let last_bin = TydiBinary { data: vec![0b101], len: 3 }; // Value = 5
-        let char_bin = TydiBinary { data: vec![0b01000011], len: 8 }; // Value = 67 or 0x43
+        let last_bin = TydiBinary::new(vec![0b101], 3); // Value = 5
+        let char_bin = TydiBinary::new(vec![0b01000011], 8); // Value = 67 or 0x43
         let package = last_bin.concatenate(&char_bin); // Expected value = 0x021D or [0x1D, 0x02]
         assert_eq!(package.data[0], 0x1D);
         assert_eq!(package.data[1], 0x02);
@@ -394,23 +842,38 @@ mod tests {
         let binary = TydiBinary::from(value);
 
         assert_eq!(binary.len, 64);
-        assert_eq!(binary.data, value.to_ne_bytes().to_vec());
+        assert_eq!(binary.data, value.to_le_bytes().to_vec());
 
-        let binary: TydiBinary = value.into();
+        let big = TydiBitOrder::new(TydiEndianness::Big, TydiFieldOrder::LsbFirst);
+        let binary = value.to_tydi_binary(big);
 
         assert_eq!(binary.len, 64);
-        assert_eq!(binary.data, value.to_ne_bytes().to_vec());
+        assert_eq!(binary.data, value.to_be_bytes().to_vec());
+        assert_eq!(u64::from(binary), value);
     }
 
     #[test]
     fn test_binary_from_f64() {
-        let value: f64 = 3.14159;
+        let value: f64 = std::f64::consts::PI;
         let binary = TydiBinary::from(value);
 
-        let val2 = true;
-
         assert_eq!(binary.len, 64);
-        assert_eq!(binary.data, value.to_ne_bytes().to_vec());
+        assert_eq!(binary.data, value.to_le_bytes().to_vec());
+
+        let big = TydiBitOrder::new(TydiEndianness::Big, TydiFieldOrder::LsbFirst);
+        let binary = value.to_tydi_binary(big);
+        assert_eq!(binary.data, value.to_be_bytes().to_vec());
+        assert_eq!(f64::from(binary), value);
+    }
+
+    #[test]
+    fn test_pointer_sized_as_64_bits() {
+        let binary = TydiBinary::from(usize::MAX);
+        assert_eq!(binary, TydiBinary::from(u64::MAX));
+        assert_eq!(usize::from_tydi_binary(binary).0, usize::MAX);
+        let binary = TydiBinary::from(-2isize);
+        assert_eq!(binary, TydiBinary::from(-2i64));
+        assert_eq!(isize::from_tydi_binary(binary).0, -2);
     }
 
     #[test]
@@ -418,7 +881,117 @@ mod tests {
         let value = 'm';
         let binary = TydiBinary::from(value);
 
-        assert_eq!(binary.len, 8);
-        // assert_eq!(binary.data, value.to_string().as_bytes().to_vec());
+        assert_eq!(binary.len, 32);
+        assert_eq!(binary.data, (value as u32).to_le_bytes().to_vec());
+        assert_eq!(char::from_tydi_binary(TydiBinary::from('€')).0, '€');
+    }
+
+    #[test]
+    fn test_option_packing() {
+        let some: TydiBinary = Some(0u16).into();
+        let none: TydiBinary = None::<u16>.into();
+        assert_eq!(some.len, 17);
+        assert_eq!(none.len, 17);
+        // Only the valid bit tells a zero value apart from no value
+        assert_ne!(some, none);
+
+        assert_eq!(Option::<u16>::from_tydi_binary(some).0, Some(0));
+        assert_eq!(Option::<u16>::from_tydi_binary(none).0, None);
+        let (value, res) = Option::<u32>::from_tydi_binary(Some(0xBEEFu32).into());
+        assert_eq!(value, Some(0xBEEF));
+        assert_eq!(res.len, 0);
+    }
+
+    #[test]
+    fn test_bit_field() {
+        let binary = 1000u32.to_bit_field(12, false, TydiBitOrder::PORTABLE);
+        assert_eq!(binary.len, 12);
+        assert_eq!(u32::from_bit_field(binary, 12, false).0, 1000);
+
+        let binary = (-5i32).to_bit_field(20, true, TydiBitOrder::PORTABLE);
+        assert_eq!(binary.to_string(), "0b11111111111111111011");
+        assert_eq!(i32::from_bit_field(binary.clone(), 20, true).0, -5);
+        // Without sign extension the same bits are a large positive number
+        assert_eq!(i32::from_bit_field(binary, 20, false).0, (1 << 20) - 5);
+
+        // Whole bytes follow the endianness like primitives, other widths stay little endian
+        let big = TydiBitOrder::new(TydiEndianness::Big, TydiFieldOrder::LsbFirst);
+        let (bytes, bits) = (0x1234u32.to_bit_field(16, false, big), 0x234u32.to_bit_field(12, false, big));
+        assert_eq!(bytes, 0x1234u16.to_tydi_binary(big));
+        assert_eq!(bytes.data, vec![0x12, 0x34]);
+        assert_eq!(bits.data, vec![0x34, 0x02]);
+        assert_eq!(u32::from_bit_field(bytes, 16, false).0, 0x1234);
+        assert_eq!(u32::from_bit_field(bits, 12, false).0, 0x234);
+    }
+
+    #[test]
+    #[should_panic(expected = "does not fit in 4 unsigned bits")]
+    fn test_bit_field_range() {
+        16u8.to_bit_field(4, false, TydiBitOrder::PORTABLE);
+    }
+
+    #[test]
+    fn test_inline_packing() {
+        let hash = [0xDEu8, 0xAD, 0xBE, 0xEF];
+        let binary: TydiBinary = hash.into();
+        assert_eq!(binary.len, 32);
+        assert_eq!(<[u8; 4]>::from_tydi_binary(binary).0, hash);
+
+        let coordinates = (-3i16, 7i16, true);
+        let binary: TydiBinary = coordinates.into();
+        assert_eq!(binary.len, 16 + 16 + 1);
+        assert_eq!(<(i16, i16, bool)>::from_tydi_binary(binary).0, coordinates);
+
+        let bounded = pack_bounded(vec![1u8, 2], 3, TydiBitOrder::PORTABLE);
+        // Two length bits to count up to three, followed by three items
+        assert_eq!(bounded.len, 2 + 3 * 8);
+        let (items, res) = unpack_bounded::<u8>(bounded.clone(), 3);
+        assert_eq!(items, vec![1, 2]);
+        assert_eq!(res.len, 0);
+        // The length delimits the items, so fields after them are unpacked as well
+        let binary = bounded.concatenate(&TydiBinary::from(9u8));
+        let (items, res) = unpack_bounded::<u8>(binary, 3);
+        assert_eq!((items, u8::from_tydi_binary(res).0), (vec![1, 2], 9));
+    }
+
+    /// Bits of a simple pseudo-random sequence, to compare word-level operations against.
+    fn reference_bits(len: usize, seed: u64) -> Vec<bool> {
+        let mut state = seed;
+        (0..len).map(|_| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            state >> 63 == 1
+        }).collect()
+    }
+
+    #[test]
+    fn test_append_and_split() {
+        for (len1, len2) in [(0, 13), (3, 0), (5, 70), (8, 64), (13, 131), (67, 9), (64, 64)] {
+            let bits1 = reference_bits(len1, len1 as u64);
+            let bits2 = reference_bits(len2, len2 as u64 + 1);
+            let joined = [bits1.clone(), bits2.clone()].concat();
+
+            let mut binary: TydiBinary = bits1.clone().into();
+            binary.append(&bits2.clone().into());
+            assert_eq!(binary, TydiBinary::from(joined.clone()));
+
+            let (first, second) = binary.split(len1);
+            assert_eq!(Vec::<bool>::from(first), bits1);
+            assert_eq!(Vec::<bool>::from(second), bits2);
+        }
+    }
+
+    #[test]
+    fn test_bit_slice() {
+        let bits = reference_bits(150, 7);
+        let binary: TydiBinary = bits.clone().into();
+        let (_, slice) = binary.as_bits().split_at(11);
+        let (slice, _) = slice.split_at(100);
+        assert_eq!(slice.len(), 100);
+        assert!((0..100).all(|i| slice.get(i) == bits[11 + i]));
+
+        let expected = (0..64).rev().fold(0u64, |acc, i| (acc << 1) | bits[11 + 5 + i] as u64);
+        assert_eq!(slice.read_bits(5, 64), expected);
+        assert_eq!(slice.read_bits(5, 3), expected & 0b111);
+        assert_eq!(Vec::<bool>::from(slice.skip(37).to_binary()), bits[48..111].to_vec());
     }
 }
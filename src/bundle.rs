@@ -0,0 +1,704 @@
+use crate::binary::{FromTydiBinary, ToTydiBinary, TydiBinary};
+use crate::complexity::TydiComplexity;
+use crate::drilling::{packets_from_binaries, TydiPacktestToBinary};
+use crate::logical::{TydiLogical, TydiLogicalType, TydiPhysicalStream};
+use crate::order::TydiBitOrder;
+use crate::{TydiBinaryStream, TydiPacket, TydiStream};
+
+/// Types that can be split into the physical streams of their sequence fields, and recreated from them.
+///
+/// Implemented by `#[derive(Tydi)]` for structs, and by [impl_tydi_leaf] for types without sequences.
+pub trait TydiDrill: Sized + Clone + TydiLogical {
+    /// Child streams of a stream of this type.
+    type Streams;
+    /// Binary form of [TydiDrill::Streams].
+    type BinaryStreams;
+
+    /// Creates the child streams of a stream of this type. Only the items that end up in the child streams are
+    /// cloned, each of them once, see [TydiDrill::without_streams].
+    fn drill_streams(stream: &TydiStream<&Self>) -> Self::Streams;
+    /// Clones the part of this value that is carried by its own stream, leaving the sequences that are drilled into
+    /// child streams empty.
+    fn without_streams(&self) -> Self;
+    /// Replaces the contents of the sequences in [stream] with the data from the child streams.
+    fn inject_streams(stream: &mut TydiStream<Self>, streams: Self::Streams);
+    /// Packs the child streams in [order], with packets that follow the rules of complexity [c].
+    fn finish_streams(streams: &Self::Streams, c: TydiComplexity, order: TydiBitOrder) -> Self::BinaryStreams;
+    /// Decodes the child streams of a stream of this type with dimensionality [dim].
+    fn streams_from_binary(binary: Self::BinaryStreams, dim: usize) -> Self::Streams;
+    /// Appends the binary child streams that carry data to [result], in the order of the physical streams returned by
+    /// [crate::logical::TydiLogicalType::split].
+    fn collect_binary_streams<'a>(binary: &'a Self::BinaryStreams, result: &mut Vec<&'a TydiBinaryStream>);
+    /// Takes the binary child streams from [streams] in the order of [TydiDrill::collect_binary_streams].
+    fn take_binary_streams<I: Iterator<Item = TydiBinaryStream>>(streams: &mut I) -> Self::BinaryStreams;
+
+    /// Binary form of a stream of this type at complexity [c] in [order], or `None` if this type carries no data of its
+    /// own in the stream.
+    fn finish_items(items: &TydiStream<Self>, c: TydiComplexity, order: TydiBitOrder) -> Option<TydiBinaryStream>;
+    /// Decodes a stream of this type with dimensionality [dim], recreating it from its child [streams] if it has no
+    /// binary form. Sequences in the stream are left empty.
+    fn items_from_binary(items: Option<TydiBinaryStream>, streams: &Self::Streams, dim: usize) -> TydiStream<Self>;
+
+    /// A value with empty sequences that stands in for an absent value, such as an option that is `None`, in the child
+    /// streams.
+    fn placeholder() -> Self;
+}
+
+/// Implements [TydiDrill] for types without sequences, which pack into a single binary.
+///
+/// Generic types are given as `impl_tydi_leaf!([T] Wrapper<T> where [T: Bounds])`.
+#[macro_export]
+macro_rules! impl_tydi_leaf {
+    ([$($generics:tt)*] $t:ty where [$($bounds:tt)*]) => {
+        impl<$($generics)*> $crate::bundle::TydiDrill for $t where $($bounds)* {
+            type Streams = ();
+            type BinaryStreams = ();
+
+            fn drill_streams(_stream: &$crate::TydiStream<&Self>) -> Self::Streams {}
+
+            fn without_streams(&self) -> Self {
+                self.clone()
+            }
+
+            fn inject_streams(_stream: &mut $crate::TydiStream<Self>, _streams: Self::Streams) {}
+            fn finish_streams(_streams: &Self::Streams, _c: $crate::complexity::TydiComplexity, _order: $crate::order::TydiBitOrder) -> Self::BinaryStreams {}
+            fn streams_from_binary(_binary: Self::BinaryStreams, _dim: usize) -> Self::Streams {}
+            fn collect_binary_streams<'a>(_binary: &'a Self::BinaryStreams, _result: &mut Vec<&'a $crate::TydiBinaryStream>) {}
+            fn take_binary_streams<I: Iterator<Item = $crate::TydiBinaryStream>>(_streams: &mut I) -> Self::BinaryStreams {}
+
+            fn finish_items(items: &$crate::TydiStream<Self>, c: $crate::complexity::TydiComplexity, order: $crate::order::TydiBitOrder)
+                -> Option<$crate::TydiBinaryStream> {
+                $crate::bundle::finish_data_items(items, c, order)
+            }
+
+            fn items_from_binary(items: Option<$crate::TydiBinaryStream>, _streams: &Self::Streams, dim: usize) -> $crate::TydiStream<Self> {
+                $crate::bundle::data_items_from_binary(items, dim)
+            }
+
+            fn placeholder() -> Self {
+                $crate::bundle::data_placeholder()
+            }
+        }
+    };
+    ($($t:ty),*) => {
+        $(
+            $crate::impl_tydi_leaf!([] $t where []);
+        )*
+    };
+}
+
+impl_tydi_leaf!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, bool, char);
+
+// Arrays and tuples are packed inline
+impl_tydi_leaf!([T, const N: usize] [T; N] where [T: TydiLogical + ToTydiBinary + FromTydiBinary + Clone]);
+
+macro_rules! impl_tydi_tuple {
+    ($($t:ident),+) => {
+        impl_tydi_leaf!([$($t),+] ($($t,)+) where [$($t: TydiLogical + ToTydiBinary + FromTydiBinary + Clone),+]);
+    };
+}
+
+impl_tydi_tuple!(A);
+impl_tydi_tuple!(A, B);
+impl_tydi_tuple!(A, B, C);
+impl_tydi_tuple!(A, B, C, D);
+impl_tydi_tuple!(A, B, C, D, E);
+impl_tydi_tuple!(A, B, C, D, E, F);
+impl_tydi_tuple!(A, B, C, D, E, F, G);
+impl_tydi_tuple!(A, B, C, D, E, F, G, H);
+
+/// [TydiDrill::finish_items] for types that carry data, packed at the width of their logical type.
+pub fn finish_data_items<T>(items: &TydiStream<T>, c: TydiComplexity, order: TydiBitOrder) -> Option<TydiBinaryStream>
+where
+    T: TydiLogical + ToTydiBinary + Clone
+{
+    Some(items.finish_with(T::logical_type().data_width(), c, order))
+}
+
+/// [TydiDrill::items_from_binary] for types that carry data.
+pub fn data_items_from_binary<T: FromTydiBinary>(items: Option<TydiBinaryStream>, dim: usize) -> TydiStream<T> {
+    packets_from_binaries(items.expect("a stream of a type that carries data should have a binary form"), dim)
+}
+
+/// [TydiDrill::placeholder] for types that carry data, unpacked from all zero bits. Sequences are left empty when
+/// unpacking.
+pub fn data_placeholder<T: TydiLogical + FromTydiBinary>() -> T {
+    T::from_tydi_binary(TydiBinary::zeros(T::logical_type().data_width())).0
+}
+
+impl TydiDrill for String {
+    type Streams = TydiStream<u8>;
+    type BinaryStreams = TydiBinaryStream;
+
+    fn drill_streams(stream: &TydiStream<&Self>) -> Self::Streams {
+        stream.drill_iter(|e| e.bytes()).collect()
+    }
+
+    fn without_streams(&self) -> Self {
+        String::new()
+    }
+
+    fn inject_streams(stream: &mut TydiStream<Self>, streams: Self::Streams) {
+        stream.inject_string(|e| { e.clear(); e }, streams);
+    }
+
+    fn finish_streams(streams: &Self::Streams, c: TydiComplexity, order: TydiBitOrder) -> Self::BinaryStreams {
+        streams.finish_with(u8::logical_type().data_width(), c, order)
+    }
+
+    fn streams_from_binary(binary: Self::BinaryStreams, dim: usize) -> Self::Streams {
+        packets_from_binaries(binary, dim + 1)
+    }
+
+    fn collect_binary_streams<'a>(binary: &'a Self::BinaryStreams, result: &mut Vec<&'a TydiBinaryStream>) {
+        result.push(binary);
+    }
+
+    fn take_binary_streams<I: Iterator<Item = TydiBinaryStream>>(streams: &mut I) -> Self::BinaryStreams {
+        streams.next().expect("missing the binary stream of a string")
+    }
+
+    fn finish_items(_items: &TydiStream<Self>, _c: TydiComplexity, _order: TydiBitOrder) -> Option<TydiBinaryStream> {
+        None
+    }
+
+    fn items_from_binary(_items: Option<TydiBinaryStream>, streams: &Self::Streams, _dim: usize) -> TydiStream<Self> {
+        let strings = TydiStream(streams.0.clone()).vectorize_inner();
+        TydiStream(strings.0.into_iter().map(|el| el.map_data(|_| String::new())).collect())
+    }
+
+    fn placeholder() -> Self {
+        String::new()
+    }
+}
+
+/// Child streams of a sequence, the stream of its items followed by the child streams of those items.
+pub struct TydiSequenceStreams<B: TydiDrill> {
+    pub items: TydiStream<B>,
+    pub children: B::Streams,
+}
+
+/// Binary form of [TydiSequenceStreams].
+pub struct TydiSequenceBinaryStreams<B: TydiDrill> {
+    /// `None` if the items carry no data of their own, such as strings.
+    pub items: Option<TydiBinaryStream>,
+    pub children: B::BinaryStreams,
+}
+
+impl<B: TydiDrill> TydiDrill for Vec<B> {
+    type Streams = TydiSequenceStreams<B>;
+    type BinaryStreams = TydiSequenceBinaryStreams<B>;
+
+    fn drill_streams(stream: &TydiStream<&Self>) -> Self::Streams {
+        let borrowed: TydiStream<&B> = stream.drill_iter(|e| e.iter()).collect();
+        let children = B::drill_streams(&borrowed);
+        let items = borrowed.project(|e| e.without_streams());
+        TydiSequenceStreams { items, children }
+    }
+
+    fn without_streams(&self) -> Self {
+        Vec::new()
+    }
+
+    fn inject_streams(stream: &mut TydiStream<Self>, streams: Self::Streams) {
+        let mut items = streams.items;
+        B::inject_streams(&mut items, streams.children);
+        stream.inject(|e| { e.clear(); e }, items);
+    }
+
+    fn finish_streams(streams: &Self::Streams, c: TydiComplexity, order: TydiBitOrder) -> Self::BinaryStreams {
+        TydiSequenceBinaryStreams {
+            items: B::finish_items(&streams.items, c, order),
+            children: B::finish_streams(&streams.children, c, order),
+        }
+    }
+
+    fn streams_from_binary(binary: Self::BinaryStreams, dim: usize) -> Self::Streams {
+        let children = B::streams_from_binary(binary.children, dim + 1);
+        let items = B::items_from_binary(binary.items, &children, dim + 1);
+        TydiSequenceStreams { items, children }
+    }
+
+    fn collect_binary_streams<'a>(binary: &'a Self::BinaryStreams, result: &mut Vec<&'a TydiBinaryStream>) {
+        // Items without data of their own do not get a physical stream
+        if let Some(items) = &binary.items && B::logical_type().data_width() > 0 {
+            result.push(items);
+        }
+        B::collect_binary_streams(&binary.children, result);
+    }
+
+    fn take_binary_streams<I: Iterator<Item = TydiBinaryStream>>(streams: &mut I) -> Self::BinaryStreams {
+        let items = if B::logical_type().data_width() > 0 {
+            Some(streams.next().expect("missing the binary stream of the items of a sequence"))
+        } else {
+            None
+        };
+        TydiSequenceBinaryStreams { items, children: B::take_binary_streams(streams) }
+    }
+
+    fn finish_items(_items: &TydiStream<Self>, _c: TydiComplexity, _order: TydiBitOrder) -> Option<TydiBinaryStream> {
+        None
+    }
+
+    fn items_from_binary(_items: Option<TydiBinaryStream>, streams: &Self::Streams, _dim: usize) -> TydiStream<Self> {
+        let sequences = TydiStream(streams.items.0.clone()).vectorize_inner();
+        TydiStream(sequences.0.into_iter().map(|el| el.map_data(|_| Vec::new())).collect())
+    }
+
+    fn placeholder() -> Self {
+        Vec::new()
+    }
+}
+
+/// Options share the child streams of [S], in which an absent value is an empty sequence. They are told apart from
+/// present, empty, values by the valid bit in the parent stream.
+impl<S: TydiDrill> TydiDrill for Option<S> {
+    type Streams = S::Streams;
+    type BinaryStreams = S::BinaryStreams;
+
+    fn drill_streams(stream: &TydiStream<&Self>) -> Self::Streams {
+        let absent = S::placeholder();
+        S::drill_streams(&stream.project(|e| e.as_ref().unwrap_or(&absent)))
+    }
+
+    fn without_streams(&self) -> Self {
+        self.as_ref().map(S::without_streams)
+    }
+
+    fn inject_streams(stream: &mut TydiStream<Self>, streams: Self::Streams) {
+        let mut values = stream.project(|e| e.clone().unwrap_or_else(S::placeholder));
+        S::inject_streams(&mut values, streams);
+        for (el, value) in stream.0.iter_mut().zip(values.0) {
+            if let (Some(Some(data)), Some(value)) = (el.data.as_mut(), value.data) {
+                *data = value;
+            }
+        }
+    }
+
+    fn finish_streams(streams: &Self::Streams, c: TydiComplexity, order: TydiBitOrder) -> Self::BinaryStreams {
+        S::finish_streams(streams, c, order)
+    }
+
+    fn streams_from_binary(binary: Self::BinaryStreams, dim: usize) -> Self::Streams {
+        S::streams_from_binary(binary, dim)
+    }
+
+    fn collect_binary_streams<'a>(binary: &'a Self::BinaryStreams, result: &mut Vec<&'a TydiBinaryStream>) {
+        S::collect_binary_streams(binary, result);
+    }
+
+    fn take_binary_streams<I: Iterator<Item = TydiBinaryStream>>(streams: &mut I) -> Self::BinaryStreams {
+        S::take_binary_streams(streams)
+    }
+
+    fn finish_items(items: &TydiStream<Self>, c: TydiComplexity, order: TydiBitOrder) -> Option<TydiBinaryStream> {
+        let valid = items.project(|e| e.is_some()).finish_with(1, c, order);
+        let Some(values) = S::finish_items(&items.project(|e| e.clone().unwrap_or_else(S::placeholder)), c, order) else {
+            return Some(valid);
+        };
+        // Insert the valid bit between the strobe and last bits and the data of the value
+        Some(TydiBinaryStream(valid.0.iter().zip(values.0).map(|(valid, value)| {
+            let (_, data) = value.split(valid.len - 1);
+            valid.concatenate(&data)
+        }).collect()))
+    }
+
+    fn items_from_binary(items: Option<TydiBinaryStream>, streams: &Self::Streams, dim: usize) -> TydiStream<Self> {
+        let items = items.expect("a stream of options should have a binary form");
+        let (valid, values): (Vec<_>, Vec<_>) = items.0.iter().map(|el| {
+            let (header, res) = el.split(1 + dim);
+            let (valid, data) = res.split(1);
+            (header.concatenate(&valid), header.concatenate(&data))
+        }).unzip();
+        let valid: TydiStream<bool> = packets_from_binaries(TydiBinaryStream(valid), dim);
+        let values = S::items_from_binary(Some(TydiBinaryStream(values)), streams, dim);
+        TydiStream(valid.0.into_iter().zip(values.0).map(|(valid, value)| TydiPacket {
+            data: valid.data.map(|valid| value.data.filter(|_| valid)),
+            last: valid.last,
+        }).collect())
+    }
+
+    fn placeholder() -> Self {
+        None
+    }
+}
+
+/// All physical streams of a sequence of [T], the stream of the sequence itself and the child streams.
+pub struct TydiBundle<T: TydiDrill> {
+    pub root: TydiStream<T>,
+    pub streams: T::Streams,
+}
+
+/// Binary form of a [TydiBundle].
+pub struct TydiBinaryBundle<T: TydiDrill> {
+    pub root: Option<TydiBinaryStream>,
+    pub streams: T::BinaryStreams,
+}
+
+impl<T: TydiDrill> TydiBundle<T> {
+    pub fn new(items: Vec<T>) -> Self {
+        let root = TydiStream::from(items);
+        let streams = T::drill_streams(&root.as_refs());
+        Self { root, streams }
+    }
+
+    /// Recreates the original items.
+    pub fn reverse(self) -> Vec<T> {
+        let mut root = self.root;
+        T::inject_streams(&mut root, self.streams);
+        root.unpack()
+    }
+
+    /// Packs the streams in [TydiBitOrder::PORTABLE], keeping their packets as they are.
+    pub fn finish(&self) -> TydiBinaryBundle<T> {
+        self.finish_with(TydiComplexity::C8, TydiBitOrder::PORTABLE)
+    }
+
+    /// Packs the streams in [order], with packets that follow the rules of complexity [c].
+    pub fn finish_with(&self, c: TydiComplexity, order: TydiBitOrder) -> TydiBinaryBundle<T> {
+        TydiBinaryBundle {
+            root: T::finish_items(&self.root, c, order),
+            streams: T::finish_streams(&self.streams, c, order),
+        }
+    }
+}
+
+impl<T: TydiDrill> TydiBinaryBundle<T> {
+    /// Decodes the binaries into typed streams.
+    pub fn reverse(self) -> TydiBundle<T> {
+        let streams = T::streams_from_binary(self.streams, 1);
+        let root = T::items_from_binary(self.root, &streams, 1);
+        TydiBundle { root, streams }
+    }
+
+    /// The physical streams of a sequence of [T] named [name], each along with its binary.
+    pub fn physical_streams(&self, name: &str) -> Vec<(TydiPhysicalStream, &TydiBinaryStream)> {
+        let physical = TydiLogicalType::stream(T::logical_type(), 1).split(name);
+        let mut binaries = Vec::new();
+        if let Some(root) = &self.root && T::logical_type().data_width() > 0 {
+            binaries.push(root);
+        }
+        T::collect_binary_streams(&self.streams, &mut binaries);
+        assert_eq!(physical.len(), binaries.len(), "the binary streams do not match the physical streams of the type");
+        physical.into_iter().zip(binaries).collect()
+    }
+
+    /// Recreates the binary bundle from the binaries of its physical streams, in the order of
+    /// [TydiBinaryBundle::physical_streams].
+    pub fn from_physical_streams(streams: Vec<TydiBinaryStream>) -> Self {
+        let mut streams = streams.into_iter();
+        let root = if T::logical_type().data_width() > 0 { streams.next() } else { None };
+        let result = Self { root, streams: T::take_binary_streams(&mut streams) };
+        assert!(streams.next().is_none(), "more binary streams than physical streams of the type");
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::order::{TydiBitOrder, TydiEndianness, TydiFieldOrder};
+    use crate::Tydi;
+
+    #[derive(Debug, PartialEq, Eq, Clone, Tydi)]
+    pub struct Author {
+        user_id: u32,
+        username: String,
+    }
+
+    #[derive(Debug, PartialEq, Eq, Clone, Tydi)]
+    pub struct Comment {
+        comment_id: u32,
+        author: Author,
+        content: String,
+        in_reply_to: Option<u32>,
+    }
+
+    #[derive(Debug, PartialEq, Eq, Clone, Tydi)]
+    pub struct Post {
+        post_id: u32,
+        title: String,
+        subtitle: Option<String>,
+        author: Author,
+        tags: Vec<String>,
+        scores: Vec<u16>,
+        comments: Vec<Comment>,
+    }
+
+    fn posts() -> Vec<Post> {
+        vec![
+            Post {
+                post_id: 1,
+                title: "Exploring Andalusia".into(),
+                subtitle: None,
+                author: Author { user_id: 456, username: "TravelBug_88".into() },
+                tags: vec!["Travel".into(), "".into(), "Spain".into()],
+                scores: vec![3, 4],
+                comments: vec![
+                    Comment { comment_id: 1, author: Author { user_id: 789, username: "CultureVulture".into() }, content: "Magical!".into(), in_reply_to: None },
+                    Comment { comment_id: 2, author: Author { user_id: 456, username: "".into() }, content: "".into(), in_reply_to: Some(0) },
+                ],
+            },
+            Post {
+                post_id: 2,
+                title: "".into(),
+                subtitle: Some("".into()),
+                author: Author { user_id: 654, username: "CodeNewbie".into() },
+                tags: vec![],
+                scores: vec![],
+                comments: vec![],
+            },
+            Post {
+                post_id: 3,
+                title: "Rust".into(),
+                subtitle: Some("Ferris".into()),
+                author: Author { user_id: 1, username: "Ferris".into() },
+                tags: vec!["Programming".into()],
+                scores: vec![5],
+                comments: vec![
+                    Comment { comment_id: 3, author: Author { user_id: 2, username: "Corro".into() }, content: "Nice".into(), in_reply_to: None },
+                ],
+            },
+        ]
+    }
+
+    #[test]
+    fn test_bundle_roundtrip() {
+        let bundle = TydiBundle::new(posts());
+        assert_eq!(bundle.streams.title.0.len(), "Exploring Andalusia".len() + 1 + "Rust".len());
+        assert_eq!(bundle.streams.comments.items.0.len(), 4);
+        assert_eq!(bundle.reverse(), posts());
+    }
+
+    /// Has no `Default`, so absent reviews are stood in for by [TydiDrill::placeholder]
+    #[derive(Debug, PartialEq, Eq, Clone, Tydi)]
+    pub struct Review {
+        score: u8,
+        remarks: Vec<String>,
+    }
+
+    #[derive(Debug, PartialEq, Eq, Clone, Tydi)]
+    pub struct Draft {
+        draft_id: u32,
+        review: Option<Review>,
+    }
+
+    #[test]
+    fn test_option_without_default() {
+        let drafts = vec![
+            Draft { draft_id: 1, review: None },
+            Draft { draft_id: 2, review: Some(Review { score: 4, remarks: vec!["Short".into(), "".into()] }) },
+            Draft { draft_id: 3, review: Some(Review { score: 0, remarks: vec![] }) },
+        ];
+        assert_eq!(TydiBundle::new(drafts.clone()).reverse(), drafts);
+        assert_eq!(TydiBundle::new(drafts.clone()).finish().reverse().reverse(), drafts);
+    }
+
+    #[test]
+    fn test_binary_bundle_roundtrip() {
+        let binary = TydiBundle::new(posts()).finish();
+        let root = binary.root.as_ref().unwrap();
+        assert_eq!(root.0.len(), 3);
+        // The subtitle only adds its valid bit to the root stream
+        assert_eq!(root.0[0].len, 1 + 1 + 32 + 1 + 32);
+        // Absent subtitles are transferred as empty sequences, just like empty ones
+        assert_eq!(binary.streams.subtitle.0.len(), 1 + 1 + "Ferris".len());
+        // Strings carry no data of their own, only their characters are transferred
+        assert!(binary.streams.tags.items.is_none());
+        assert_eq!(binary.streams.tags.children.0[0].len, 1 + 3 + 8);
+        assert_eq!(binary.streams.comments.children.author.username.0[0].len, 1 + 3 + 8);
+
+        let physical = binary.physical_streams("posts");
+        let names: Vec<String> = physical.iter().map(|(stream, _)| stream.name()).collect();
+        assert_eq!(names, vec![
+            "posts", "posts__title", "posts__subtitle__some", "posts__author__username", "posts__tags", "posts__scores",
+            "posts__comments", "posts__comments__author__username", "posts__comments__content",
+        ]);
+        for (stream, binaries) in &physical {
+            assert_eq!(binaries.0[0].len, 1 + stream.dimensionality + stream.data_width(), "{}", stream.name());
+        }
+
+        assert_eq!(binary.reverse().reverse(), posts());
+
+        // The binaries are unpacked in the order they were packed in
+        let big_msb_first = TydiBitOrder::new(TydiEndianness::Big, TydiFieldOrder::MsbFirst);
+        let binary = TydiBundle::new(posts()).finish_with(TydiComplexity::C8, big_msb_first);
+        assert_eq!(binary.reverse().reverse(), posts());
+    }
+
+    #[test]
+    fn test_finish_with_complexity() {
+        // The closing last flags of the items are postponed to a packet without data, which requires C4
+        let items = TydiStream(vec![
+            TydiPacket { data: Some(1u8), last: vec![false, false] },
+            TydiPacket { data: Some(2u8), last: vec![false, false] },
+            TydiPacket { data: None, last: vec![true, true] },
+        ]);
+        let root = TydiStream(vec![TydiPacket { data: Some(vec![]), last: vec![true] }]);
+        let bundle: TydiBundle<Vec<u8>> = TydiBundle { root, streams: TydiSequenceStreams { items, children: () } };
+
+        let postponed = bundle.finish();
+        assert_eq!(postponed.streams.items.as_ref().unwrap().0.len(), 3);
+        let normalized = bundle.finish_with(TydiComplexity::C1, TydiBitOrder::PORTABLE);
+        let binaries = normalized.streams.items.as_ref().unwrap();
+        assert_eq!(binaries.0.len(), 2);
+        let packets: TydiStream<u8> = packets_from_binaries(TydiBinaryStream(binaries.0.clone()), 2);
+        assert_eq!(packets.validate(TydiComplexity::C1), Ok(()));
+
+        assert_eq!(postponed.reverse().reverse(), vec![vec![1, 2]]);
+        assert_eq!(normalized.reverse().reverse(), vec![vec![1, 2]]);
+    }
+
+    /// The parameters only appear in fields without child streams, so the generated streams do not use them otherwise
+    #[derive(Debug, PartialEq, Eq, Clone, Tydi)]
+    pub struct Window<T: TydiLogical + ToTydiBinary + FromTydiBinary + Clone, const N: usize> {
+        #[tydi(max_len = 4)]
+        samples: Vec<T>,
+        offsets: [u8; N],
+        label: String,
+    }
+
+    #[derive(Debug, PartialEq, Eq, Clone, Tydi)]
+    pub enum Sample<T> where T: TydiLogical + ToTydiBinary + FromTydiBinary + Clone {
+        Missing,
+        Values(#[tydi(max_len = 2)] Vec<T>),
+    }
+
+    #[test]
+    fn test_generic_bundle_roundtrip() {
+        let windows = vec![
+            Window { samples: vec![1u16, 2], offsets: [3, 4, 5], label: "first".into() },
+            Window { samples: vec![], offsets: [0; 3], label: "".into() },
+        ];
+        assert_eq!(Window::<u16, 3>::logical_type().data_width(), 3 + 4 * 16 + 3 * 8);
+        assert_eq!(TydiBundle::new(windows.clone()).finish().reverse().reverse(), windows);
+
+        let samples = vec![Sample::Values(vec![7u8]), Sample::Missing];
+        assert_eq!(TydiBundle::new(samples.clone()).finish().reverse().reverse(), samples);
+    }
+
+    #[derive(Debug, PartialEq, Eq, Clone, Tydi)]
+    pub enum Event {
+        Ping,
+        Liked(u32),
+        Tagged(u32, Vec<String>),
+        Commented { post_id: u32, author: Author, content: String },
+    }
+
+    fn events() -> Vec<Event> {
+        vec![
+            Event::Tagged(1, vec!["Travel".into(), "Spain".into()]),
+            Event::Ping,
+            Event::Commented { post_id: 1, author: Author { user_id: 2, username: "Corro".into() }, content: "Nice".into() },
+            Event::Liked(7),
+            Event::Tagged(2, vec![]),
+        ]
+    }
+
+    #[test]
+    fn test_union_bundle_roundtrip() {
+        // Two tag bits, followed by the post id and user id of the largest variant
+        assert_eq!(Event::logical_type().data_width(), 2 + 32 + 32);
+        let ping: TydiBinary = Event::Ping.into();
+        assert_eq!(ping.len, 66);
+        assert_eq!(Event::from_tydi_binary(ping).0, Event::Ping);
+
+        let bundle = TydiBundle::new(events());
+        // Inactive variants transfer empty sequences
+        assert_eq!(bundle.streams.tagged_1.items.0.len(), 2 + 3 + 1);
+        assert_eq!(bundle.streams.commented_content.0.len(), 4 + 4);
+        assert_eq!(bundle.reverse(), events());
+
+        let binary = TydiBundle::new(events()).finish();
+        let names: Vec<String> = binary.physical_streams("events").iter().map(|(stream, _)| stream.name()).collect();
+        assert_eq!(names, vec![
+            "events", "events__Tagged__field_1", "events__Commented__author__username", "events__Commented__content",
+        ]);
+        assert_eq!(binary.reverse().reverse(), events());
+    }
+
+    /// Has fields named as the locals of the generated code
+    #[derive(Debug, PartialEq, Eq, Clone, Tydi)]
+    pub enum Frame {
+        Idle,
+        Data { order: u8, payload: u16, binary: bool, reader: u8, tag: Vec<String> },
+    }
+
+    #[test]
+    fn test_union_fields_named_as_generated_locals() {
+        let frames = vec![
+            Frame::Data { order: 1, payload: 0xBEEF, binary: true, reader: 2, tag: vec!["a".into()] },
+            Frame::Idle,
+        ];
+        let data = Frame::Data { order: 1, payload: 0xBEEF, binary: true, reader: 2, tag: vec![] };
+        let big_msb_first = TydiBitOrder::new(TydiEndianness::Big, TydiFieldOrder::MsbFirst);
+        assert_eq!(Frame::from_tydi_binary(data.clone().to_tydi_binary(big_msb_first)).0, data);
+        assert_eq!(TydiBundle::new(frames.clone()).finish().reverse().reverse(), frames);
+    }
+
+    thread_local! {
+        static CLONES: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    }
+
+    /// A leaf that counts how often it is cloned.
+    #[derive(Debug, PartialEq, Eq)]
+    pub struct Counted(u8);
+
+    impl Clone for Counted {
+        fn clone(&self) -> Self {
+            CLONES.with(|clones| clones.set(clones.get() + 1));
+            Counted(self.0)
+        }
+    }
+
+    impl TydiLogical for Counted {
+        fn logical_type() -> TydiLogicalType {
+            u8::logical_type()
+        }
+    }
+
+    impl ToTydiBinary for Counted {
+        fn to_tydi_binary(self, order: TydiBitOrder) -> TydiBinary {
+            self.0.to_tydi_binary(order)
+        }
+    }
+
+    impl FromTydiBinary for Counted {
+        fn from_tydi_binary(value: TydiBinary) -> (Self, TydiBinary) {
+            let (value, res) = u8::from_tydi_binary(value);
+            (Counted(value), res)
+        }
+    }
+
+    impl_tydi_leaf!(Counted);
+
+    #[derive(Debug, PartialEq, Eq, Clone, Tydi)]
+    pub struct Reply {
+        author: Counted,
+        reactions: Vec<Counted>,
+    }
+
+    #[derive(Debug, PartialEq, Eq, Clone, Tydi)]
+    pub struct Thread {
+        id: Counted,
+        replies: Vec<Reply>,
+    }
+
+    #[test]
+    fn test_drill_clones_once() {
+        let threads = vec![Thread {
+            id: Counted(1),
+            replies: vec![
+                Reply { author: Counted(2), reactions: vec![Counted(3), Counted(4)] },
+                Reply { author: Counted(5), reactions: vec![] },
+            ],
+        }];
+        let expected = threads.clone();
+        CLONES.with(|clones| clones.set(0));
+        let bundle = TydiBundle::new(threads);
+        // The roots are moved, and every nested item is cloned once into the stream that carries it
+        assert_eq!(CLONES.with(|clones| clones.get()), 2 + 2);
+        assert_eq!(bundle.streams.replies.items.0[0].data, Some(Reply { author: Counted(2), reactions: vec![] }));
+        assert_eq!(bundle.finish().reverse().reverse(), expected);
+    }
+}
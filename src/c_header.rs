@@ -0,0 +1,304 @@
+use std::fmt::Write;
+use crate::layout::{extended, join, leaves, place, stream_paths};
+use crate::logical::{TydiLogical, TydiPhysicalStream};
+use crate::order::{TydiBitOrder, TydiEndianness, TydiFieldOrder};
+use crate::vcd::field_widths;
+
+/// Keywords of C and C++ that are likely to clash with field names.
+const RESERVED: &[&str] = &[
+    "auto", "bool", "break", "case", "catch", "char", "class", "const", "continue", "default", "delete", "do",
+    "double", "else", "enum", "explicit", "extern", "false", "float", "for", "friend", "goto", "if", "inline", "int",
+    "long", "namespace", "new", "operator", "private", "protected", "public", "register", "restrict", "return",
+    "short", "signed", "sizeof", "static", "struct", "switch", "template", "this", "throw", "true", "try", "typedef",
+    "typename", "union", "unsigned", "using", "virtual", "void", "volatile", "while",
+];
+
+/// Bit access shared by all generated headers, guarded so that several headers can be included together.
+const HELPERS: &str = r#"#ifndef TYDI_BIT_HELPERS
+#define TYDI_BIT_HELPERS
+
+/* Bit i of a buffer is bit i % 8 of byte i / 8, as in a TydiBinary. */
+
+static inline uint64_t tydi_get_bits(const uint8_t *buf, size_t offset, size_t width) {
+    uint64_t value = 0;
+    for (size_t i = 0; i < width; i++) {
+        size_t bit = offset + i;
+        value |= (uint64_t) ((buf[bit / 8] >> (bit % 8)) & 1) << i;
+    }
+    return value;
+}
+
+static inline void tydi_set_bits(uint8_t *buf, size_t offset, size_t width, uint64_t value) {
+    for (size_t i = 0; i < width; i++) {
+        size_t bit = offset + i;
+        uint8_t mask = (uint8_t) (1u << (bit % 8));
+        if ((value >> i) & 1) {
+            buf[bit / 8] |= mask;
+        } else {
+            buf[bit / 8] &= (uint8_t) ~mask;
+        }
+    }
+}
+
+/* Fields wider than 64 bits are copied to and from ceil(width / 8) bytes, the first byte holding the lowest bits. */
+
+static inline void tydi_get_wide(const uint8_t *buf, size_t offset, size_t width, uint8_t *value) {
+    for (size_t i = 0; i < width; i += 8) {
+        value[i / 8] = (uint8_t) tydi_get_bits(buf, offset + i, width - i < 8 ? width - i : 8);
+    }
+}
+
+static inline void tydi_set_wide(uint8_t *buf, size_t offset, size_t width, const uint8_t *value) {
+    for (size_t i = 0; i < width; i += 8) {
+        tydi_set_bits(buf, offset + i, width - i < 8 ? width - i : 8, value[i / 8]);
+    }
+}
+
+/* Big endian fields of whole bytes hold their most significant byte at the lowest bits, so their bytes are swapped. */
+
+static inline uint64_t tydi_swap_bytes(uint64_t value, size_t width) {
+    uint64_t result = 0;
+    for (size_t i = 0; i < width; i += 8) {
+        result = (result << 8) | ((value >> i) & 0xff);
+    }
+    return result;
+}
+
+static inline void tydi_get_wide_swapped(const uint8_t *buf, size_t offset, size_t width, uint8_t *value) {
+    for (size_t i = 0; i < width; i += 8) {
+        value[(width - i) / 8 - 1] = (uint8_t) tydi_get_bits(buf, offset + i, 8);
+    }
+}
+
+static inline void tydi_set_wide_swapped(uint8_t *buf, size_t offset, size_t width, const uint8_t *value) {
+    for (size_t i = 0; i < width; i += 8) {
+        tydi_set_bits(buf, offset + i, 8, value[(width - i) / 8 - 1]);
+    }
+}
+
+#endif /* TYDI_BIT_HELPERS */
+"#;
+
+/// C identifier of the sanitized [parts] joined by `__`, with a trailing underscore if it is a keyword.
+pub fn c_identifier<S: AsRef<str>>(parts: &[S]) -> String {
+    let name = join(parts);
+    if RESERVED.contains(&name.as_str()) { format!("{}_", name) } else { name }
+}
+
+/// Smallest unsigned C type holding [width] bits, at most 64.
+fn c_type(width: usize) -> &'static str {
+    match width {
+        0..=8 => "uint8_t",
+        9..=16 => "uint16_t",
+        17..=32 => "uint32_t",
+        _ => "uint64_t",
+    }
+}
+
+/// Writes a getter and a setter named `{prefix}_get_{field}` and `{prefix}_set_{field}` for the [width] bits at the
+/// offset given by the C expression [offset], taking the extra parameters [params]. The bytes of the field are swapped
+/// if [swapped], which requires a whole number of bytes.
+fn accessors(out: &mut String, prefix: &str, field: &str, params: &str, offset: &str, width: usize, swapped: bool) {
+    if width <= 64 {
+        let ty = c_type(width);
+        let (get, set) = if swapped {
+            (format!("tydi_swap_bytes(tydi_get_bits(transfer, {}, {}), {})", offset, width, width),
+             format!("tydi_set_bits(transfer, {}, {}, tydi_swap_bytes(value, {}))", offset, width, width))
+        } else {
+            (format!("tydi_get_bits(transfer, {}, {})", offset, width),
+             format!("tydi_set_bits(transfer, {}, {}, value)", offset, width))
+        };
+        writeln!(out, "static inline {} {}_get_{}(const uint8_t *transfer{}) {{\n    \
+            return ({}) {};\n}}\n", ty, prefix, field, params, ty, get).unwrap();
+        writeln!(out, "static inline void {}_set_{}(uint8_t *transfer{}, {} value) {{\n    \
+            {};\n}}\n", prefix, field, params, ty, set).unwrap();
+    } else {
+        let suffix = if swapped { "_swapped" } else { "" };
+        writeln!(out, "static inline void {}_get_{}(const uint8_t *transfer{}, uint8_t *value) {{\n    \
+            tydi_get_wide{}(transfer, {}, {}, value);\n}}\n", prefix, field, params, suffix, offset, width).unwrap();
+        writeln!(out, "static inline void {}_set_{}(uint8_t *transfer{}, const uint8_t *value) {{\n    \
+            tydi_set_wide{}(transfer, {}, {}, value);\n}}\n", prefix, field, params, suffix, offset, width).unwrap();
+    }
+}
+
+/// Writes the constants and accessors of [stream] at [path] to [out].
+///
+/// The constants are named after the path of the stream, followed by the transfer field or by `element` and the path of
+/// the element field.
+fn write_stream(out: &mut String, stream: &TydiPhysicalStream, path: &[String], order: TydiBitOrder) {
+    let big = order.endianness == TydiEndianness::Big;
+    let order = order.field_order;
+    let prefix = c_identifier(path);
+    let constant = |rest: &[&str]| c_identifier(&extended(path, rest)).to_uppercase();
+    let element_width = stream.element_width();
+    let header = field_widths(stream);
+    let header_width: usize = header[..4].iter().map(|(_, width)| width).sum();
+    let transfer_width = header_width + stream.data_width();
+
+    writeln!(out, "/* {}: {} lane(s), dimensionality {}, complexity {}, {:?} */\n",
+             stream.name(), stream.lanes, stream.dimensionality, stream.complexity, stream.direction).unwrap();
+    writeln!(out, "#define {} {}", constant(&["lanes"]), stream.lanes).unwrap();
+    writeln!(out, "#define {} {}", constant(&["element", "width"]), element_width).unwrap();
+    writeln!(out, "#define {} {}", constant(&["transfer", "width"]), transfer_width).unwrap();
+    writeln!(out, "#define {} {}", constant(&["transfer", "bytes"]), transfer_width.div_ceil(8)).unwrap();
+
+    // The transfer is the header fields followed by the lanes, see TydiTransfer::to_binary
+    let mut widths: Vec<usize> = header[..4].iter().map(|(_, width)| *width).collect();
+    widths.push(stream.data_width());
+    let offsets = place(0, transfer_width, &widths, order);
+    for ((signal, width), offset) in header[..4].iter().zip(&offsets) {
+        if *width > 0 {
+            writeln!(out, "#define {} {}", constant(&[signal, "offset"]), offset).unwrap();
+            writeln!(out, "#define {} {}", constant(&[signal, "width"]), width).unwrap();
+        }
+    }
+    let lane_offset = match order {
+        TydiFieldOrder::LsbFirst => format!("({} + (lane) * {})", offsets[4], element_width),
+        TydiFieldOrder::MsbFirst => format!("({} + ({} - 1 - (lane)) * {})", offsets[4], stream.lanes, element_width),
+    };
+    writeln!(out, "#define {}(lane) {}\n", constant(&["lane", "offset"]), lane_offset).unwrap();
+
+    for (signal, width) in &header[..4] {
+        if *width > 0 {
+            accessors(out, &prefix, signal, "", &constant(&[signal, "offset"]), *width, false);
+        }
+    }
+
+    let mut element = Vec::new();
+    leaves(&stream.element, vec!["element".to_string()], 0, order, &mut element);
+    let (params, lane) = if stream.lanes > 1 { (", size_t lane", "lane") } else { ("", "0") };
+    for leaf in element {
+        let field_constant = |suffix: &str| c_identifier(&extended(&[path, &leaf.path].concat(), &[suffix])).to_uppercase();
+        writeln!(out, "#define {} {}", field_constant("offset"), leaf.offset).unwrap();
+        // The width of an element without fields is defined above
+        if leaf.path.len() > 1 {
+            writeln!(out, "#define {} {}", field_constant("width"), leaf.width).unwrap();
+        }
+        writeln!(out).unwrap();
+        let offset = format!("{}({}) + {}", constant(&["lane", "offset"]), lane, field_constant("offset"));
+        // Union tags are packed as bits, primitives of whole bytes in the order of their bytes
+        let swapped = big && !leaf.tag && leaf.width.is_multiple_of(8);
+        accessors(out, &prefix, &c_identifier(&leaf.path), params, &offset, leaf.width, swapped);
+    }
+}
+
+/// Generates a C header for the [streams] of a logical type named [name], for host software that reads or writes
+/// transfers in memory.
+///
+/// For every stream, the header holds the widths and bit offsets of the transfer fields and of the fields of an element,
+/// and inline functions that get and set each of these fields in a transfer buffer. The offsets are those of
+/// [crate::transfer::TydiTransfer::to_binary] in [order], which for a single lane is
+/// [crate::TydiPacket::to_binary]: `strb`, `stai`, `endi` and `last`, followed by the lanes. The fields of unions are
+/// given for every variant, along with the `tag` selecting the variant.
+///
+/// With [TydiEndianness::Big], the accessors of element fields of a whole number of bytes swap these bytes, so they get
+/// and set numeric values as primitives are packed. Arrays and tuples are a single field, whose bytes are swapped as a
+/// whole.
+pub fn c_header(name: &str, streams: &[TydiPhysicalStream], order: TydiBitOrder) -> String {
+    let guard = format!("{}_TYDI_H", c_identifier(&[name]).to_uppercase());
+    let mut result = String::new();
+    writeln!(result, "#ifndef {}\n#define {}\n", guard, guard).unwrap();
+    writeln!(result, "#include <stddef.h>\n#include <stdint.h>\n").unwrap();
+    writeln!(result, "{}", HELPERS).unwrap();
+    for (stream, path) in streams.iter().zip(stream_paths(streams)) {
+        write_stream(&mut result, stream, &path, order);
+    }
+    writeln!(result, "#endif /* {} */", guard).unwrap();
+    result
+}
+
+/// [c_header] for the physical streams of [T], named after [name].
+pub fn c_header_for<T: TydiLogical>(name: &str, order: TydiBitOrder) -> String {
+    c_header(name, &T::logical_type().split(name), order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::{ToTydiBinary, TydiBinary};
+    use crate::layout::tests::readings;
+    use crate::logical::{TydiLogicalStream, TydiLogicalType};
+    use crate::logical::TydiLogicalType::Bits;
+    use crate::TydiPacket;
+
+    #[test]
+    fn test_offsets_match_packing() {
+        let orders = [
+            TydiBitOrder::PORTABLE,
+            TydiBitOrder::new(TydiEndianness::Little, TydiFieldOrder::MsbFirst),
+            TydiBitOrder::new(TydiEndianness::Big, TydiFieldOrder::LsbFirst),
+        ];
+        for order in orders {
+            let mut data = true.to_tydi_binary(order);
+            data.append(&0x1234u16.to_tydi_binary(order));
+            let mut unit = TydiBinary::new(vec![0b01], 2).in_order(order);
+            unit.append(&TydiBinary::new(vec![0x5], 4).in_order(order));
+            unit.append(&TydiBinary::zeros(4).in_order(order));
+            data.append(&unit);
+            let binary = TydiPacket { data: Some(data), last: vec![true] }.to_binary_in(27, order);
+
+            let header = c_header("sensor", &readings().split("readings"), binary.order);
+            let bits = binary.as_bits();
+            let offset = |name: &str| -> usize {
+                let line = header.lines().find(|line| line.starts_with(&format!("#define {} ", name))).unwrap();
+                line.rsplit(' ').next().unwrap().parse().unwrap()
+            };
+            assert!(bits.get(offset("READINGS__STRB__OFFSET")), "{:?}", order);
+            assert!(bits.get(offset("READINGS__LAST__OFFSET")), "{:?}", order);
+            // The single lane follows strb and last, which are at the top with the first field at the most significant bits
+            let lane = if order.field_order == TydiFieldOrder::LsbFirst { 2 } else { 0 };
+            assert!(bits.get(lane + offset("READINGS__ELEMENT__VALID__OFFSET")), "{:?}", order);
+            // The accessors of a big endian value swap its bytes
+            let value = bits.read_bits(lane + offset("READINGS__ELEMENT__VALUE__OFFSET"), 16);
+            let big = order.endianness == TydiEndianness::Big;
+            assert_eq!(value, if big { 0x3412 } else { 0x1234 }, "{:?}", order);
+            assert_eq!(header.contains("return (uint16_t) tydi_swap_bytes(tydi_get_bits(transfer, \
+                READINGS__LANE__OFFSET(0) + READINGS__ELEMENT__VALUE__OFFSET, 16), 16);\n"), big, "{:?}", order);
+            assert_eq!(bits.read_bits(lane + offset("READINGS__ELEMENT__UNIT__TAG__OFFSET"), 2), 1, "{:?}", order);
+            assert_eq!(bits.read_bits(lane + offset("READINGS__ELEMENT__UNIT__CELSIUS__OFFSET"), 4), 5, "{:?}", order);
+        }
+    }
+
+    #[test]
+    fn test_header() {
+        let mut chars = TydiLogicalStream::new(Bits(8), 1);
+        chars.throughput = 4.0;
+        let mut streams = TydiLogicalType::Stream(Box::new(chars)).split("chars");
+        streams.extend(TydiLogicalType::stream(Bits(128), 1).split("wide"));
+        let header = c_header("text", &streams, TydiBitOrder::PORTABLE);
+        assert!(header.starts_with("#ifndef TEXT_TYDI_H\n#define TEXT_TYDI_H\n\n#include <stddef.h>\n#include <stdint.h>\n"));
+        assert!(header.contains("#define CHARS__TRANSFER__WIDTH 41\n#define CHARS__TRANSFER__BYTES 6\n"));
+        assert!(header.contains("#define CHARS__LANE__OFFSET(lane) (9 + (lane) * 8)\n"));
+        assert!(header.contains("static inline uint8_t chars_get_element(const uint8_t *transfer, size_t lane) {\n    \
+            return (uint8_t) tydi_get_bits(transfer, CHARS__LANE__OFFSET(lane) + CHARS__ELEMENT__OFFSET, 8);\n}\n"));
+        assert!(header.contains("static inline void chars_set_stai(uint8_t *transfer, uint8_t value) {\n"));
+        assert!(header.contains("static inline void wide_get_element(const uint8_t *transfer, uint8_t *value) {\n    \
+            tydi_get_wide(transfer, WIDE__LANE__OFFSET(0) + WIDE__ELEMENT__OFFSET, 128, value);\n}\n"));
+        assert!(header.ends_with("#endif /* TEXT_TYDI_H */\n"));
+
+        let big = c_header("text", &streams, TydiBitOrder::new(TydiEndianness::Big, TydiFieldOrder::LsbFirst));
+        assert!(big.contains("static inline void chars_set_element(uint8_t *transfer, size_t lane, uint8_t value) {\n    \
+            tydi_set_bits(transfer, CHARS__LANE__OFFSET(lane) + CHARS__ELEMENT__OFFSET, 8, tydi_swap_bytes(value, 8));\n}\n"));
+        assert!(big.contains("static inline void wide_get_element(const uint8_t *transfer, uint8_t *value) {\n    \
+            tydi_get_wide_swapped(transfer, WIDE__LANE__OFFSET(0) + WIDE__ELEMENT__OFFSET, 128, value);\n}\n"));
+        // The transfer fields are bits, which are never swapped
+        assert!(big.contains("    tydi_set_bits(transfer, CHARS__STAI__OFFSET, 2, value);\n"));
+    }
+
+    #[test]
+    fn test_field_names() {
+        // A field named like a transfer field, and fields whose names only clash when joined by a single underscore
+        let element = TydiLogicalType::group(vec![
+            ("strb", Bits(1)),
+            ("a_b", TydiLogicalType::group(vec![("c", Bits(2))])),
+            ("a", TydiLogicalType::group(vec![("b_c", Bits(3))])),
+        ]);
+        let header = c_header("text", &TydiLogicalType::stream(element, 1).split("chars"), TydiBitOrder::PORTABLE);
+        assert!(header.contains("#define CHARS__STRB__OFFSET 0\n"));
+        assert!(header.contains("#define CHARS__ELEMENT__STRB__OFFSET 0\n"));
+        assert!(header.contains("#define CHARS__ELEMENT__A_B__C__OFFSET 1\n"));
+        assert!(header.contains("#define CHARS__ELEMENT__A__B_C__OFFSET 3\n"));
+        assert!(header.contains("static inline uint8_t chars_get_element__a__b_c(const uint8_t *transfer) {\n"));
+        assert_eq!(header.matches("chars_get_strb(").count(), 1);
+    }
+}
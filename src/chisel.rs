@@ -0,0 +1,180 @@
+use std::cmp::Reverse;
+use std::fmt::Write;
+use crate::layout::{declare, extended, sanitize, stream_paths, Declarations, Member};
+use crate::logical::{TydiDirection, TydiLogical, TydiLogicalType, TydiPhysicalStream};
+use crate::order::TydiBitOrder;
+
+/// Keywords of Scala, which have to be quoted in backticks to be used as names.
+const RESERVED: &[&str] = &[
+    "abstract", "case", "catch", "class", "def", "do", "else", "enum", "export", "extends", "false", "final",
+    "finally", "for", "forSome", "given", "if", "implicit", "import", "lazy", "match", "new", "null", "object",
+    "override", "package", "private", "protected", "return", "sealed", "super", "then", "this", "throw", "trait",
+    "true", "try", "type", "val", "var", "while", "with", "yield",
+];
+
+/// The sanitized [part] in `PascalCase`, such as `InReplyTo` for `in_reply_to`.
+fn pascal_part(part: &str) -> String {
+    sanitize(part).split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map(|c| c.to_uppercase().chain(chars).collect::<String>()).unwrap_or_default()
+        })
+        .collect()
+}
+
+/// [parts] in `PascalCase` joined by underscores, such as `Posts_InReplyTo` for `["posts", "in_reply_to"]`.
+fn pascal_case<S: AsRef<str>>(parts: &[S]) -> String {
+    parts.iter().map(|part| pascal_part(part.as_ref())).collect::<Vec<_>>().join("_")
+}
+
+/// Scala name of [parts] in `camelCase` joined by underscores, quoted in backticks if it is a keyword.
+pub fn scala_identifier<S: AsRef<str>>(parts: &[S]) -> String {
+    let name = parts.iter().map(|part| {
+        let pascal = pascal_part(part.as_ref());
+        let mut chars = pascal.chars();
+        chars.next().map(|c| c.to_lowercase().chain(chars).collect::<String>()).unwrap_or_default()
+    }).collect::<Vec<_>>().join("_");
+    if RESERVED.contains(&name.as_str()) { format!("`{}`", name) } else { name }
+}
+
+/// Chisel type of a bit vector of [width] bits, where a single bit is a `Bool`.
+fn chisel_bits(width: usize) -> (String, String) {
+    if width == 1 {
+        ("Bool".to_string(), "Bool()".to_string())
+    } else {
+        ("UInt".to_string(), format!("UInt({}.W)", width))
+    }
+}
+
+/// Declarations of the `Group` classes of a source file, collected while walking logical types.
+///
+/// Unions become classes of a `tag` and a `payload`, along with a companion object with the tag of every variant, which
+/// keeps the payload at the bits it is packed at.
+#[derive(Default)]
+struct Groups(String);
+
+impl Declarations for Groups {
+    /// Type and constructor of a field.
+    type Type = (String, String);
+
+    fn bits(&mut self, width: usize) -> (String, String) {
+        chisel_bits(width)
+    }
+
+    fn tags(&mut self, parts: &[String], variants: &[String]) {
+        writeln!(self.0, "object {} {{", pascal_case(parts)).unwrap();
+        for (i, name) in variants.iter().enumerate() {
+            writeln!(self.0, "  val {}: Int = {}", scala_identifier(&[format!("{}_tag", name)]), i).unwrap();
+        }
+        writeln!(self.0, "}}\n").unwrap();
+    }
+
+    /// Declares a `Group`, whose first field takes the most significant bits in `asUInt`.
+    fn record(&mut self, parts: &[String], _: usize, mut members: Vec<Member<(String, String)>>) -> (String, String) {
+        members.sort_by_key(|member| Reverse(member.offset));
+        let class_name = pascal_case(parts);
+        writeln!(self.0, "class {} extends Group {{", class_name).unwrap();
+        for member in members {
+            let (type_name, constructor) = member.declared;
+            writeln!(self.0, "  val {}: {} = {}", scala_identifier(&[member.name]), type_name, constructor).unwrap();
+        }
+        writeln!(self.0, "}}\n").unwrap();
+        (class_name.clone(), format!("new {}", class_name))
+    }
+}
+
+/// Generates Scala source in [package] with Chisel `Group` bundles for the [streams] of a logical type named [name], in
+/// the style of the Tydi-Chisel library.
+///
+/// Every stream gets classes for its element and user types, as far as these are groups or unions, and a
+/// `PhysicalStream` definition with its lane count, dimensionality and complexity in an object named `{Name}Streams`,
+/// as well as a `PhysicalStreamDetailed` definition of the same name in its nested `Detailed` object. The bundles
+/// convert with `asUInt` to the bits that [crate::TydiPacket::to_binary_in] packs in [order].
+pub fn chisel_source(package: &str, name: &str, streams: &[TydiPhysicalStream], order: TydiBitOrder) -> String {
+    let order = order.field_order;
+    let mut declarations = Groups::default();
+    let mut definitions = String::new();
+    let mut detailed = String::new();
+    for (stream, path) in streams.iter().zip(stream_paths(streams)) {
+        // Streams take elements of Tydi-Chisel, which wraps plain bits in a `BitsEl`
+        let mut tydi_el = |kind: &str, t: &TydiLogicalType| match t {
+            TydiLogicalType::Group(_) | TydiLogicalType::Union(_) => {
+                declare(&mut declarations, &extended(&path, &[kind]), t, order).map(|(_, constructor)| constructor)
+            }
+            _ => (t.data_width() > 0).then(|| format!("BitsEl({}.W)", t.data_width())),
+        };
+        let element = tydi_el("element", &stream.element).unwrap_or("Null()".to_string());
+        let user = tydi_el("user", &stream.user).unwrap_or("Null()".to_string());
+
+        writeln!(definitions, "  /** {}: {} lane(s), dimensionality {}, complexity {}, {:?} */",
+                 stream.name(), stream.lanes, stream.dimensionality, stream.complexity, stream.direction).unwrap();
+        writeln!(definitions, "  def {}: PhysicalStream = PhysicalStream({}, n = {}, d = {}, c = {}, u = {})",
+                 scala_identifier(&path), element, stream.lanes, stream.dimensionality,
+                 stream.complexity.level(), user).unwrap();
+        writeln!(detailed, "    def {} = PhysicalStreamDetailed({}, n = {}, d = {}, c = {}, r = {}, u = {})",
+                 scala_identifier(&path), element, stream.lanes, stream.dimensionality,
+                 stream.complexity.level(), stream.direction == TydiDirection::Reverse, user).unwrap();
+    }
+
+    let mut result = String::new();
+    writeln!(result, "package {}\n", package).unwrap();
+    writeln!(result, "import chisel3._").unwrap();
+    writeln!(result, "import nl.tudelft.tydi_chisel._\n").unwrap();
+    write!(result, "{}", declarations.0).unwrap();
+    writeln!(result, "object {}Streams {{", pascal_case(&[name])).unwrap();
+    write!(result, "{}", definitions).unwrap();
+    writeln!(result, "\n  object Detailed {{\n{}  }}\n}}", detailed).unwrap();
+    result
+}
+
+/// [chisel_source] for the physical streams of [T], named after [name].
+pub fn chisel_source_for<T: TydiLogical>(package: &str, name: &str, order: TydiBitOrder) -> String {
+    chisel_source(package, name, &T::logical_type().split(name), order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::tests::readings;
+    use crate::logical::TydiLogicalStream;
+    use crate::logical::TydiLogicalType::Bits;
+    use crate::complexity::TydiComplexity;
+
+    #[test]
+    fn test_names() {
+        assert_eq!(pascal_case(&["posts", "comments", "in_reply_to"]), "Posts_Comments_InReplyTo");
+        assert_eq!(scala_identifier(&["user_id"]), "userId");
+        assert_eq!(scala_identifier(&["type"]), "`type`");
+        assert_eq!(scala_identifier(&["field_1", "2nd-try"]), "field1_n2ndTry");
+        assert_ne!(pascal_case(&["a_b", "c"]), pascal_case(&["a", "b_c"]));
+    }
+
+    #[test]
+    fn test_bundles() {
+        let source = chisel_source("sensor", "sensor", &readings().split("readings"), TydiBitOrder::PORTABLE);
+        assert!(source.starts_with("package sensor\n\nimport chisel3._\nimport nl.tudelft.tydi_chisel._\n\n"));
+        assert!(source.contains("object Readings_Element_Unit {\n  val noneTag: Int = 0\n  val celsiusTag: Int = 1\n  \
+            val kelvinTag: Int = 2\n}\n"));
+        // The first field is declared last, as it takes the least significant bits
+        assert!(source.contains("class Readings_Element_Unit extends Group {\n  val payload: UInt = UInt(8.W)\n  \
+            val tag: UInt = UInt(2.W)\n}\n"));
+        assert!(source.contains("class Readings_Element extends Group {\n  \
+            val unit: Readings_Element_Unit = new Readings_Element_Unit\n  val value: UInt = UInt(16.W)\n  \
+            val valid: Bool = Bool()\n}\n"));
+    }
+
+    #[test]
+    fn test_stream_definitions() {
+        let mut chars = TydiLogicalStream::new(Bits(8), 2);
+        chars.throughput = 4.0;
+        chars.complexity = TydiComplexity::C7;
+        chars.direction = TydiDirection::Reverse;
+        let streams = TydiLogicalType::Stream(Box::new(chars)).split("chars");
+        let source = chisel_source("text", "chars", &streams, TydiBitOrder::PORTABLE);
+        assert!(source.ends_with("object CharsStreams {\n  \
+            /** chars: 4 lane(s), dimensionality 2, complexity C7, Reverse */\n  \
+            def chars: PhysicalStream = PhysicalStream(BitsEl(8.W), n = 4, d = 2, c = 7, u = Null())\n\n  \
+            object Detailed {\n    \
+            def chars = PhysicalStreamDetailed(BitsEl(8.W), n = 4, d = 2, c = 7, r = true, u = Null())\n  }\n}\n"));
+    }
+}
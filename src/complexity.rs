@@ -0,0 +1,247 @@
+use std::fmt;
+use std::fmt::Display;
+use crate::transfer::{TydiLast, TydiTransfer};
+use crate::{TydiPacket, TydiStream};
+
+/// Complexity level of a physical stream, stating which freedoms a source has in how it presents its data.
+///
+/// The levels that govern the contents of transfers are checked and applied here:
+/// - C < 4: `last` flags cannot be postponed to a transfer without data. Transfers without data are only allowed to
+///   encode empty sequences.
+/// - C < 5: all lanes are used, unless the transfer asserts a `last` flag or is the final transfer of the stream.
+/// - C < 6: `stai` is always 0.
+/// - C < 7: the strobe is high for exactly the lanes `stai..=endi`, or low for all lanes.
+/// - C < 8: `last` is shared by all lanes instead of specified per lane.
+///
+/// Levels C2 and C3 only restrict when `valid` may be released, which is a matter of handshake timing rather than of
+/// the transferred data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TydiComplexity {
+    C1 = 1,
+    C2 = 2,
+    C3 = 3,
+    C4 = 4,
+    C5 = 5,
+    C6 = 6,
+    C7 = 7,
+    C8 = 8,
+}
+
+impl TydiComplexity {
+    /// Numeric level of this complexity.
+    pub fn level(&self) -> u8 {
+        *self as u8
+    }
+
+    /// The complexity of numeric level [level], if it is one of 1 to 8.
+    pub fn from_level(level: u8) -> Option<Self> {
+        let levels = [Self::C1, Self::C2, Self::C3, Self::C4, Self::C5, Self::C6, Self::C7, Self::C8];
+        levels.get(level.checked_sub(1)? as usize).copied()
+    }
+
+    /// Whether `last` is specified per lane rather than per transfer.
+    pub fn per_lane_last(&self) -> bool {
+        *self >= TydiComplexity::C8
+    }
+}
+
+impl Display for TydiComplexity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "C{}", self.level())
+    }
+}
+
+/// A construct in a stream that is not allowed at the complexity the stream was checked against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TydiComplexityViolation {
+    /// Index of the offending packet or transfer.
+    pub index: usize,
+    /// The lowest complexity at which the construct is allowed.
+    pub required: TydiComplexity,
+    pub reason: &'static str,
+}
+
+impl Display for TydiComplexityViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at index {} requires complexity {}", self.reason, self.index, self.required)
+    }
+}
+
+/// How a packet without data relates to the packet before it.
+enum DatalessKind {
+    /// Carries no `last` flags at all.
+    Empty,
+    /// Opens and closes an empty sequence.
+    EmptySequence,
+    /// Closes a sequence of which the elements were already transferred.
+    Postponed,
+}
+
+/// Classifies a packet without data from its `last` flags and those of the packet before it.
+fn classify_dataless(last: &[bool], previous: Option<&[bool]>) -> DatalessKind {
+    // The innermost dimension this packet closes
+    match last.iter().rposition(|&l| l) {
+        None => DatalessKind::Empty,
+        Some(k) => {
+            // If the previous packet closed the same dimension there is nothing left to close, so this packet
+            // represents a new, empty, sequence.
+            if previous.is_none_or(|p| p.get(k).copied().unwrap_or(true)) {
+                DatalessKind::EmptySequence
+            } else {
+                DatalessKind::Postponed
+            }
+        }
+    }
+}
+
+impl<T: Clone> TydiStream<T> {
+    /// Checks the packets against the rules of complexity [c] that can be observed without lanes.
+    pub fn validate(&self, c: TydiComplexity) -> Result<(), TydiComplexityViolation> {
+        if c >= TydiComplexity::C4 {
+            return Ok(());
+        }
+        for (i, packet) in self.0.iter().enumerate() {
+            if packet.data.is_some() {
+                continue;
+            }
+            let previous = i.checked_sub(1).map(|p| self.0[p].last.as_slice());
+            let reason = match classify_dataless(&packet.last, previous) {
+                DatalessKind::EmptySequence => continue,
+                DatalessKind::Empty => "empty transfer",
+                DatalessKind::Postponed => "postponed last",
+            };
+            return Err(TydiComplexityViolation { index: i, required: TydiComplexity::C4, reason });
+        }
+        Ok(())
+    }
+
+    /// Rewrites the packets to follow the rules of complexity [c].
+    ///
+    /// Below C4, postponed `last` flags are moved to the packet holding the final element of the sequence and
+    /// packets that carry neither data nor `last` flags are removed. The represented data stays the same.
+    pub fn normalize(&self, c: TydiComplexity) -> TydiStream<T> {
+        if c >= TydiComplexity::C4 {
+            return TydiStream(self.0.clone());
+        }
+        let mut result: Vec<TydiPacket<T>> = Vec::with_capacity(self.0.len());
+        for packet in self.0.iter() {
+            if packet.data.is_some() {
+                result.push(packet.clone());
+                continue;
+            }
+            match classify_dataless(&packet.last, result.last().map(|p| p.last.as_slice())) {
+                DatalessKind::EmptySequence => result.push(packet.clone()),
+                DatalessKind::Empty => {}
+                DatalessKind::Postponed => {
+                    let previous = result.last_mut().unwrap();
+                    for (prev, &new) in previous.last.iter_mut().zip(packet.last.iter()) {
+                        *prev |= new;
+                    }
+                }
+            }
+        }
+        TydiStream(result)
+    }
+}
+
+/// Checks a sequence of transfers against the rules of complexity [c].
+pub fn validate_transfers<T>(transfers: &[TydiTransfer<T>], c: TydiComplexity) -> Result<(), TydiComplexityViolation> {
+    let violation = |index, required, reason| Err(TydiComplexityViolation { index, required, reason });
+
+    for (i, transfer) in transfers.iter().enumerate() {
+        let n = transfer.lanes.len();
+        let strb = transfer.strb();
+        let active = strb.iter().any(|&s| s);
+
+        if matches!(transfer.last, TydiLast::PerLane(_)) && c < TydiComplexity::C8 {
+            return violation(i, TydiComplexity::C8, "last flags per lane");
+        }
+        if active && c < TydiComplexity::C7 {
+            let contiguous = strb.iter().enumerate().all(|(lane, &s)| s == (lane >= transfer.stai && lane <= transfer.endi));
+            if !contiguous {
+                return violation(i, TydiComplexity::C7, "strobe not covering stai..=endi");
+            }
+        }
+        if transfer.stai != 0 && c < TydiComplexity::C6 {
+            return violation(i, TydiComplexity::C6, "non-zero stai");
+        }
+        let asserts_last = transfer.last.any();
+        if active && transfer.endi != n - 1 && c < TydiComplexity::C5 && !asserts_last && i != transfers.len() - 1 {
+            return violation(i, TydiComplexity::C5, "unused lanes without last");
+        }
+        if !active && c < TydiComplexity::C4 {
+            let previous = i.checked_sub(1).map(|p| transfers[p].last.of_final(transfers[p].endi));
+            match classify_dataless(transfer.last.of_final(transfer.endi), previous) {
+                DatalessKind::EmptySequence => {}
+                DatalessKind::Empty => return violation(i, TydiComplexity::C4, "empty transfer"),
+                DatalessKind::Postponed => return violation(i, TydiComplexity::C4, "postponed last"),
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transfer::tests::strings_stream;
+
+    #[test]
+    fn test_drilled_stream_is_c1() {
+        assert_eq!(strings_stream().validate(TydiComplexity::C1), Ok(()));
+        let transfers = strings_stream().to_transfers(4, TydiComplexity::C1);
+        assert_eq!(validate_transfers(&transfers, TydiComplexity::C1), Ok(()));
+    }
+
+    #[test]
+    fn test_postponed_last() {
+        let stream = TydiStream(vec![
+            TydiPacket { data: Some(1u8), last: vec![false] },
+            TydiPacket { data: Some(2u8), last: vec![false] },
+            TydiPacket { data: None, last: vec![true] },
+            TydiPacket { data: None, last: vec![true] },
+            TydiPacket { data: None, last: vec![false] },
+        ]);
+        let err = stream.validate(TydiComplexity::C3).unwrap_err();
+        assert_eq!(err.index, 2);
+        assert_eq!(err.required, TydiComplexity::C4);
+        assert_eq!(stream.validate(TydiComplexity::C4), Ok(()));
+
+        let normalized = stream.normalize(TydiComplexity::C3);
+        assert_eq!(normalized.0, vec![
+            TydiPacket { data: Some(1u8), last: vec![false] },
+            TydiPacket { data: Some(2u8), last: vec![true] },
+            // The second data-less packet is an empty sequence
+            TydiPacket { data: None, last: vec![true] },
+        ]);
+        assert_eq!(normalized.validate(TydiComplexity::C1), Ok(()));
+    }
+
+    #[test]
+    fn test_transfer_rules() {
+        let transfer = |lanes: Vec<Option<u8>>, stai, endi, last: Vec<bool>| TydiTransfer { lanes, stai, endi, last: TydiLast::Shared(last) };
+
+        let holes = vec![transfer(vec![Some(1), None, Some(3), None], 0, 2, vec![true])];
+        assert_eq!(validate_transfers(&holes, TydiComplexity::C6).unwrap_err().required, TydiComplexity::C7);
+        assert_eq!(validate_transfers(&holes, TydiComplexity::C7), Ok(()));
+
+        let offset = vec![transfer(vec![None, Some(2), Some(3), None], 1, 2, vec![true])];
+        assert_eq!(validate_transfers(&offset, TydiComplexity::C5).unwrap_err().required, TydiComplexity::C6);
+
+        let partial = vec![
+            transfer(vec![Some(1), Some(2), None, None], 0, 1, vec![false]),
+            transfer(vec![Some(3), None, None, None], 0, 0, vec![true]),
+        ];
+        assert_eq!(validate_transfers(&partial, TydiComplexity::C4).unwrap_err().required, TydiComplexity::C5);
+
+        // A single lane still has last flags of its own at C8
+        let per_lane = vec![TydiTransfer { lanes: vec![Some(1u8)], stai: 0, endi: 0, last: TydiLast::PerLane(vec![vec![true]]) }];
+        assert_eq!(validate_transfers(&per_lane, TydiComplexity::C7).unwrap_err().required, TydiComplexity::C8);
+        assert_eq!(validate_transfers(&per_lane, TydiComplexity::C8), Ok(()));
+
+        // Streams of dimensionality 0 have no last flags at all
+        let dataless = vec![transfer(vec![Some(1)], 0, 0, vec![]), transfer(vec![None], 0, 0, vec![])];
+        assert_eq!(validate_transfers(&dataless, TydiComplexity::C1).unwrap_err().reason, "empty transfer");
+        assert_eq!(validate_transfers(&dataless[..1], TydiComplexity::C1), Ok(()));
+    }
+}
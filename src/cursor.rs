@@ -0,0 +1,290 @@
+use crate::binary::{FromTydiBinary, ToTydiBinary, TydiBinary, TydiBitField, TydiBitSlice};
+use crate::error::TydiError;
+use crate::logical::TydiLogical;
+use crate::order::{TydiBitOrder, TydiFieldOrder};
+
+/// Builds a [TydiBinary] by appending values one after the other, moving the bits of every value only once.
+///
+/// Values are packed in the order of the writer, [TydiBitOrder::PORTABLE] unless it is created with
+/// [TydiBitWriter::in_order].
+#[derive(Debug, Clone)]
+pub struct TydiBitWriter {
+    order: TydiBitOrder,
+    /// Everything written so far under [TydiFieldOrder::LsbFirst].
+    binary: TydiBinary,
+    /// The fields written so far under [TydiFieldOrder::MsbFirst]. They are placed once the writer finishes, as
+    /// placing them one by one would move all earlier fields up for every new one.
+    pending: Vec<TydiBinary>,
+}
+
+impl Default for TydiBitWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TydiBitWriter {
+    pub fn new() -> Self {
+        Self::in_order(TydiBitOrder::PORTABLE)
+    }
+
+    /// A writer that packs values in [order].
+    pub fn in_order(order: TydiBitOrder) -> Self {
+        Self { order, binary: TydiBinary::empty().in_order(order), pending: Vec::new() }
+    }
+
+    /// A writer with room for [bits] bits.
+    pub fn with_capacity(bits: usize) -> Self {
+        Self { binary: TydiBinary::with_capacity(bits), ..Self::new() }
+    }
+
+    /// The order values are packed in.
+    pub fn order(&self) -> TydiBitOrder {
+        self.order
+    }
+
+    /// Number of bits written so far.
+    pub fn len(&self) -> usize {
+        self.binary.len + self.pending.iter().map(|field| field.len).sum::<usize>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Appends [value] packed in the order of the writer.
+    pub fn write<T: ToTydiBinary>(&mut self, value: T) -> &mut Self {
+        let binary = value.to_tydi_binary(self.order);
+        self.write_binary(&binary)
+    }
+
+    /// Appends a binary that is already packed. Panics if it holds bits and is packed in another order than the
+    /// writer.
+    pub fn write_binary(&mut self, binary: &TydiBinary) -> &mut Self {
+        if binary.len == 0 {
+            return self;
+        }
+        assert!(binary.order == self.order,
+                "cannot write a binary packed in {:?} to a writer in {:?}", binary.order, self.order);
+        match self.order.field_order {
+            TydiFieldOrder::LsbFirst => self.binary.append(binary),
+            TydiFieldOrder::MsbFirst => self.pending.push(binary.clone()),
+        }
+        self
+    }
+
+    /// Appends the lowest [count] bits of [value], least significant bit first. Panics if [value] does not fit.
+    pub fn write_bits(&mut self, value: u64, count: usize) -> &mut Self {
+        assert!(count <= 64, "cannot write more than 64 bits at once");
+        assert!(count == 64 || value >> count == 0, "{} does not fit in {} bits", value, count);
+        let bits = TydiBinary::new(value.to_le_bytes()[..count.div_ceil(8)].to_vec(), count);
+        self.write(bits.in_order(self.order))
+    }
+
+    /// Appends [count] zero bits, such as the padding of a union.
+    pub fn write_zeros(&mut self, count: usize) -> &mut Self {
+        self.write(TydiBinary::zeros(count).in_order(self.order))
+    }
+
+    /// Appends [value] packed into [bits] bits, see [TydiBitField::to_bit_field].
+    pub fn write_bit_field<T: TydiBitField>(&mut self, value: T, bits: usize, signed: bool) -> &mut Self {
+        self.write(value.to_bit_field(bits, signed, self.order))
+    }
+
+    /// The binary holding everything written.
+    pub fn finish(self) -> TydiBinary {
+        if self.pending.is_empty() {
+            return self.binary;
+        }
+        TydiBinary::concatenate_all(&self.pending)
+    }
+}
+
+/// Reads values from a [TydiBinary] at an advancing offset, taking the place of threading the remainder returned by
+/// [FromTydiBinary::from_tydi_binary] by hand.
+///
+/// Fields are taken from the least or most significant end according to the [TydiFieldOrder] of the
+/// [crate::order::TydiBitOrder] the binary was packed in. Typed values are read as wide as their logical type. The
+/// panicking functions have a `try_` variant that returns [TydiError::Truncated] when fewer bits remain than are needed.
+#[derive(Debug, Clone, Copy)]
+pub struct TydiBitReader<'a> {
+    bits: TydiBitSlice<'a>,
+}
+
+impl<'a> TydiBitReader<'a> {
+    pub fn new(binary: &'a TydiBinary) -> Self {
+        Self::from_slice(binary.as_bits())
+    }
+
+    pub fn from_slice(bits: TydiBitSlice<'a>) -> Self {
+        Self { bits }
+    }
+
+    /// Number of bits that have not been read yet.
+    pub fn remaining(&self) -> usize {
+        self.bits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits.is_empty()
+    }
+
+    fn check(&self, count: usize) -> Result<(), TydiError> {
+        if count > self.remaining() {
+            return Err(TydiError::Truncated { needed: count, available: self.remaining() });
+        }
+        Ok(())
+    }
+
+    /// The bits that have not been read yet, without consuming them.
+    pub fn rest(&self) -> TydiBitSlice<'a> {
+        self.bits
+    }
+
+    pub fn skip(&mut self, count: usize) -> &mut Self {
+        self.try_skip(count).expect("cannot skip past the end of the binary");
+        self
+    }
+
+    pub fn try_skip(&mut self, count: usize) -> Result<&mut Self, TydiError> {
+        self.check(count)?;
+        self.bits = self.bits.split_field(count).1;
+        Ok(self)
+    }
+
+    /// Reads the next [count] bits, at most 64, as an unsigned integer whose least significant bit comes first.
+    pub fn read_bits(&mut self, count: usize) -> u64 {
+        self.try_read_bits(count).expect("not enough bits left to read")
+    }
+
+    pub fn try_read_bits(&mut self, count: usize) -> Result<u64, TydiError> {
+        let value = self.try_peek_bits(count)?;
+        self.bits = self.bits.split_field(count).1;
+        Ok(value)
+    }
+
+    pub fn peek_bits(&self, count: usize) -> u64 {
+        self.try_peek_bits(count).expect("not enough bits left to peek")
+    }
+
+    pub fn try_peek_bits(&self, count: usize) -> Result<u64, TydiError> {
+        self.check(count)?;
+        let start = match self.bits.order().field_order {
+            TydiFieldOrder::LsbFirst => 0,
+            TydiFieldOrder::MsbFirst => self.remaining() - count,
+        };
+        Ok(self.bits.read_bits(start, count))
+    }
+
+    /// Reads the next [count] bits into a binary of their own.
+    pub fn read_binary(&mut self, count: usize) -> TydiBinary {
+        self.try_read_binary(count).expect("not enough bits left to read")
+    }
+
+    pub fn try_read_binary(&mut self, count: usize) -> Result<TydiBinary, TydiError> {
+        self.check(count)?;
+        let (field, rest) = self.bits.split_field(count);
+        self.bits = rest;
+        Ok(field.to_binary())
+    }
+
+    /// Reads a value of [T], which takes as many bits as the data width of its logical type.
+    pub fn read<T: FromTydiBinary + TydiLogical>(&mut self) -> T {
+        self.try_read().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Like [TydiBitReader::read], returning an error if too few bits remain or they are not a valid [T], see
+    /// [FromTydiBinary::try_from_tydi_binary].
+    pub fn try_read<T: FromTydiBinary + TydiLogical>(&mut self) -> Result<T, TydiError> {
+        let mut reader = *self;
+        let binary = reader.try_read_binary(T::logical_type().data_width())?;
+        let value = T::try_from_tydi_binary(binary)?.0;
+        *self = reader;
+        Ok(value)
+    }
+
+    /// Reads a value of [T] without consuming it.
+    pub fn peek<T: FromTydiBinary + TydiLogical>(&self) -> T {
+        self.try_peek().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_peek<T: FromTydiBinary + TydiLogical>(&self) -> Result<T, TydiError> {
+        let mut reader = *self;
+        reader.try_read()
+    }
+
+    /// Reads a value that was packed into [bits] bits, see [TydiBitField::from_bit_field].
+    pub fn read_bit_field<T: TydiBitField>(&mut self, bits: usize, signed: bool) -> T {
+        self.try_read_bit_field(bits, signed).expect("not enough bits left to read")
+    }
+
+    pub fn try_read_bit_field<T: TydiBitField>(&mut self, bits: usize, signed: bool) -> Result<T, TydiError> {
+        let binary = self.try_read_binary(bits)?;
+        Ok(T::from_bit_field(binary, bits, signed).0)
+    }
+
+    /// Copies the bits that have not been read yet into a new binary, as the remainder returned by
+    /// [FromTydiBinary::from_tydi_binary].
+    pub fn finish(self) -> TydiBinary {
+        self.bits.to_binary()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::order::{TydiBitOrder, TydiEndianness};
+
+    #[test]
+    fn test_writer_reader_roundtrip() {
+        let mut writer = TydiBitWriter::new();
+        writer.write(true).write_bits(0b101, 3).write(0xBEEFu16).write_bit_field(-5i32, 7, true).write(Some(9u8));
+        assert_eq!(writer.len(), 1 + 3 + 16 + 7 + 9);
+        let binary = writer.finish();
+
+        let mut reader = TydiBitReader::new(&binary);
+        assert!(reader.read::<bool>());
+        assert_eq!(reader.peek_bits(3), 0b101);
+        assert_eq!(reader.read_bits(3), 0b101);
+        assert_eq!(reader.peek::<u16>(), 0xBEEF);
+        assert_eq!(reader.skip(16).remaining(), 16);
+        assert_eq!(reader.read_bit_field::<i32>(7, true), -5);
+        assert_eq!(reader.read::<Option<u8>>(), Some(9));
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_reader_truncated() {
+        let binary: TydiBinary = 7u8.into();
+        let mut reader = TydiBitReader::new(&binary);
+        reader.skip(3);
+        assert_eq!(reader.try_read::<u8>(), Err(TydiError::Truncated { needed: 8, available: 5 }));
+        // A failed read does not advance the reader
+        assert_eq!(reader.remaining(), 5);
+        assert_eq!(reader.finish(), TydiBinary::new(vec![0], 5));
+    }
+
+    #[test]
+    fn test_writer_msb_first() {
+        let msb_first = TydiBitOrder::new(TydiEndianness::Little, TydiFieldOrder::MsbFirst);
+        let fields: Vec<TydiBinary> = (0..20u8).map(|i| (i * 13).to_tydi_binary(msb_first).split(1 + i as usize % 8).0).collect();
+        let mut appended = TydiBinary::empty().in_order(msb_first);
+        fields.iter().for_each(|field| appended.append(field));
+        assert_eq!(TydiBinary::concatenate_all(&fields), appended);
+
+        let mut writer = TydiBitWriter::in_order(msb_first);
+        fields.iter().for_each(|field| { writer.write_binary(field); });
+        assert_eq!(writer.len(), appended.len);
+        let binary = writer.finish();
+        assert_eq!(binary, appended);
+        // The first field is at the most significant bits
+        assert_eq!(TydiBitReader::new(&binary).read_binary(1), fields[0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot write a binary packed in")]
+    fn test_writer_rejects_other_orders() {
+        let big = TydiBitOrder::new(TydiEndianness::Big, TydiFieldOrder::LsbFirst);
+        TydiBitWriter::new().write(7u8).write_binary(&7u8.to_tydi_binary(big));
+    }
+}
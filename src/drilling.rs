@@ -1,4 +1,9 @@
-use crate::binary::TydiBinary;
+use std::iter::Peekable;
+use crate::binary::{ToTydiBinary, TydiBinary};
+use crate::complexity::TydiComplexity;
+use crate::error::TydiError;
+use crate::order::TydiBitOrder;
+use crate::logical::TydiLogical;
 use crate::{binary, TydiBinaryStream, TydiPacket, TydiStream};
 
 pub trait TydiConvert<T> {
@@ -19,8 +24,100 @@ impl<T: Clone> TydiConvert<T> for Vec<T> {
     }
 }
 
+/// Takes the items into a stream of dimensionality one, like [TydiConvert::convert] without cloning them.
+impl<T> From<Vec<T>> for TydiStream<T> {
+    fn from(value: Vec<T>) -> Self {
+        let len = value.len();
+        TydiStream(value.into_iter().enumerate().map(|(i, el)| TydiPacket { data: Some(el), last: vec![i == len-1] }).collect())
+    }
+}
+
 pub fn packets_from_binaries<T: binary::FromTydiBinary>(value: TydiBinaryStream, dim: usize) -> TydiStream<T> {
-    TydiStream(value.0.iter().map(|el| TydiPacket::from_binary(el.clone(), dim)).collect())
+    TydiStream(value.0.into_iter().map(|el| TydiPacket::from_binary(el, dim)).collect())
+}
+
+/// Like [packets_from_binaries], returning an error if any of the binaries is not a packet of [T].
+pub fn try_packets_from_binaries<T>(value: TydiBinaryStream, dim: usize) -> Result<TydiStream<T>, TydiError>
+where
+    T: binary::FromTydiBinary + TydiLogical
+{
+    Ok(TydiStream(value.0.into_iter().map(|el| TydiPacket::try_from_binary(el, dim)).collect::<Result<_, _>>()?))
+}
+
+/// The innermost `last` flag of the packet at [index], along with the flags of the other dimensions.
+fn split_innermost_last(last: &[bool], index: usize) -> Result<(bool, Vec<bool>), TydiError> {
+    match last.split_last() {
+        Some((&innermost, rest)) => Ok((innermost, rest.to_vec())),
+        None => Err(TydiError::MalformedLast { index, reason: "no dimension left to consume" }),
+    }
+}
+
+/// Iterator over the packets of a drilled stream, see [TydiStream::drill_iter].
+pub struct DrillIter<'a, T, F, I: Iterator> {
+    parents: std::slice::Iter<'a, TydiPacket<T>>,
+    f: F,
+    /// Remaining elements of the sequence of the current parent, along with the `last` flags of that parent.
+    current: Option<(Peekable<I>, &'a [bool])>,
+}
+
+impl<'a, T, F, B> Iterator for DrillIter<'a, T, F, B::IntoIter>
+where
+    F: Fn(&'a T) -> B,
+    B: IntoIterator
+{
+    type Item = TydiPacket<B::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((items, parent_last)) = self.current.as_mut() {
+                if let Some(item) = items.next() {
+                    // Only the final element closes the parent sequences
+                    let last = if items.peek().is_none() {
+                        [parent_last, &[true][..]].concat()
+                    } else {
+                        vec![false; parent_last.len() + 1]
+                    };
+                    return Some(TydiPacket { data: Some(item), last });
+                }
+                self.current = None;
+            }
+
+            let parent = self.parents.next()?;
+            let Some(data) = &parent.data else {
+                return Some(TydiPacket { data: None, last: [parent.last.clone(), vec![false]].concat() });
+            };
+            let mut items = (self.f)(data).into_iter().peekable();
+            // It can be that this dimension is empty, in that case yield a single empty packet
+            if items.peek().is_none() {
+                return Some(TydiPacket { data: None, last: [parent.last.clone(), vec![true]].concat() });
+            }
+            self.current = Some((items, &parent.last));
+        }
+    }
+}
+
+impl<T> FromIterator<TydiPacket<T>> for TydiStream<T> {
+    fn from_iter<I: IntoIterator<Item = TydiPacket<T>>>(iter: I) -> Self {
+        TydiStream(iter.into_iter().collect())
+    }
+}
+
+impl<T> TydiStream<T> {
+    /// A stream of references to the elements, with the same `last` flags.
+    pub fn as_refs(&self) -> TydiStream<&T> {
+        TydiStream(self.0.iter().map(|el| TydiPacket { data: el.data.as_ref(), last: el.last.clone() }).collect())
+    }
+
+    /// Like [TydiStream::drill], but borrowing the elements instead of cloning them, and yielding the packets lazily.
+    ///
+    /// [f] can return an iterator over references into the element, e.g. `|e| e.tags.iter()`.
+    pub fn drill_iter<'a, F, B>(&'a self, f: F) -> DrillIter<'a, T, F, B::IntoIter>
+    where
+        F: Fn(&'a T) -> B,
+        B: IntoIterator
+    {
+        DrillIter { parents: self.0.iter(), f, current: None }
+    }
 }
 
 impl<T: Clone> TydiStream<T> {
@@ -29,46 +126,15 @@ impl<T: Clone> TydiStream<T> {
     }
 
     /// "Drill" into the structure to the iterable field referenced in [f], creating a new dimension in the `last` data.
+    ///
+    /// The `last` flags of the parent packet are only carried by the final element of the new sequence, as that is
+    /// the element that closes the parent sequences.
     pub fn drill<F, B>(&self, f: F) -> TydiStream<<B as IntoIterator>::Item>
     where
         F: Fn(T) -> B,
         B: IntoIterator
     {
-        type ResultType<B> = Vec<TydiPacket<<B as IntoIterator>::Item>>;
-
-        let d = self.0.first().and_then(|el| Some(el.last.len())).unwrap_or(0);
-        // Map through existing items in our vector of packets
-        let v = self.0.iter().flat_map(|el| {
-            let el = (*el).clone();
-            let new_lasts = [el.last.clone(), vec![false]].concat();
-            // If the packet contains data
-            let new_vec: ResultType<B> = if let Some(old_data) = el.data {
-                // Apply drilling function create packets from elements of resulting iterable
-                let mut res: ResultType<B> = f(old_data).into_iter().map(|n_el| {
-                    TydiPacket { data: Some(n_el), last: new_lasts.clone() }
-                }).collect();
-
-                // It can be that this dimension is empty, in that case return a single empty packet
-                if res.is_empty() {
-                    vec![TydiPacket { data: None, last: [el.last.clone(), vec![true]].concat() }]
-                } else {
-                    // Patch last element
-                    /*if let Some(el) = res.last_mut() {
-                        el.last[d] = true
-                    }*/
-                    let res_len = res.len();
-                    res[res_len - 1].last[d] = true;
-                    res
-                }
-            } else {
-                vec![TydiPacket {
-                    data: None,
-                    last: new_lasts,
-                }]
-            };
-            new_vec
-        }).collect();
-        TydiStream(v)
+        self.drill_iter(|el| f(el.clone())).collect()
     }
 
     /// Inject the [data] in the vector referenced in the function [f] by consuming the lowest dimension in the `last` data.
@@ -80,30 +146,47 @@ impl<T: Clone> TydiStream<T> {
         self.inject_vec(f, data.0)
     }
 
+    /// Like [TydiStream::inject], returning an error if the `last` data of [data] is malformed.
+    pub fn try_inject<F, B>(&mut self, f: F, data: TydiStream<B>) -> Result<&mut Self, TydiError>
+    where
+        F: Fn(&mut T) -> &mut Vec<B>,
+        B: Clone
+    {
+        self.try_inject_vec(f, data.0)
+    }
+
     /// Inject the [data] in the vector referenced in the function [f] by consuming the lowest dimension in the `last` data.
     pub fn inject_vec<F, B>(&mut self, f: F, data: Vec<TydiPacket<B>>) -> &mut Self
     where
         F: Fn(&mut T) -> &mut Vec<B>,
         B: Clone
     {
-        let mut data_iter = data.iter();
+        self.try_inject_vec(f, data).expect("malformed last data")
+    }
+
+    /// Like [TydiStream::inject_vec], returning an error if the `last` data of [data] is malformed. Elements before
+    /// the malformed packet have already been injected when the error is returned.
+    pub fn try_inject_vec<F, B>(&mut self, f: F, data: Vec<TydiPacket<B>>) -> Result<&mut Self, TydiError>
+    where
+        F: Fn(&mut T) -> &mut Vec<B>,
+        B: Clone
+    {
+        let mut data_iter = data.iter().enumerate();
         for x in self.0.iter_mut() {
-            let self_option = x.data.as_mut();
-            if self_option.is_none() {
+            let Some(self_data) = x.data.as_mut() else {
                 data_iter.next();
                 continue
-            }
-            let self_data = self_option.unwrap();
-            let mut target = f(self_data);
-            while let Some(el) = data_iter.next() {
-                if el.data.is_none() { break }
-                target.push(el.data.clone().unwrap());
-                if *el.last.last().unwrap() == true {
+            };
+            let target = f(self_data);
+            for (index, el) in data_iter.by_ref() {
+                let Some(el_data) = &el.data else { break };
+                target.push(el_data.clone());
+                if split_innermost_last(&el.last, index)?.0 {
                     break
                 }
             }
         }
-        self
+        Ok(self)
     }
 
     /// Inject the [data] in the vector referenced in the function [f] by consuming the lowest dimension in the `last` data.
@@ -111,30 +194,94 @@ impl<T: Clone> TydiStream<T> {
     where
         F: Fn(&mut T) -> &mut String
     {
-        let strings = data.solidify_into_strings().unpack_with_default("".to_string());
+        self.try_inject_string(f, data).expect("malformed strings")
+    }
+
+    /// Like [TydiStream::inject_string], returning an error if [data] has malformed `last` data or is not valid UTF-8.
+    pub fn try_inject_string<F>(&mut self, f: F, data: TydiStream<u8>) -> Result<&mut Self, TydiError>
+    where
+        F: Fn(&mut T) -> &mut String
+    {
+        let strings = data.try_solidify_into_strings()?.unpack_with_default("".to_string());
         let mut strings_iter = strings.iter();
 
         for x in self.0.iter_mut() {
             let self_option = x.data.as_mut();
+            // Packets without data still have a (data-less) packet in the child stream
             if self_option.is_none() {
+                strings_iter.next();
                 continue
             }
             let self_data = self_option.unwrap();
-            let mut target = f(self_data);
+            let target = f(self_data);
             if let Some(el) = strings_iter.next() {
                 target.push_str(el);
             }
         }
+        Ok(self)
+    }
+
+    /// Creates a stream of the field referenced in [f], keeping the `last` data.
+    pub fn project<F, B>(&self, f: F) -> TydiStream<B>
+    where
+        F: Fn(&T) -> B
+    {
+        TydiStream(self.0.iter().map(|el| TydiPacket { data: el.data.as_ref().map(&f), last: el.last.clone() }).collect())
+    }
+
+    /// Apply [g] to a stream of the field referenced in [f] and write the modified field back.
+    pub fn inject_field<F, G, B>(&mut self, f: F, g: G) -> &mut Self
+    where
+        F: Fn(&mut T) -> &mut B,
+        G: FnOnce(&mut TydiStream<B>),
+        B: Clone
+    {
+        let mut field = TydiStream(self.0.iter_mut().map(|el| TydiPacket {
+            data: el.data.as_mut().map(|data| f(data).clone()),
+            last: el.last.clone(),
+        }).collect());
+        g(&mut field);
+        for (x, field_el) in self.0.iter_mut().zip(field.0) {
+            if let (Some(data), Some(field_data)) = (x.data.as_mut(), field_el.data) {
+                *f(data) = field_data;
+            }
+        }
+        self
+    }
+
+    /// Like [TydiStream::inject_field], for a field that is not present in every element, such as a field of an enum
+    /// variant. [g] gets `None` for the elements that lack the field.
+    pub fn inject_optional_field<F, G, B>(&mut self, f: F, g: G) -> &mut Self
+    where
+        F: Fn(&mut T) -> Option<&mut B>,
+        G: FnOnce(&mut TydiStream<Option<B>>),
+        B: Clone
+    {
+        let mut field = TydiStream(self.0.iter_mut().map(|el| TydiPacket {
+            data: el.data.as_mut().map(|data| f(data).cloned()),
+            last: el.last.clone(),
+        }).collect());
+        g(&mut field);
+        for (x, field_el) in self.0.iter_mut().zip(field.0) {
+            if let (Some(data), Some(Some(field_data))) = (x.data.as_mut(), field_el.data)
+                && let Some(target) = f(data) {
+                *target = field_data;
+            }
+        }
         self
     }
 
     /// Creates one layer of `Vec` by consuming the lowest dimension in the `last` data.
     pub fn vectorize(self) -> Vec<Vec<TydiPacket<T>>> {
+        self.try_vectorize().expect("malformed last data")
+    }
+
+    /// Like [TydiStream::vectorize], returning an error if a packet has no dimension left to consume.
+    pub fn try_vectorize(self) -> Result<Vec<Vec<TydiPacket<T>>>, TydiError> {
         let mut result: Vec<Vec<TydiPacket<T>>> = Vec::new();
         let mut inner_result: Vec<TydiPacket<T>> = Vec::new();
-        for x in self.0.iter() {
-            let mut last_copy = x.last.clone();
-            let last_el_in_dim = last_copy.pop().unwrap();
+        for (index, x) in self.0.iter().enumerate() {
+            let (last_el_in_dim, last_copy) = split_innermost_last(&x.last, index)?;
             // If the element is the last in the lowest dimension and empty (None) we don't push the item
             if !(last_el_in_dim && x.data.is_none()) {
                 inner_result.push(TydiPacket {
@@ -147,12 +294,17 @@ impl<T: Clone> TydiStream<T> {
                 inner_result = Vec::new();
             }
         }
-        result
+        Ok(result)
     }
 
     /// Consuming the final dimension in the `last` data and emit a `Vec`.
     pub fn unpack(self) -> Vec<T> {
-        self.0.iter().map(|el| el.data.clone().unwrap()).collect()
+        self.try_unpack().expect("packet without data")
+    }
+
+    /// Like [TydiStream::unpack], returning an error if a packet carries no data.
+    pub fn try_unpack(self) -> Result<Vec<T>, TydiError> {
+        self.0.into_iter().enumerate().map(|(index, el)| el.data.ok_or(TydiError::MissingData { index })).collect()
     }
 
     /// Consuming the final dimension in the `last` data and emit a `Vec`.
@@ -162,12 +314,16 @@ impl<T: Clone> TydiStream<T> {
 
     /// Creates one layer of `Vec` inside the packet by consuming the lowest dimension in the `last` data.
     pub fn vectorize_inner(self) -> TydiStream<Vec<T>> {
+        self.try_vectorize_inner().expect("malformed last data")
+    }
+
+    /// Like [TydiStream::vectorize_inner], returning an error if a packet has no dimension left to consume.
+    pub fn try_vectorize_inner(self) -> Result<TydiStream<Vec<T>>, TydiError> {
         // The top vector gets shorter as items are placed in the inner vectors instead.
         let mut result: Vec<TydiPacket<Vec<T>>> = Vec::new();
         let mut inner_result: Vec<T> = Vec::new();
-        for x in self.0.iter() {
-            let mut last_copy = x.last.clone();
-            let last_el_in_dim = last_copy.pop().unwrap();
+        for (index, x) in self.0.iter().enumerate() {
+            let (last_el_in_dim, last_copy) = split_innermost_last(&x.last, index)?;
             if x.data.is_some() {
                 inner_result.push(x.data.clone().unwrap());
             }
@@ -185,24 +341,39 @@ impl<T: Clone> TydiStream<T> {
                 inner_result = Vec::new();
             }
         }
-        TydiStream(result)
+        Ok(TydiStream(result))
     }
 }
 
 impl TydiStream<u8> {
     pub fn solidify_into_strings(self) -> TydiStream<String> {
-        let vectorized = self.vectorize_inner();
-        let v = vectorized.0.into_iter().map(|e| e.map_data(|x| String::from_utf8(x).unwrap())).collect::<Vec<TydiPacket<String>>>();
-        TydiStream(v)
+        self.try_solidify_into_strings().expect("malformed strings")
+    }
+
+    /// Like [TydiStream::solidify_into_strings], returning an error if the `last` data is malformed or a string is not
+    /// valid UTF-8.
+    pub fn try_solidify_into_strings(self) -> Result<TydiStream<String>, TydiError> {
+        let vectorized = self.try_vectorize_inner()?;
+        let v = vectorized.0.into_iter().map(|e| {
+            let TydiPacket { data, last } = e;
+            Ok(TydiPacket { data: data.map(String::from_utf8).transpose()?, last })
+        }).collect::<Result<Vec<TydiPacket<String>>, TydiError>>()?;
+        Ok(TydiStream(v))
     }
 }
 
 pub trait TydiPacktestToBinary {
     fn finish(&self, size: usize) -> TydiBinaryStream;
     fn finish_vec(&self, size: usize) -> Vec<TydiBinary>;
+    /// Like [TydiPacktestToBinary::finish], with the packets rewritten to follow the rules of complexity [c], see
+    /// [TydiStream::normalize], and packed in [order].
+    fn finish_with(&self, size: usize, c: TydiComplexity, order: TydiBitOrder) -> TydiBinaryStream;
+    /// Packs the stream in [order] into transfers of [lanes] element lanes that follow the rules of complexity [c], see
+    /// [TydiStream::to_transfers].
+    fn finish_lanes(&self, size: usize, lanes: usize, c: TydiComplexity, order: TydiBitOrder) -> TydiBinaryStream;
 }
 
-impl<T: Into<TydiBinary> + Clone> TydiPacktestToBinary for TydiStream<T> {
+impl<T: ToTydiBinary + Clone> TydiPacktestToBinary for TydiStream<T> {
     fn finish(&self, size: usize) -> TydiBinaryStream {
         TydiBinaryStream(self.finish_vec(size))
     }
@@ -210,4 +381,57 @@ impl<T: Into<TydiBinary> + Clone> TydiPacktestToBinary for TydiStream<T> {
     fn finish_vec(&self, size: usize) -> Vec<TydiBinary> {
         self.0.iter().map(|el| el.clone().to_binary(size)).collect()
     }
+
+    fn finish_with(&self, size: usize, c: TydiComplexity, order: TydiBitOrder) -> TydiBinaryStream {
+        TydiBinaryStream(self.normalize(c).0.into_iter().map(|el| el.to_binary_in(size, order)).collect())
+    }
+
+    fn finish_lanes(&self, size: usize, lanes: usize, c: TydiComplexity, order: TydiBitOrder) -> TydiBinaryStream {
+        TydiBinaryStream(self.to_transfers(lanes, c).into_iter().map(|el| el.to_binary_in(size, order)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drill_last_flags() {
+        let sequences = vec![vec![1u8, 2], vec![], vec![3, 4]];
+        let stream = sequences.convert().drill(|e| e);
+        // Only the final element of a sequence carries the `last` flags of its parent, so elements before it do not
+        // close the parent sequence as well.
+        assert_eq!(stream.0, vec![
+            TydiPacket { data: Some(1), last: vec![false, false] },
+            TydiPacket { data: Some(2), last: vec![false, true] },
+            TydiPacket { data: None, last: vec![false, true] },
+            TydiPacket { data: Some(3), last: vec![false, false] },
+            TydiPacket { data: Some(4), last: vec![true, true] },
+        ]);
+
+        // A parent without data has no sequence to open or close
+        let parents = TydiStream(vec![
+            TydiPacket { data: None, last: vec![false] },
+            TydiPacket { data: Some(vec![5u8]), last: vec![true] },
+        ]);
+        assert_eq!(parents.drill(|e| e).0, vec![
+            TydiPacket { data: None, last: vec![false, false] },
+            TydiPacket { data: Some(5), last: vec![true, true] },
+        ]);
+    }
+
+    #[test]
+    fn test_drill_inject_roundtrip() {
+        let sequences = vec![vec![vec![1u8, 2], vec![]], vec![], vec![vec![3]]];
+        let parents = sequences.convert();
+        let items = parents.drill(|e| e);
+        let bytes = items.drill(|e| e);
+        assert_eq!(bytes.0.last().unwrap().last, vec![true, true, true]);
+
+        let mut recreated_items = TydiStream(items.0.into_iter().map(|el| el.map_data(|_| Vec::new())).collect());
+        recreated_items.inject(|e| e, bytes);
+        let mut recreated = TydiStream(parents.0.into_iter().map(|el| el.map_data(|_| Vec::new())).collect());
+        recreated.inject(|e| e, recreated_items);
+        assert_eq!(recreated.unpack(), sequences);
+    }
 }
@@ -0,0 +1,849 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{json, Map, Value};
+use crate::binary::TydiBinary;
+use crate::cursor::{TydiBitReader, TydiBitWriter};
+use crate::error::TydiError;
+use crate::logical::{bounded_logical_type, union_tag_to_binary, union_tag_width, TydiLogicalStream, TydiLogicalType,
+                     TydiPhysicalStream, TydiSynchronicity};
+use crate::order::{TydiBitOrder, TydiEndianness};
+use crate::transfer::index_from_binary;
+use crate::{TydiBinaryStream, TydiPacket, TydiStream};
+
+/// The value of absent fields and variants.
+static NULL: Value = Value::Null;
+
+fn invalid(path: &str, reason: impl Into<String>) -> TydiError {
+    TydiError::InvalidValue { path: path.to_string(), reason: reason.into() }
+}
+
+/// Whether [t] holds streams, which are transferred separately from the rest of it.
+fn has_streams(t: &TydiLogicalType) -> bool {
+    match t {
+        TydiLogicalType::Null | TydiLogicalType::Bits(_) => false,
+        TydiLogicalType::Group(fields) | TydiLogicalType::Union(fields) => fields.iter().any(|(_, t)| has_streams(t)),
+        TydiLogicalType::Stream(_) => true,
+    }
+}
+
+/// The value [decode_value] gives for data of [t] that is all zeros: zero bits, the first variant of unions and empty
+/// sequences.
+pub fn default_value(t: &TydiLogicalType) -> Value {
+    match t {
+        TydiLogicalType::Null => Value::Null,
+        TydiLogicalType::Bits(_) => Value::from(0),
+        TydiLogicalType::Group(fields) => {
+            Value::Object(fields.iter().map(|(name, t)| (name.clone(), default_value(t))).collect())
+        }
+        TydiLogicalType::Union(variants) => {
+            variants.first().map_or(Value::Null, |(name, t)| variant_value(name, t, default_value(t)))
+        }
+        TydiLogicalType::Stream(stream) if stream.dimensionality == 0 => default_value(&stream.element),
+        TydiLogicalType::Stream(_) => Value::Array(vec![]),
+    }
+}
+
+/// The value of the variant [name] of type [t] holding [value]: just the name if the variant has no data.
+fn variant_value(name: &str, t: &TydiLogicalType, value: Value) -> Value {
+    match t {
+        TydiLogicalType::Null => Value::String(name.to_string()),
+        _ => Value::Object(Map::from_iter([(name.to_string(), value)])),
+    }
+}
+
+/// The values of the [fields] of a group in [value], null for fields that are left out.
+fn members<'a>(fields: &[(String, TydiLogicalType)], value: &'a Value, path: &str) -> Result<Vec<&'a Value>, TydiError> {
+    match value {
+        Value::Null => Ok(vec![&NULL; fields.len()]),
+        Value::Object(object) => {
+            if let Some(unknown) = object.keys().find(|key| fields.iter().all(|(name, _)| name != *key)) {
+                return Err(invalid(path, format!("unknown field `{}`", unknown)));
+            }
+            Ok(fields.iter().map(|(name, _)| object.get(name).unwrap_or(&NULL)).collect())
+        }
+        _ => Err(invalid(path, format!("expected an object, got {}", value))),
+    }
+}
+
+/// The selected variant in [value], given as its name, as an object with its name as only key, or as null for the
+/// first variant.
+fn variant<'a>(variants: &[(String, TydiLogicalType)], value: &'a Value, path: &str) -> Result<(usize, &'a Value), TydiError> {
+    let (name, inner) = match value {
+        Value::Null => return Ok((0, &NULL)),
+        Value::String(name) => (name, &NULL),
+        Value::Object(object) if object.len() == 1 => object.iter().next().unwrap(),
+        _ => return Err(invalid(path, format!("expected a variant name or an object with one variant, got {}", value))),
+    };
+    let index = variants.iter().position(|(n, _)| n == name)
+        .ok_or_else(|| invalid(path, format!("unknown variant `{}`", name)))?;
+    Ok((index, inner))
+}
+
+/// The elements of a sequence in [value], null being an empty sequence. A string is taken as its UTF-8 bytes if
+/// [bytes] is set.
+fn items(value: &Value, bytes: bool, path: &str) -> Result<Vec<Value>, TydiError> {
+    match value {
+        Value::Null => Ok(vec![]),
+        Value::Array(items) => Ok(items.clone()),
+        Value::String(text) if bytes => Ok(text.bytes().map(Value::from).collect()),
+        _ => Err(invalid(path, format!("expected an array, got {}", value))),
+    }
+}
+
+/// Packs [value] into [width] bits, as an unsigned or two's complement integer. Booleans are 1 or 0, and strings of
+/// hexadecimal digits starting with `0x` hold values of any width.
+///
+/// Fields of a whole number of bytes are packed in the [TydiEndianness] of [order], like primitives, and unpacked in
+/// that of the binary they are read from.
+fn pack_bits(value: &Value, width: usize, order: TydiBitOrder, path: &str) -> Result<TydiBinary, TydiError> {
+    let (mut bytes, negative) = match value {
+        Value::Null => (vec![], false),
+        Value::Bool(b) => (vec![*b as u8], false),
+        Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(u), _) => (u.to_le_bytes().to_vec(), false),
+            (None, Some(i)) => (i.to_le_bytes().to_vec(), true),
+            _ => return Err(invalid(path, format!("expected an integer, got {}", n))),
+        },
+        Value::String(text) => {
+            let digits = text.strip_prefix("0x").filter(|d| !d.is_empty() && d.chars().all(|c| c.is_ascii_hexdigit()))
+                .ok_or_else(|| invalid(path, format!("expected hexadecimal digits after `0x`, got {:?}", text)))?;
+            let mut bytes = vec![0u8; digits.len().div_ceil(2)];
+            for (i, digit) in digits.chars().rev().enumerate() {
+                bytes[i / 2] |= (digit.to_digit(16).unwrap() as u8) << (4 * (i % 2));
+            }
+            (bytes, false)
+        }
+        _ => return Err(invalid(path, format!("expected an integer, got {}", value))),
+    };
+
+    // The value fits if the bits above the field only repeat the sign
+    bytes.resize(bytes.len().max(width.div_ceil(8)) + 1, if negative { 0xFF } else { 0 });
+    let len = bytes.len() * 8;
+    let binary = TydiBinary::new(bytes, len);
+    let bits = binary.as_bits();
+    let fits = (width..bits.len()).all(|i| bits.get(i) == negative) && (!negative || (width > 0 && bits.get(width - 1)));
+    if !fits {
+        return Err(invalid(path, format!("{} does not fit in {} bits", value, width)));
+    }
+    let mut field = bits.split_at(width).0.to_binary().in_order(order);
+    if width.is_multiple_of(8) && order.endianness == TydiEndianness::Big {
+        field.data.reverse();
+    }
+    Ok(field)
+}
+
+/// Reverses [pack_bits], giving a number for fields of up to 64 bits and a hexadecimal string for wider fields.
+fn unpack_bits(mut field: TydiBinary) -> Value {
+    if field.len.is_multiple_of(8) && field.order.endianness == TydiEndianness::Big {
+        field.data.reverse();
+    }
+    if field.len <= 64 {
+        return Value::from(field.as_bits().read_bits(0, field.len));
+    }
+    let hex: String = field.data.iter().rev().map(|byte| format!("{:02x}", byte)).collect();
+    Value::String(format!("0x{}", &hex[hex.len() - field.len.div_ceil(4)..]))
+}
+
+/// Packs the part of [value] that [t] transfers in the stream it is part of in [order], leaving out its child streams.
+fn pack(t: &TydiLogicalType, value: &Value, order: TydiBitOrder, path: &str) -> Result<TydiBinary, TydiError> {
+    match t {
+        TydiLogicalType::Null | TydiLogicalType::Stream(_) => Ok(TydiBinary::empty().in_order(order)),
+        TydiLogicalType::Bits(width) => pack_bits(value, *width, order, path),
+        TydiLogicalType::Group(fields) => {
+            let mut writer = TydiBitWriter::in_order(order);
+            for ((name, field), value) in fields.iter().zip(members(fields, value, path)?) {
+                writer.write(pack(field, value, order, &format!("{}.{}", path, name))?);
+            }
+            Ok(writer.finish())
+        }
+        TydiLogicalType::Union(variants) => {
+            let (index, inner) = variant(variants, value, path)?;
+            let (name, variant_type) = &variants[index];
+            let payload = pack(variant_type, inner, order, &format!("{}.{}", path, name))?;
+            let mut result = union_tag_to_binary(index, variants.len()).in_order(order);
+            let padding = TydiBinary::zeros(t.data_width() - result.len - payload.len).in_order(order);
+            result.append(&payload);
+            result.append(&padding);
+            Ok(result)
+        }
+    }
+}
+
+/// Reverses [pack], placing default values at child streams.
+fn unpack(t: &TydiLogicalType, reader: &mut TydiBitReader, path: &str) -> Result<Value, TydiError> {
+    match t {
+        TydiLogicalType::Null | TydiLogicalType::Stream(_) => Ok(default_value(t)),
+        TydiLogicalType::Bits(width) => Ok(unpack_bits(reader.try_read_binary(*width)?)),
+        TydiLogicalType::Group(fields) => fields.iter().map(|(name, field)| {
+            Ok((name.clone(), unpack(field, reader, &format!("{}.{}", path, name))?))
+        }).collect::<Result<Map<_, _>, _>>().map(Value::Object),
+        TydiLogicalType::Union(variants) => {
+            let index = index_from_binary(reader.try_read_binary(union_tag_width(variants.len()))?);
+            let (name, variant_type) = variants.get(index)
+                .ok_or_else(|| invalid(path, format!("tag {} selects none of the {} variants", index, variants.len())))?;
+            let value = unpack(variant_type, reader, &format!("{}.{}", path, name))?;
+            reader.try_skip(t.data_width() - union_tag_width(variants.len()) - variant_type.data_width())?;
+            Ok(variant_value(name, variant_type, value))
+        }
+    }
+}
+
+/// Encodes the child streams of [t] into [result], in the order of [TydiLogicalType::split]. [packets] are the
+/// packets of the stream [t] is part of, holding the parts of the value that are of [t].
+fn encode_streams(t: &TydiLogicalType, packets: Vec<TydiPacket<Value>>, order: TydiBitOrder, path: &str,
+                  result: &mut Vec<TydiBinaryStream>) -> Result<(), TydiError> {
+    match t {
+        TydiLogicalType::Null | TydiLogicalType::Bits(_) => Ok(()),
+        TydiLogicalType::Group(fields) | TydiLogicalType::Union(fields) => {
+            for (i, (name, field)) in fields.iter().enumerate().filter(|(_, (_, field))| has_streams(field)) {
+                // Absent values, including the variants that are not selected, hold empty sequences
+                let field_packets = packets.iter().map(|packet| {
+                    let data = packet.data.as_ref().map(|value| match t {
+                        TydiLogicalType::Group(_) => members(fields, value, path).map(|values| values[i].clone()),
+                        _ => variant(fields, value, path).map(|(index, inner)| {
+                            if index == i { inner.clone() } else { Value::Null }
+                        }),
+                    }).transpose()?;
+                    Ok(TydiPacket { data, last: packet.last.clone() })
+                }).collect::<Result<_, TydiError>>()?;
+                encode_streams(field, field_packets, order, &format!("{}.{}", path, name), result)?;
+            }
+            Ok(())
+        }
+        TydiLogicalType::Stream(stream) => {
+            let mut packets = match stream.synchronicity {
+                TydiSynchronicity::Sync | TydiSynchronicity::Desync => packets,
+                TydiSynchronicity::Flatten | TydiSynchronicity::FlatDesync => packets.into_iter()
+                    .filter_map(|packet| packet.data.map(|data| TydiPacket { data: Some(data), last: vec![] }))
+                    .collect(),
+            };
+            for dimension in 0..stream.dimensionality {
+                let bytes = dimension + 1 == stream.dimensionality && stream.element == TydiLogicalType::Bits(8);
+                let sequences = packets.into_iter().map(|packet| {
+                    let data = packet.data.map(|value| items(&value, bytes, path)).transpose()?;
+                    Ok(TydiPacket { data, last: packet.last })
+                }).collect::<Result<Vec<_>, TydiError>>()?;
+                packets = TydiStream(sequences).drill(|items| items).0;
+            }
+
+            if stream.is_physical() {
+                let width = stream.element.data_width();
+                let binaries = packets.iter().map(|packet| {
+                    let data = packet.data.as_ref().map(|value| pack(&stream.element, value, order, path)).transpose()?;
+                    TydiPacket { data, last: packet.last.clone() }.try_to_binary_in(width, order)
+                }).collect::<Result<_, _>>()?;
+                result.push(TydiBinaryStream(binaries));
+            }
+            encode_streams(&stream.element, packets, order, path, result)
+        }
+    }
+}
+
+/// Encodes [value] as a value of [t] into the binaries of the physical streams of [t] in [order], each along with the
+/// stream as split off by [TydiLogicalType::split] with [name].
+///
+/// The value is split up as [TydiStream::drill] does for derived types:
+/// - Bits are integers, negative ones in two's complement, or booleans. Fields wider than 64 bits can be given as a
+///   string of hexadecimal digits starting with `0x`.
+/// - Groups are objects with a member per field. Fields that are left out get their default value.
+/// - Unions are the name of a variant without data, or an object with the name of the selected variant as only key.
+/// - Streams are arrays, nested as deep as their dimensionality. Streams of `Bits(8)` also take a string as its UTF-8
+///   bytes.
+///
+/// Null is taken as the default value of any type: zeros, the first variant or an empty sequence. The user data of
+/// streams is not encoded. The root of [t] is a single value, so a sequence of items is of a stream type.
+pub fn encode_value(name: &str, t: &TydiLogicalType, value: &Value, order: TydiBitOrder)
+                    -> Result<Vec<(TydiPhysicalStream, TydiBinaryStream)>, TydiError> {
+    let mut binaries = Vec::new();
+    encode_streams(t, vec![TydiPacket { data: Some(value.clone()), last: vec![] }], order, "$", &mut binaries)?;
+    Ok(t.split(name).into_iter().zip(binaries).collect())
+}
+
+/// Reads the binaries of the physical streams of a logical type, in the order of [TydiLogicalType::split].
+struct Decoder<'a> {
+    streams: &'a [TydiBinaryStream],
+    /// Index of the next physical stream to decode.
+    next: usize,
+}
+
+impl Decoder<'_> {
+    /// The packets of the physical stream at [index], transferring [stream] with [dimensionality] dimensions.
+    fn packets(&self, index: usize, stream: &TydiLogicalStream, dimensionality: usize, path: &str)
+               -> Result<Vec<TydiPacket<Value>>, TydiError> {
+        let expected = 1 + dimensionality + stream.element.data_width();
+        self.streams[index].0.iter().map(|binary| {
+            if binary.len < expected {
+                return Err(TydiError::Truncated { needed: expected, available: binary.len });
+            }
+            if binary.len > expected {
+                return Err(TydiError::SizeMismatch { expected, actual: binary.len });
+            }
+            let mut reader = TydiBitReader::new(binary);
+            let strobe = reader.read::<bool>();
+            let last: Vec<bool> = reader.read_binary(dimensionality).into();
+            let data = strobe.then(|| unpack(&stream.element, &mut reader, path)).transpose()?;
+            Ok(TydiPacket { data, last })
+        }).collect()
+    }
+
+    /// The packets of the first synchronous stream in [t], with the dimensions of [t] itself left, or `None` if there
+    /// is none. [index] is the index of the first physical stream in [t], and is moved past the streams that are
+    /// passed over.
+    fn sync_packets(&self, t: &TydiLogicalType, index: &mut usize, dimensionality: usize, path: &str)
+                    -> Result<Option<Vec<TydiPacket<Value>>>, TydiError> {
+        match t {
+            TydiLogicalType::Null | TydiLogicalType::Bits(_) => Ok(None),
+            TydiLogicalType::Group(fields) | TydiLogicalType::Union(fields) => {
+                for (name, field) in fields {
+                    if let Some(packets) = self.sync_packets(field, index, dimensionality, &format!("{}.{}", path, name))? {
+                        return Ok(Some(packets));
+                    }
+                }
+                Ok(None)
+            }
+            TydiLogicalType::Stream(stream) => {
+                if matches!(stream.synchronicity, TydiSynchronicity::Flatten | TydiSynchronicity::FlatDesync) {
+                    *index += t.split("").len();
+                    return Ok(None);
+                }
+                let dimensionality = dimensionality + stream.dimensionality;
+                let packets = if stream.is_physical() {
+                    *index += 1;
+                    self.packets(*index - 1, stream, dimensionality, path)?
+                } else {
+                    match self.sync_packets(&stream.element, index, dimensionality, path)? {
+                        Some(packets) => packets,
+                        None => return Ok(None),
+                    }
+                };
+                vectorize(packets, stream.dimensionality).map(Some)
+            }
+        }
+    }
+
+    /// Fills in the child streams of [t] in [packets], the packets of the stream [t] is part of, which have
+    /// [dimensionality] dimensions.
+    fn attach(&mut self, t: &TydiLogicalType, mut packets: Vec<TydiPacket<Value>>, dimensionality: usize, path: &str)
+              -> Result<Vec<TydiPacket<Value>>, TydiError> {
+        match t {
+            TydiLogicalType::Null | TydiLogicalType::Bits(_) => Ok(packets),
+            TydiLogicalType::Group(fields) | TydiLogicalType::Union(fields) => {
+                let is_group = matches!(t, TydiLogicalType::Group(_));
+                for (name, field) in fields.iter().filter(|(_, field)| has_streams(field)) {
+                    // The streams of variants that are not selected hold empty sequences, which are decoded and
+                    // dropped
+                    let field_packets = packets.iter_mut().map(|packet| TydiPacket {
+                        data: packet.data.as_mut().map(|value| match value.get_mut(name) {
+                            Some(inner) => inner.take(),
+                            None if is_group => Value::Null,
+                            None => default_value(field),
+                        }),
+                        last: packet.last.clone(),
+                    }).collect();
+                    let field_path = format!("{}.{}", path, name);
+                    let field_packets = self.attach(field, field_packets, dimensionality, &field_path)?;
+                    for (packet, field_packet) in packets.iter_mut().zip(field_packets) {
+                        if let (Some(inner), Some(field_value)) =
+                            (packet.data.as_mut().and_then(|value| value.get_mut(name)), field_packet.data) {
+                            *inner = field_value;
+                        }
+                    }
+                }
+                Ok(packets)
+            }
+            TydiLogicalType::Stream(stream) => {
+                let flatten = matches!(stream.synchronicity, TydiSynchronicity::Flatten | TydiSynchronicity::FlatDesync);
+                let child_dimensionality = stream.dimensionality + if flatten { 0 } else { dimensionality };
+                let child_packets = if stream.is_physical() {
+                    self.next += 1;
+                    self.packets(self.next - 1, stream, child_dimensionality, path)?
+                } else {
+                    // Without data of its own, the sequences of the stream show in those of a synchronous child
+                    let mut index = self.next;
+                    let packets = self.sync_packets(&stream.element, &mut index, child_dimensionality, path)?
+                        .ok_or_else(|| invalid(path, "the stream carries no data and has no synchronous child streams"))?;
+                    packets.into_iter().map(|packet| packet.map_data(|_| default_value(&stream.element))).collect()
+                };
+                let child_packets = self.attach(&stream.element, child_packets, child_dimensionality, path)?;
+
+                let mut sequences = vectorize(child_packets, stream.dimensionality)?.into_iter();
+                for packet in packets.iter_mut().filter(|packet| !flatten || packet.data.is_some()) {
+                    let sequence = sequences.next()
+                        .ok_or_else(|| invalid(path, "the stream holds fewer sequences than its parent elements"))?;
+                    if let Some(value) = packet.data.as_mut() {
+                        *value = sequence.data.ok_or_else(|| invalid(path, "an element has no sequence"))?;
+                    }
+                }
+                if sequences.next().is_some() {
+                    return Err(invalid(path, "the stream holds more sequences than its parent elements"));
+                }
+                Ok(packets)
+            }
+        }
+    }
+}
+
+/// Gathers [packets] into arrays by consuming the [dimensions] innermost dimensions of their `last` flags.
+fn vectorize(packets: Vec<TydiPacket<Value>>, dimensions: usize) -> Result<Vec<TydiPacket<Value>>, TydiError> {
+    let mut stream = TydiStream(packets);
+    for _ in 0..dimensions {
+        stream = stream.try_vectorize_inner()?.0.into_iter().map(|packet| packet.map_data(Value::Array)).collect();
+    }
+    Ok(stream.0)
+}
+
+/// Decodes the binaries of the physical streams of [t], in the order of [TydiLogicalType::split], into the value they
+/// were encoded from by [encode_value].
+///
+/// Values are given in their canonical form: bits as numbers, or hexadecimal strings if they are wider than 64 bits,
+/// strings as arrays of bytes, and all fields of groups. Parts of the root that are not transferred in a stream get
+/// their default value.
+pub fn decode_value(t: &TydiLogicalType, streams: &[TydiBinaryStream]) -> Result<Value, TydiError> {
+    let expected = t.split("").len();
+    if streams.len() != expected {
+        return Err(invalid("$", format!("expected {} binary streams, got {}", expected, streams.len())));
+    }
+    let mut decoder = Decoder { streams, next: 0 };
+    let root = vec![TydiPacket { data: Some(default_value(t)), last: vec![] }];
+    let mut root = decoder.attach(t, root, 0, "$")?;
+    Ok(root.pop().and_then(|packet| packet.data).unwrap_or_default())
+}
+
+/// Shape of JSON values known at runtime, as an alternative to deriving [crate::Tydi] for Rust types that mirror them.
+///
+/// Every shape is transferred as the Rust type it corresponds to, so a schema mirroring a derived type splits into the
+/// same physical streams with the same binaries.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TydiSchema {
+    /// `true` or `false`, transferred as a `bool`.
+    Bool,
+    /// An integer of [bits] bits, 1 to 64, in two's complement if [signed].
+    Integer { bits: usize, signed: bool },
+    /// A number, transferred as an `f32`.
+    Float32,
+    /// A number, transferred as an `f64`.
+    Float64,
+    /// UTF-8 text, transferred as a `String`.
+    String,
+    /// UTF-8 text of at most [max_len] bytes, transferred inline as a `Vec<u8>` with `#[tydi(max_len = ..)]`.
+    BoundedString { max_len: usize },
+    /// A date and time in RFC 3339 format, transferred as milliseconds since the Unix epoch in an `i64`.
+    Timestamp,
+    /// A sequence of values, transferred as a `Vec`.
+    Array(Box<TydiSchema>),
+    /// An object with the given properties, transferred as a struct.
+    Object(Vec<(String, TydiSchema)>),
+    /// A value that can be null or left out of its object, transferred as an `Option`.
+    Optional(Box<TydiSchema>),
+    /// One of the given strings, transferred as an enum of unit variants.
+    Enum(Vec<String>),
+}
+
+impl TydiSchema {
+    /// Creates an object schema from named properties.
+    pub fn object(properties: Vec<(&str, TydiSchema)>) -> Self {
+        Self::Object(properties.into_iter().map(|(name, schema)| (name.to_string(), schema)).collect())
+    }
+
+    pub fn array(items: TydiSchema) -> Self {
+        Self::Array(Box::new(items))
+    }
+
+    pub fn optional(value: TydiSchema) -> Self {
+        Self::Optional(Box::new(value))
+    }
+
+    /// Checks that every integer of this schema at [path] takes 1 to 64 bits, the widths values are converted at.
+    fn check(&self, path: &str) -> Result<(), TydiError> {
+        match self {
+            Self::Integer { bits, .. } if !(1..=64).contains(bits) => Err(TydiError::InvalidSchema {
+                path: path.to_string(),
+                reason: format!("an integer takes 1 to 64 bits, not {}", bits),
+            }),
+            Self::Array(items) => items.check(&format!("{}[]", path)),
+            Self::Object(properties) => properties.iter()
+                .try_for_each(|(name, schema)| schema.check(&format!("{}.{}", path, name))),
+            Self::Optional(value) => value.check(path),
+            _ => Ok(()),
+        }
+    }
+
+    /// The logical type of the values of this schema.
+    pub fn logical_type(&self) -> TydiLogicalType {
+        match self {
+            Self::Bool => TydiLogicalType::Bits(1),
+            Self::Integer { bits, .. } => TydiLogicalType::Bits(*bits),
+            Self::Float32 => TydiLogicalType::Bits(32),
+            Self::Float64 | Self::Timestamp => TydiLogicalType::Bits(64),
+            Self::String => TydiLogicalType::stream(TydiLogicalType::Bits(8), 1),
+            Self::BoundedString { max_len } => bounded_logical_type::<u8>(*max_len),
+            Self::Array(items) => TydiLogicalType::stream(items.logical_type(), 1),
+            Self::Object(properties) => TydiLogicalType::Group(
+                properties.iter().map(|(name, schema)| (name.clone(), schema.logical_type())).collect()),
+            Self::Optional(value) => TydiLogicalType::union(vec![("none", TydiLogicalType::Null), ("some", value.logical_type())]),
+            Self::Enum(names) => TydiLogicalType::Union(
+                names.iter().map(|name| (name.clone(), TydiLogicalType::Null)).collect()),
+        }
+    }
+
+    /// Converts [value] into the form [encode_value] takes for the logical type of this schema, when encoding in
+    /// [order].
+    fn to_logical(&self, value: &Value, order: TydiBitOrder, path: &str) -> Result<Value, TydiError> {
+        let expected = |what: &str| invalid(path, format!("expected {}, got {}", what, value));
+        match (self, value) {
+            (Self::Bool, Value::Bool(_)) | (Self::String, Value::String(_)) => Ok(value.clone()),
+            (Self::Integer { bits, signed }, Value::Number(n)) => {
+                let n = n.as_i64().map(i128::from).or(n.as_u64().map(i128::from)).ok_or_else(|| expected("an integer"))?;
+                let (min, max) = if *signed { (-1 << (bits - 1), (1 << (bits - 1)) - 1) } else { (0, (1 << bits) - 1) };
+                if n < min || n > max {
+                    return Err(invalid(path, format!("{} is out of the range {}..={}", n, min, max)));
+                }
+                Ok(value.clone())
+            }
+            (Self::BoundedString { max_len }, Value::String(text)) => {
+                if text.len() > *max_len {
+                    return Err(invalid(path, format!("{} bytes do not fit in a string of at most {}", text.len(), max_len)));
+                }
+                // The bytes are packed as by `pack_bounded`, and given as the value that packs into the same bits
+                let mut items = TydiBitWriter::in_order(order);
+                text.bytes().for_each(|byte| { items.write(byte); });
+                items.write_zeros((max_len - text.len()) * 8);
+                Ok(json!({"length": text.len(), "items": unpack_bits(items.finish())}))
+            }
+            (Self::Float32, Value::Number(n)) => Ok(Value::from((n.as_f64().unwrap() as f32).to_bits())),
+            (Self::Float64, Value::Number(n)) => Ok(Value::from(n.as_f64().unwrap().to_bits())),
+            (Self::Timestamp, Value::String(text)) => DateTime::parse_from_rfc3339(text)
+                .map(|time| Value::from(time.timestamp_millis()))
+                .map_err(|error| invalid(path, format!("invalid timestamp {:?}: {}", text, error))),
+            (Self::Array(items), Value::Array(values)) => values.iter().enumerate()
+                .map(|(i, value)| items.to_logical(value, order, &format!("{}[{}]", path, i)))
+                .collect::<Result<_, _>>().map(Value::Array),
+            (Self::Object(properties), Value::Object(object)) => {
+                if let Some(unknown) = object.keys().find(|key| properties.iter().all(|(name, _)| name != *key)) {
+                    return Err(invalid(path, format!("unknown property `{}`", unknown)));
+                }
+                properties.iter().map(|(name, schema)| {
+                    let property_path = format!("{}.{}", path, name);
+                    let value = match (object.get(name), schema) {
+                        (Some(value), _) => value,
+                        (None, Self::Optional(_)) => &NULL,
+                        (None, _) => return Err(invalid(path, format!("missing property `{}`", name))),
+                    };
+                    Ok((name.clone(), schema.to_logical(value, order, &property_path)?))
+                }).collect::<Result<_, _>>().map(Value::Object)
+            }
+            (Self::Optional(_), Value::Null) => Ok(Value::from("none")),
+            (Self::Optional(inner), _) => Ok(json!({"some": inner.to_logical(value, order, path)?})),
+            (Self::Enum(names), Value::String(name)) if names.contains(name) => Ok(value.clone()),
+            (Self::Enum(names), _) => Err(expected(&format!("one of {:?}", names))),
+            (Self::Bool, _) => Err(expected("a boolean")),
+            (Self::Integer { .. } | Self::Float32 | Self::Float64, _) => Err(expected("a number")),
+            (Self::String | Self::BoundedString { .. } | Self::Timestamp, _) => Err(expected("a string")),
+            (Self::Array(_), _) => Err(expected("an array")),
+            (Self::Object(_), _) => Err(expected("an object")),
+        }
+    }
+
+    /// Reverses [TydiSchema::to_logical] on the canonical values given by [decode_value] for binaries packed in
+    /// [order].
+    fn to_json(&self, value: Value, order: TydiBitOrder, path: &str) -> Result<Value, TydiError> {
+        let unexpected = |value: &Value| invalid(path, format!("unexpected {} for {:?}", value, self));
+        let bits = |value: &Value| value.as_u64().ok_or_else(|| unexpected(value));
+        match self {
+            Self::Bool => Ok(Value::Bool(bits(&value)? != 0)),
+            Self::Integer { bits: width, signed: true } => {
+                let shift = 64 - width;
+                Ok(Value::from(((bits(&value)? << shift) as i64) >> shift))
+            }
+            Self::Integer { .. } | Self::Enum(_) => Ok(value),
+            Self::Float32 => Ok(Value::from(f32::from_bits(bits(&value)? as u32))),
+            Self::Float64 => Ok(Value::from(f64::from_bits(bits(&value)?))),
+            Self::String => {
+                let bytes = value.as_array().ok_or_else(|| unexpected(&value))?.iter()
+                    .map(|byte| byte.as_u64().map(|byte| byte as u8).ok_or_else(|| unexpected(byte)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Value::String(String::from_utf8(bytes).map_err(TydiError::InvalidUtf8)?))
+            }
+            Self::BoundedString { max_len } => {
+                let length = bits(&value["length"])?.min(*max_len as u64);
+                let items = pack_bits(&value["items"], max_len * 8, order, path)?;
+                let mut reader = TydiBitReader::new(&items);
+                let bytes = (0..length).map(|_| reader.read::<u8>()).collect();
+                Ok(Value::String(String::from_utf8(bytes).map_err(TydiError::InvalidUtf8)?))
+            }
+            Self::Timestamp => DateTime::<Utc>::from_timestamp_millis(bits(&value)? as i64)
+                .map(|time| Value::String(time.to_rfc3339_opts(SecondsFormat::AutoSi, true)))
+                .ok_or_else(|| unexpected(&value)),
+            Self::Array(items) => match value {
+                Value::Array(values) => values.into_iter().enumerate()
+                    .map(|(i, value)| items.to_json(value, order, &format!("{}[{}]", path, i)))
+                    .collect::<Result<_, _>>().map(Value::Array),
+                _ => Err(unexpected(&value)),
+            },
+            Self::Object(properties) => match value {
+                Value::Object(mut object) => properties.iter().map(|(name, schema)| {
+                    let value = object.remove(name).ok_or_else(|| invalid(path, format!("missing property `{}`", name)))?;
+                    Ok((name.clone(), schema.to_json(value, order, &format!("{}.{}", path, name))?))
+                }).collect::<Result<_, _>>().map(Value::Object),
+                _ => Err(unexpected(&value)),
+            },
+            Self::Optional(inner) => match value {
+                Value::Object(mut object) if object.contains_key("some") => inner.to_json(object.remove("some").unwrap(), order, path),
+                _ => Ok(Value::Null),
+            },
+        }
+    }
+}
+
+/// Encodes and decodes JSON values of a [TydiSchema], the way [crate::bundle::TydiBundle] does for derived types.
+///
+/// Objects are split into the fields of a group, and arrays and strings into child streams, as [TydiStream::drill]
+/// does. The root is a single value, so a dataset of items is an [TydiSchema::Array] of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DynamicTydi {
+    schema: TydiSchema,
+    logical_type: TydiLogicalType,
+}
+
+impl DynamicTydi {
+    pub fn new(schema: TydiSchema) -> Self {
+        Self::try_new(schema).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Like [DynamicTydi::new], returning [TydiError::InvalidSchema] if an integer of [schema] does not take 1 to 64
+    /// bits.
+    pub fn try_new(schema: TydiSchema) -> Result<Self, TydiError> {
+        schema.check("$")?;
+        let logical_type = schema.logical_type();
+        Ok(Self { schema, logical_type })
+    }
+
+    pub fn schema(&self) -> &TydiSchema {
+        &self.schema
+    }
+
+    pub fn logical_type(&self) -> &TydiLogicalType {
+        &self.logical_type
+    }
+
+    /// The physical streams of values of the schema, named after [name].
+    pub fn physical_streams(&self, name: &str) -> Vec<TydiPhysicalStream> {
+        self.logical_type.split(name)
+    }
+
+    /// Encodes [value] into the binaries of the physical streams named after [name], each along with its stream.
+    ///
+    /// Returns [TydiError::InvalidValue] if [value] does not follow the schema, with the path to the offending part.
+    pub fn encode(&self, name: &str, value: &Value) -> Result<Vec<(TydiPhysicalStream, TydiBinaryStream)>, TydiError> {
+        self.encode_in(name, value, TydiBitOrder::PORTABLE)
+    }
+
+    /// Like [DynamicTydi::encode], packing the binaries in [order].
+    pub fn encode_in(&self, name: &str, value: &Value, order: TydiBitOrder)
+                     -> Result<Vec<(TydiPhysicalStream, TydiBinaryStream)>, TydiError> {
+        encode_value(name, &self.logical_type, &self.schema.to_logical(value, order, "$")?, order)
+    }
+
+    /// Rebuilds the value from the binaries of its physical streams, in the order of [DynamicTydi::physical_streams].
+    ///
+    /// Optional properties without value come back as null, and timestamps in UTC. The binaries are unpacked in the
+    /// order they were packed in.
+    pub fn decode(&self, streams: &[TydiBinaryStream]) -> Result<Value, TydiError> {
+        let order = streams.iter().find_map(|stream| stream.0.first()).map_or(TydiBitOrder::PORTABLE, |binary| binary.order);
+        self.schema.to_json(decode_value(&self.logical_type, streams)?, order, "$")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::pack_bounded;
+    use crate::bundle::TydiBundle;
+    use crate::logical::TydiLogical;
+    use crate::logical::TydiLogicalType::{Bits, Null};
+    use crate::order::{TydiBitOrder, TydiFieldOrder};
+    use crate::til::parse_til;
+    use crate::Tydi;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Default, Tydi)]
+    struct Article {
+        id: u32,
+        score: Option<i16>,
+        title: String,
+        tags: Vec<String>,
+    }
+
+    fn binaries(streams: &[(TydiPhysicalStream, TydiBinaryStream)]) -> Vec<TydiBinaryStream> {
+        streams.iter().map(|(_, binary)| TydiBinaryStream(binary.0.clone())).collect()
+    }
+
+    #[test]
+    fn test_matches_derived() {
+        let articles = vec![
+            Article { id: 1, score: Some(-3), title: "Hi".to_string(), tags: vec!["a".to_string(), "".to_string()] },
+            Article { id: 2, score: None, title: "".to_string(), tags: vec![] },
+        ];
+        let value = json!([
+            {"id": 1, "score": {"some": -3}, "title": "Hi", "tags": ["a", ""]},
+            {"id": 2, "score": "none", "title": "", "tags": []},
+        ]);
+        let t = Vec::<Article>::logical_type();
+        let encoded = encode_value("articles", &t, &value, TydiBitOrder::PORTABLE).unwrap();
+        let binary_bundle = TydiBundle::new(articles).finish();
+        let derived = binary_bundle.physical_streams("articles");
+        assert_eq!(encoded.len(), derived.len());
+        for ((physical, binary), (derived_physical, derived_binary)) in encoded.iter().zip(derived) {
+            assert_eq!(physical, &derived_physical);
+            assert_eq!(binary.0, derived_binary.0);
+        }
+
+        // Strings come back as their bytes
+        assert_eq!(decode_value(&t, &binaries(&encoded)).unwrap(), json!([
+            {"id": 1, "score": {"some": 65533}, "title": [72, 105], "tags": [[97], []]},
+            {"id": 2, "score": "none", "title": [], "tags": []},
+        ]));
+    }
+
+    #[test]
+    fn test_til_roundtrip() {
+        let namespaces = parse_til("namespace sensor {
+            type reading = Group(value: Bits(12), unit: Union(none: Null, celsius: Bits(4)));
+            type samples = Stream(data: reading, dimensionality: 1, throughput: 2.0);
+            type burst = Stream(data: Group(id: Bits(72), samples: samples), dimensionality: 1, synchronicity: Flatten);
+        }").unwrap();
+        let t = namespaces[0].logical_type("burst").unwrap();
+        let value = json!([
+            {"id": "0x1000000000000000ff", "samples": [{"value": 300, "unit": {"celsius": 2}}, {"value": 1}]},
+            {"id": 7, "samples": []},
+        ]);
+        let encoded = encode_value("burst", t, &value, TydiBitOrder::PORTABLE).unwrap();
+        assert_eq!(encoded.iter().map(|(physical, binary)| (physical.name(), binary.0.len())).collect::<Vec<_>>(),
+                   vec![("burst".to_string(), 2), ("burst__samples".to_string(), 3)]);
+        assert_eq!(decode_value(t, &binaries(&encoded)).unwrap(), json!([
+            {"id": "0x1000000000000000ff", "samples": [{"value": 300, "unit": {"celsius": 2}}, {"value": 1, "unit": "none"}]},
+            {"id": "0x000000000000000007", "samples": []},
+        ]));
+
+        // A stream without data takes its sequences from its child
+        let nested = TydiLogicalType::stream(TydiLogicalType::stream(Bits(8), 1), 1);
+        let value = json!([[1, 2], [], [3]]);
+        let encoded = encode_value("nested", &nested, &value, TydiBitOrder::PORTABLE).unwrap();
+        assert_eq!(encoded.len(), 1);
+        assert_eq!(decode_value(&nested, &binaries(&encoded)).unwrap(), value);
+    }
+
+    #[test]
+    fn test_errors() {
+        let t = TydiLogicalType::stream(TydiLogicalType::group(vec![
+            ("small", Bits(4)),
+            ("unit", TydiLogicalType::union(vec![("none", Null), ("celsius", Bits(4))])),
+        ]), 1);
+        let reason = |value: Value| match encode_value("t", &t, &value, TydiBitOrder::PORTABLE) {
+            Err(TydiError::InvalidValue { path, reason }) => format!("{}: {}", path, reason),
+            other => panic!("expected an invalid value, got {:?}", other),
+        };
+        assert_eq!(reason(json!([{"small": 16}])), "$.small: 16 does not fit in 4 bits");
+        assert_eq!(reason(json!([{"small": -9}])), "$.small: -9 does not fit in 4 bits");
+        assert_eq!(reason(json!([{"large": 1}])), "$: unknown field `large`");
+        assert_eq!(reason(json!([{"unit": "kelvin"}])), "$.unit: unknown variant `kelvin`");
+        assert_eq!(reason(json!({"small": 1})), "$: expected an array, got {\"small\":1}");
+        assert!(encode_value("t", &t, &json!([{"small": -8}]), TydiBitOrder::PORTABLE).is_ok());
+
+        let encoded = encode_value("t", &t, &json!([{"small": 1}]), TydiBitOrder::PORTABLE).unwrap();
+        assert!(matches!(decode_value(&t, &[]), Err(TydiError::InvalidValue { .. })));
+        let mut streams = binaries(&encoded);
+        streams[0].0[0].len -= 1;
+        assert_eq!(decode_value(&t, &streams), Err(TydiError::Truncated { needed: 11, available: 10 }));
+    }
+
+    fn readings() -> DynamicTydi {
+        DynamicTydi::new(TydiSchema::array(TydiSchema::object(vec![
+            ("valid", TydiSchema::Bool),
+            ("offset", TydiSchema::Integer { bits: 12, signed: true }),
+            ("value", TydiSchema::Float32),
+            ("precise", TydiSchema::Float64),
+            ("at", TydiSchema::Timestamp),
+            ("unit", TydiSchema::Enum(vec!["celsius".to_string(), "kelvin".to_string()])),
+            ("label", TydiSchema::optional(TydiSchema::String)),
+            ("station", TydiSchema::BoundedString { max_len: 10 }),
+            ("history", TydiSchema::array(TydiSchema::Integer { bits: 8, signed: false })),
+        ])))
+    }
+
+    #[test]
+    fn test_schema_roundtrip() {
+        let dynamic = readings();
+        let value = json!([
+            {"valid": true, "offset": -2048, "value": 0.5, "precise": -1.25, "at": "2025-06-15T10:30:00.250Z",
+             "unit": "kelvin", "label": "outdoor ☀", "station": "Delft ☀", "history": [1, 255]},
+            {"valid": false, "offset": 7, "value": 3, "precise": 0.1, "at": "2025-06-15T12:30:00+02:00",
+             "unit": "celsius", "station": "", "history": []},
+        ]);
+        let streams = dynamic.encode("readings", &value).unwrap();
+        assert_eq!(streams.iter().map(|(physical, _)| physical.name()).collect::<Vec<_>>(),
+                   vec!["readings", "readings__label__some", "readings__history"]);
+        assert_eq!(streams[0].0, dynamic.physical_streams("readings")[0]);
+
+        let mut expected = value.clone();
+        expected[1]["value"] = json!(3.0);
+        expected[1]["at"] = json!("2025-06-15T10:30:00Z");
+        expected[1]["label"] = Value::Null;
+        assert_eq!(dynamic.decode(&binaries(&streams)).unwrap(), expected);
+        let big_msb_first = TydiBitOrder::new(TydiEndianness::Big, TydiFieldOrder::MsbFirst);
+        let msb_first = TydiBitOrder::new(TydiEndianness::Little, TydiFieldOrder::MsbFirst);
+        for order in [big_msb_first, msb_first] {
+            let streams = dynamic.encode_in("readings", &value, order).unwrap();
+            assert_eq!(streams[0].1.0[0].order, order);
+            assert_eq!(dynamic.decode(&binaries(&streams)).unwrap(), expected);
+        }
+
+        // Bounded strings are packed as bounded sequences of bytes, in any order
+        for (order, max_len) in [(TydiBitOrder::PORTABLE, 6), (big_msb_first, 6), (big_msb_first, 10), (msb_first, 6)] {
+            let station = TydiSchema::BoundedString { max_len };
+            let value = station.to_logical(&json!("Delft"), order, "$").unwrap();
+            assert_eq!(pack(&station.logical_type(), &value, order, "$").unwrap(), pack_bounded(b"Delft".to_vec(), max_len, order));
+            assert_eq!(station.to_json(value, order, "$").unwrap(), json!("Delft"));
+        }
+    }
+
+    #[test]
+    fn test_schema_errors() {
+        let dynamic = readings();
+        let reason = |value: Value| match dynamic.encode("readings", &value) {
+            Err(TydiError::InvalidValue { path, reason }) => format!("{}: {}", path, reason),
+            other => panic!("expected an invalid value, got {:?}", other),
+        };
+        let reading = json!({"valid": true, "offset": 0, "value": 0, "precise": 0, "at": "2025-06-15T10:30:00Z",
+                             "unit": "celsius", "station": "x", "history": []});
+        let with = |key: &str, value: Value| {
+            let mut reading = reading.clone();
+            reading[key] = value;
+            json!([reading.clone(), reading])
+        };
+        assert!(dynamic.encode("readings", &json!([reading])).is_ok());
+        assert_eq!(reason(with("offset", json!(2048))), "$[0].offset: 2048 is out of the range -2048..=2047");
+        assert_eq!(reason(with("history", json!([1, -1]))), "$[0].history[1]: -1 is out of the range 0..=255");
+        assert_eq!(reason(with("unit", json!("fahrenheit"))),
+                   "$[0].unit: expected one of [\"celsius\", \"kelvin\"], got \"fahrenheit\"");
+        assert_eq!(reason(with("valid", Value::Null)), "$[0].valid: expected a boolean, got null");
+        assert!(reason(with("at", json!("yesterday"))).starts_with("$[0].at: invalid timestamp \"yesterday\""));
+        assert_eq!(reason(with("station", json!("Delft ☀☀"))), "$[0].station: 12 bytes do not fit in a string of at most 10");
+        assert_eq!(reason(json!([{"valid": true}])), "$[0]: missing property `offset`");
+        assert_eq!(reason(json!([{"colour": 1}])), "$[0]: unknown property `colour`");
+
+        let text = DynamicTydi::new(TydiSchema::String);
+        let streams = encode_value("text", text.logical_type(), &json!([0xff]), TydiBitOrder::PORTABLE).unwrap();
+        assert!(matches!(text.decode(&binaries(&streams)), Err(TydiError::InvalidUtf8(_))));
+
+        let schema_reason = |schema: TydiSchema| match DynamicTydi::try_new(schema) {
+            Err(TydiError::InvalidSchema { path, reason }) => format!("{}: {}", path, reason),
+            other => panic!("expected an invalid schema, got {:?}", other),
+        };
+        assert_eq!(schema_reason(TydiSchema::Integer { bits: 0, signed: true }), "$: an integer takes 1 to 64 bits, not 0");
+        let wide = TydiSchema::object(vec![("id", TydiSchema::optional(TydiSchema::Integer { bits: 128, signed: false }))]);
+        assert_eq!(schema_reason(TydiSchema::array(wide)), "$[].id: an integer takes 1 to 64 bits, not 128");
+
+        let longs = DynamicTydi::try_new(TydiSchema::array(TydiSchema::Integer { bits: 64, signed: true })).unwrap();
+        let streams = longs.encode("longs", &json!([i64::MIN, -1])).unwrap();
+        assert_eq!(longs.decode(&binaries(&streams)).unwrap(), json!([i64::MIN, -1]));
+    }
+}
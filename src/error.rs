@@ -0,0 +1,152 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::Display;
+use std::string::FromUtf8Error;
+
+/// Errors that occur when packing or unpacking malformed data, returned by the `try_` variants of functions that
+/// otherwise panic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TydiError {
+    /// A binary is not of the size it is expected to be.
+    SizeMismatch { expected: usize, actual: usize },
+    /// A binary holds fewer bits than are needed.
+    Truncated { needed: usize, available: usize },
+    /// The `last` flags of the packet at [index] do not fit the stream.
+    MalformedLast { index: usize, reason: &'static str },
+    /// The packet at [index] carries no data where it is required.
+    MissingData { index: usize },
+    /// A union tag selects none of the [variants] of the union.
+    InvalidTag { tag: usize, variants: usize },
+    /// A code point is not a valid `char`.
+    InvalidChar(u32),
+    /// A sequence of bytes is not valid UTF-8.
+    InvalidUtf8(FromUtf8Error),
+    /// A simulation trace cannot be parsed at [line], counting from 1.
+    InvalidTrace { line: usize, reason: String },
+    /// A simulation trace has no signal called [name].
+    MissingSignal { name: String },
+    /// A TIL document cannot be parsed at [line], counting from 1.
+    InvalidTil { line: usize, reason: String },
+    /// An unpacked [value] lies outside the range of the type it is unpacked into.
+    OutOfRange { value: String, reason: String },
+    /// A JSON value does not fit the type it is encoded as, or a set of binary streams does not decode into one. [path]
+    /// leads from the root of the value to the offending part.
+    InvalidValue { path: String, reason: String },
+    /// A JSON Schema cannot be imported at the JSON pointer [path].
+    InvalidSchema { path: String, reason: String },
+}
+
+impl Display for TydiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TydiError::SizeMismatch { expected, actual } => {
+                write!(f, "expected a binary of {} bits, got {} bits", expected, actual)
+            }
+            TydiError::Truncated { needed, available } => {
+                write!(f, "binary is truncated, {} bits are needed but only {} are available", needed, available)
+            }
+            TydiError::MalformedLast { index, reason } => write!(f, "malformed last flags at index {}: {}", index, reason),
+            TydiError::MissingData { index } => write!(f, "packet at index {} carries no data", index),
+            TydiError::InvalidTag { tag, variants } => {
+                write!(f, "tag {} selects none of the {} variants of the union", tag, variants)
+            }
+            TydiError::InvalidChar(code_point) => write!(f, "{:#x} is not a valid char", code_point),
+            TydiError::InvalidUtf8(error) => write!(f, "invalid UTF-8: {}", error),
+            TydiError::InvalidTrace { line, reason } => write!(f, "invalid trace at line {}: {}", line, reason),
+            TydiError::MissingSignal { name } => write!(f, "trace has no signal {}", name),
+            TydiError::InvalidTil { line, reason } => write!(f, "invalid TIL at line {}: {}", line, reason),
+            TydiError::OutOfRange { value, reason } => write!(f, "{} is out of range: {}", value, reason),
+            TydiError::InvalidValue { path, reason } => write!(f, "invalid value at {}: {}", path, reason),
+            TydiError::InvalidSchema { path, reason } => write!(f, "invalid schema at {}: {}", path, reason),
+        }
+    }
+}
+
+impl Error for TydiError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TydiError::InvalidUtf8(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<FromUtf8Error> for TydiError {
+    fn from(value: FromUtf8Error) -> Self {
+        TydiError::InvalidUtf8(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::{FromTydiBinary, TydiBinary};
+    use crate::drilling::{try_packets_from_binaries, TydiConvert, TydiPacktestToBinary};
+    use crate::{Tydi, TydiBinaryStream, TydiPacket, TydiStream};
+
+    #[test]
+    fn test_binary_errors() {
+        assert_eq!(TydiBinary::try_new(vec![0xFF], 9), Err(TydiError::Truncated { needed: 9, available: 8 }));
+        let binary = TydiBinary::new(vec![0xFF, 0x01], 12);
+        assert_eq!(binary.try_split(13).unwrap_err(), TydiError::Truncated { needed: 13, available: 12 });
+        assert!(binary.try_split_for::<u16>().is_err());
+        assert_eq!(binary.try_split_for::<u8>().unwrap().0, 0xFF);
+
+        let packet = TydiPacket { data: Some(5u16), last: vec![true] };
+        assert_eq!(packet.try_to_binary(8), Err(TydiError::SizeMismatch { expected: 8, actual: 16 }));
+    }
+
+    #[test]
+    fn test_corrupt_capture() {
+        let strings = vec!["Tydi".to_string(), "Rust".to_string()];
+        let mut capture = strings.convert().drill(|e| e.into_bytes()).finish(8);
+        // Cut the final packet short, as a capture ending mid-transfer would
+        let last = capture.0.last_mut().unwrap();
+        *last = last.split(5).0;
+        let error = try_packets_from_binaries::<u8>(capture, 2).unwrap_err();
+        assert_eq!(error, TydiError::Truncated { needed: 1 + 2 + 8, available: 5 });
+
+        let invalid = TydiStream(vec![
+            TydiPacket { data: Some(0xC3u8), last: vec![false, false] },
+            TydiPacket { data: Some(0x28u8), last: vec![true, true] },
+        ]);
+        assert!(matches!(invalid.try_solidify_into_strings(), Err(TydiError::InvalidUtf8(_))));
+
+        let flat = TydiStream(vec![TydiPacket { data: Some(1u8), last: vec![] }]);
+        assert_eq!(TydiStream(flat.0.clone()).try_vectorize_inner().unwrap_err(), TydiError::MalformedLast {
+            index: 0,
+            reason: "no dimension left to consume",
+        });
+        let mut parents = vec![vec![0u8]].convert();
+        assert!(parents.try_inject(|e| e, flat).is_err());
+        assert_eq!(TydiStream::<u8>(vec![TydiPacket { data: None, last: vec![true] }]).try_unpack(),
+                   Err(TydiError::MissingData { index: 0 }));
+        assert!(try_packets_from_binaries::<u8>(TydiBinaryStream(vec![]), 1).unwrap().0.is_empty());
+    }
+
+    #[derive(Debug, PartialEq, Clone, Tydi)]
+    enum Reading {
+        Missing,
+        Celsius(i16),
+        Label(char),
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        // Three variants take a two-bit tag, leaving tag 3 unused
+        let mut binary: TydiBinary = Reading::Celsius(-4).into();
+        binary.data[0] |= 0b11;
+        assert_eq!(TydiPacket::<Reading>::try_from_binary(TydiBinary::from(true).concatenate(&binary), 0),
+                   Err(TydiError::InvalidTag { tag: 3, variants: 3 }));
+
+        let surrogate = TydiBinary::from(true).concatenate(&TydiBinary::from(0xD800u32));
+        assert_eq!(TydiPacket::<char>::try_from_binary(surrogate, 0), Err(TydiError::InvalidChar(0xD800)));
+        let tag = TydiBinary::from(Reading::Label('a')).split(2).0;
+        let packed = tag.concatenate(&TydiBinary::from(0x11_0000u32));
+        assert_eq!(Reading::try_from_tydi_binary(packed).unwrap_err(), TydiError::InvalidChar(0x11_0000));
+
+        // The data of a packet without a strobe is not decoded
+        let empty = TydiBinary::from(false).concatenate(&TydiBinary::from(0xD800u32));
+        assert_eq!(TydiPacket::<char>::try_from_binary(empty, 0), Ok(TydiPacket { data: None, last: vec![] }));
+    }
+}
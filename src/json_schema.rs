@@ -0,0 +1,316 @@
+use serde_json::{Map, Value};
+use crate::dynamic::TydiSchema;
+use crate::error::TydiError;
+
+fn invalid(path: &str, reason: impl Into<String>) -> TydiError {
+    TydiError::InvalidSchema { path: path.to_string(), reason: reason.into() }
+}
+
+/// Smallest integer schema holding all integers from [min] to [max], where a missing bound is that of 64-bit integers.
+///
+/// Without negative values the integer is unsigned.
+fn integer(min: Option<i128>, max: Option<i128>, path: &str) -> Result<TydiSchema, TydiError> {
+    if let (Some(min), Some(max)) = (min, max) && min > max {
+        return Err(invalid(path, format!("no integers lie between {} and {}", min, max)));
+    }
+    let signed = min.is_none_or(|min| min < 0);
+    let min = min.unwrap_or(i64::MIN.into());
+    let max = max.unwrap_or(if signed { i64::MAX.into() } else { u64::MAX.into() });
+    // A single bound beyond the 64-bit range leaves the other bound on the wrong side of it
+    let bits = (min <= max).then(|| (1..=64).find(|&bits| {
+        let (low, high) = if signed { (-1i128 << (bits - 1), (1i128 << (bits - 1)) - 1) } else { (0, (1i128 << bits) - 1) };
+        low <= min && max <= high
+    })).flatten();
+    match bits {
+        Some(bits) => Ok(TydiSchema::Integer { bits, signed }),
+        None => Err(invalid(path, "the range of the integer exceeds 64 bits")),
+    }
+}
+
+/// Longest `maxLength` of a string that is packed inline, beyond which strings are streams of bytes as if unbounded,
+/// as an inline string takes the bits of its longest value in every element.
+const MAX_INLINE_LENGTH: u64 = 1024;
+
+/// The bound of an integer given by [inclusive] and [exclusive], such as `minimum` and `exclusiveMinimum`, with
+/// [direction] 1 for lower bounds and -1 for upper bounds.
+///
+/// Both the numeric `exclusiveMinimum` of recent drafts and the boolean of draft 4 are understood. Whole limits are
+/// read as integers, as 64-bit integers are not all exact as floating point numbers.
+fn bound(schema: &Map<String, Value>, inclusive: &str, exclusive: &str, direction: i128) -> Option<i128> {
+    // Turned around for upper bounds, the bound is the smallest integer at or above the limits
+    let limit = |key: &str, exclusive: bool| {
+        let value = schema.get(key)?;
+        match value.as_i64().map(i128::from).or_else(|| value.as_u64().map(i128::from)) {
+            Some(limit) => Some(limit * direction + exclusive as i128),
+            None => value.as_f64().map(|limit| limit * direction as f64)
+                .map(|limit| (if exclusive { limit.floor() + 1.0 } else { limit.ceil() }) as i128),
+        }
+    };
+    let (inclusive, exclusive) = match schema.get(exclusive) {
+        Some(Value::Bool(true)) => (None, limit(inclusive, true)),
+        _ => (limit(inclusive, false), limit(exclusive, true)),
+    };
+    inclusive.into_iter().chain(exclusive).max().map(|bound| bound * direction)
+}
+
+/// Path of the [key] member of the schema at [path], as a JSON pointer.
+fn child(path: &str, key: &str) -> String {
+    format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"))
+}
+
+/// Imports the schemas of a JSON Schema document, following local references.
+struct Importer<'a> {
+    document: &'a Value,
+    /// The references that are being followed, as a type cannot contain itself.
+    references: Vec<String>,
+}
+
+impl Importer<'_> {
+    fn import(&mut self, schema: &Value, path: &str) -> Result<TydiSchema, TydiError> {
+        let schema = schema.as_object().ok_or_else(|| invalid(path, format!("expected a schema object, got {}", schema)))?;
+        if let Some(reference) = schema.get("$ref") {
+            return self.reference(reference, path);
+        }
+
+        let mut types: Vec<&str> = match schema.get("type") {
+            Some(Value::String(t)) => vec![t.as_str()],
+            Some(Value::Array(types)) => types.iter().map(|t| t.as_str().ok_or_else(|| invalid(path, "expected type names")))
+                .collect::<Result<_, _>>()?,
+            Some(other) => return Err(invalid(path, format!("expected a type name, got {}", other))),
+            None if schema.contains_key("properties") => vec!["object"],
+            None if schema.contains_key("items") => vec!["array"],
+            None => vec![],
+        };
+        let nullable = types.contains(&"null");
+        types.retain(|t| *t != "null");
+
+        let result = match (schema.get("enum"), types.as_slice()) {
+            (Some(values), _) => return self.enumeration(values, &child(path, "enum"), nullable),
+            (None, ["object"]) => self.object(schema, path)?,
+            (None, ["array"]) => {
+                let items = schema.get("items").ok_or_else(|| invalid(path, "an array needs `items`"))?;
+                TydiSchema::array(self.import(items, &child(path, "items"))?)
+            }
+            (None, ["string"]) => match (schema.get("format").and_then(Value::as_str), schema.get("maxLength")) {
+                (Some("date-time"), _) => TydiSchema::Timestamp,
+                (_, Some(max_len)) => match max_len.as_u64() {
+                    Some(max_len) if max_len <= MAX_INLINE_LENGTH => TydiSchema::BoundedString { max_len: max_len as usize },
+                    Some(_) => TydiSchema::String,
+                    None => return Err(invalid(path, "`maxLength` has to be a whole number")),
+                },
+                (_, None) => TydiSchema::String,
+            },
+            (None, ["integer"]) => {
+                integer(bound(schema, "minimum", "exclusiveMinimum", 1), bound(schema, "maximum", "exclusiveMaximum", -1), path)?
+            }
+            (None, ["number"]) => TydiSchema::Float64,
+            (None, ["boolean"]) => TydiSchema::Bool,
+            (None, []) => return Err(invalid(path, "expected a `type` or an `enum`")),
+            (None, [t]) => return Err(invalid(path, format!("unsupported type `{}`", t))),
+            (None, _) => return Err(invalid(path, "values of several types are only supported along with null")),
+        };
+        Ok(if nullable { TydiSchema::optional(result) } else { result })
+    }
+
+    fn reference(&mut self, reference: &Value, path: &str) -> Result<TydiSchema, TydiError> {
+        let reference = reference.as_str().ok_or_else(|| invalid(path, "expected a reference string"))?;
+        let pointer = reference.strip_prefix('#')
+            .ok_or_else(|| invalid(path, format!("only references within the document are supported, got {:?}", reference)))?;
+        let target = self.document.pointer(pointer)
+            .ok_or_else(|| invalid(path, format!("unresolved reference {:?}", reference)))?;
+        if self.references.iter().any(|r| r == reference) {
+            return Err(invalid(path, format!("recursive reference {:?}", reference)));
+        }
+        self.references.push(reference.to_string());
+        let result = self.import(target, reference);
+        self.references.pop();
+        result
+    }
+
+    /// Properties that are not `required` become optional, as they can be left out.
+    fn object(&mut self, schema: &Map<String, Value>, path: &str) -> Result<TydiSchema, TydiError> {
+        let required: Vec<&str> = match schema.get("required") {
+            Some(Value::Array(names)) => names.iter().filter_map(Value::as_str).collect(),
+            Some(_) => return Err(invalid(path, "expected the names of the required properties")),
+            None => vec![],
+        };
+        let properties = match schema.get("properties") {
+            Some(Value::Object(properties)) => properties,
+            Some(_) => return Err(invalid(path, "expected an object of properties")),
+            None => return Ok(TydiSchema::Object(vec![])),
+        };
+        let properties_path = child(path, "properties");
+        properties.iter().map(|(name, property)| {
+            let property = self.import(property, &child(&properties_path, name))?;
+            let optional = !required.contains(&name.as_str()) && !matches!(property, TydiSchema::Optional(_));
+            Ok((name.clone(), if optional { TydiSchema::optional(property) } else { property }))
+        }).collect::<Result<_, _>>().map(TydiSchema::Object)
+    }
+
+    /// Strings become an enum, integers an integer wide enough for all of them.
+    fn enumeration(&mut self, values: &Value, path: &str, nullable: bool) -> Result<TydiSchema, TydiError> {
+        let values = values.as_array().ok_or_else(|| invalid(path, "expected an array of values"))?;
+        let nullable = nullable || values.contains(&Value::Null);
+        let values: Vec<&Value> = values.iter().filter(|value| !value.is_null()).collect();
+        if values.is_empty() {
+            return Err(invalid(path, "an enum needs a value other than null"));
+        }
+        let result = if let Some(names) = values.iter().map(|value| value.as_str().map(str::to_string)).collect::<Option<Vec<_>>>() {
+            TydiSchema::Enum(names)
+        } else if let Some(numbers) = values.iter().map(|value| value.as_i64().map(i128::from).or(value.as_u64().map(i128::from)))
+            .collect::<Option<Vec<_>>>() {
+            integer(numbers.iter().copied().min(), numbers.iter().copied().max(), path)?
+        } else {
+            return Err(invalid(path, "only enums of strings or of integers are supported"));
+        };
+        Ok(if nullable { TydiSchema::optional(result) } else { result })
+    }
+}
+
+impl TydiSchema {
+    /// Imports the schema of the values described by the JSON Schema [document], from which
+    /// [TydiSchema::logical_type] gives the Tydi logical type and [crate::logical::TydiLogicalType::split] the physical
+    /// streams.
+    ///
+    /// - Objects become objects of their `properties`, in the order they are listed. Properties that are not
+    ///   `required` become optional, as do schemas that allow `null` as a type or in an `enum`.
+    /// - Arrays become arrays of their `items`.
+    /// - Strings in `date-time` format become timestamps. Strings with a `maxLength` of at most 1024 are packed inline,
+    ///   where the length is taken as a number of UTF-8 bytes; other strings are streams of bytes.
+    /// - Integers are as wide as their `minimum` and `maximum` require, unsigned if they cannot be negative and 64 bits
+    ///   wide if unbounded. Integers beyond the range of 64-bit integers are rejected.
+    /// - Numbers become 64-bit floating point numbers, and booleans single bits.
+    /// - An `enum` of strings becomes a union with a variant per string, and one of integers an integer of their range.
+    ///
+    /// `$ref`s within the document are followed. Other keywords, such as `additionalProperties`, are ignored.
+    pub fn from_json_schema(document: &Value) -> Result<Self, TydiError> {
+        Importer { document, references: Vec::new() }.import(document, "#")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::logical::TydiLogicalType::{self, Bits, Null};
+
+    fn import(schema: Value) -> Result<TydiSchema, String> {
+        TydiSchema::from_json_schema(&schema).map_err(|error| error.to_string())
+    }
+
+    #[test]
+    fn test_integers() {
+        let integer = |bits, signed| Ok(TydiSchema::Integer { bits, signed });
+        assert_eq!(import(json!({"type": "integer", "minimum": 0, "maximum": 255})), integer(8, false));
+        assert_eq!(import(json!({"type": "integer", "minimum": 1, "maximum": 100})), integer(7, false));
+        assert_eq!(import(json!({"type": "integer", "minimum": 0, "exclusiveMaximum": 256})), integer(8, false));
+        assert_eq!(import(json!({"type": "integer", "minimum": 0, "maximum": 255.5})), integer(8, false));
+        assert_eq!(import(json!({"type": "integer", "minimum": -1, "maximum": 1})), integer(2, true));
+        assert_eq!(import(json!({"type": "integer", "minimum": -129, "maximum": 0})), integer(9, true));
+        assert_eq!(import(json!({"type": "integer", "minimum": -1, "exclusiveMinimum": true, "maximum": 3})), integer(2, false));
+        assert_eq!(import(json!({"type": "integer", "minimum": 0})), integer(64, false));
+        assert_eq!(import(json!({"type": "integer", "maximum": 10})), integer(64, true));
+        assert_eq!(import(json!({"enum": [1, 5, 9]})), integer(4, false));
+        assert_eq!(import(json!({"type": "integer", "minimum": 2, "maximum": 1})),
+                   Err("invalid schema at #: no integers lie between 2 and 1".to_string()));
+        assert_eq!(import(json!({"type": "integer", "minimum": -1, "maximum": u64::MAX})),
+                   Err("invalid schema at #: the range of the integer exceeds 64 bits".to_string()));
+        assert_eq!(import(json!({"type": "integer", "minimum": 1u64 << 63})), integer(64, false));
+        // Bounds at the ends of the 64-bit ranges, which floating point numbers would round
+        assert_eq!(import(json!({"type": "integer", "minimum": i64::MIN, "maximum": i64::MAX})), integer(64, true));
+        assert_eq!(import(json!({"type": "integer", "minimum": 0, "maximum": u64::MAX})), integer(64, false));
+        assert_eq!(import(json!({"type": "integer", "minimum": i64::MIN >> 1, "maximum": i64::MAX >> 1})), integer(63, true));
+        assert_eq!(import(json!({"type": "integer", "minimum": 0, "maximum": u64::MAX >> 1})), integer(63, false));
+        assert_eq!(import(json!({"type": "integer", "minimum": 0, "exclusiveMaximum": 1u64 << 63})), integer(63, false));
+        assert_eq!(import(json!({"type": "integer", "exclusiveMinimum": i64::MIN, "maximum": -1})), integer(64, true));
+        assert_eq!(import(json!({"type": "integer", "minimum": i64::MIN, "maximum": u64::MAX})),
+                   Err("invalid schema at #: the range of the integer exceeds 64 bits".to_string()));
+        for single in [json!({"minimum": 1e20}), json!({"maximum": 1e19}), json!({"maximum": -1e19}), json!({"minimum": -1e19})] {
+            let mut schema = single.clone();
+            schema["type"] = json!("integer");
+            assert_eq!(import(schema), Err("invalid schema at #: the range of the integer exceeds 64 bits".to_string()), "{}", single);
+        }
+    }
+
+    #[test]
+    fn test_objects() {
+        let schema = import(json!({
+            "type": "array",
+            "items": {
+                "type": "object",
+                "properties": {
+                    "id": {"type": "integer", "minimum": 0, "maximum": 65535},
+                    "name": {"type": "string", "maxLength": 16},
+                    "at": {"type": "string", "format": "date-time"},
+                    "unit": {"enum": ["celsius", "kelvin", null]},
+                    "value": {"type": ["number", "null"]},
+                    "tags": {"type": "array", "items": {"type": "string"}},
+                    "valid": {"type": "boolean"}
+                },
+                "required": ["id", "name", "at", "unit", "value", "tags"]
+            }
+        })).unwrap();
+        assert_eq!(schema, TydiSchema::array(TydiSchema::object(vec![
+            ("id", TydiSchema::Integer { bits: 16, signed: false }),
+            ("name", TydiSchema::BoundedString { max_len: 16 }),
+            ("at", TydiSchema::Timestamp),
+            ("unit", TydiSchema::optional(TydiSchema::Enum(vec!["celsius".to_string(), "kelvin".to_string()]))),
+            ("value", TydiSchema::optional(TydiSchema::Float64)),
+            ("tags", TydiSchema::array(TydiSchema::String)),
+            ("valid", TydiSchema::optional(TydiSchema::Bool)),
+        ])));
+
+        let element = match schema.logical_type() {
+            TydiLogicalType::Stream(stream) => stream.element,
+            other => panic!("expected a stream, got {:?}", other),
+        };
+        let unit = TydiLogicalType::union(vec![
+            ("none", Null),
+            ("some", TydiLogicalType::union(vec![("celsius", Null), ("kelvin", Null)])),
+        ]);
+        assert_eq!(element.without_streams(), TydiLogicalType::group(vec![
+            ("id", Bits(16)),
+            ("name", TydiLogicalType::group(vec![("length", Bits(5)), ("items", Bits(128))])),
+            ("at", Bits(64)),
+            ("unit", unit),
+            ("value", TydiLogicalType::union(vec![("none", Null), ("some", Bits(64))])),
+            ("tags", Null),
+            ("valid", TydiLogicalType::union(vec![("none", Null), ("some", Bits(1))])),
+        ]));
+        let streams = schema.logical_type().split("readings");
+        assert_eq!(streams.iter().map(|s| (s.name(), s.dimensionality, s.element_width())).collect::<Vec<_>>(), vec![
+            ("readings".to_string(), 1, 16 + 5 + 128 + 64 + 2 + 65 + 2),
+            ("readings__tags".to_string(), 3, 8),
+        ]);
+
+        // Strings with a large `maxLength` are streams rather than groups of their full width
+        assert_eq!(import(json!({"type": "string", "maxLength": 1024})), Ok(TydiSchema::BoundedString { max_len: 1024 }));
+        assert_eq!(import(json!({"type": "string", "maxLength": 1025})), Ok(TydiSchema::String));
+        assert_eq!(import(json!({"type": "string", "maxLength": u64::MAX})), Ok(TydiSchema::String));
+    }
+
+    #[test]
+    fn test_references_and_errors() {
+        let schema = import(json!({
+            "$defs": {"user/id": {"type": "integer", "minimum": 0, "maximum": 1023}},
+            "type": "object",
+            "properties": {"author": {"$ref": "#/$defs/user~1id"}, "editor": {"$ref": "#/$defs/user~1id"}},
+            "required": ["author"]
+        }));
+        let id = TydiSchema::Integer { bits: 10, signed: false };
+        assert_eq!(schema, Ok(TydiSchema::object(vec![("author", id.clone()), ("editor", TydiSchema::optional(id))])));
+
+        assert_eq!(import(json!({"$defs": {"node": {"type": "array", "items": {"$ref": "#/$defs/node"}}}, "$ref": "#/$defs/node"})),
+                   Err("invalid schema at #/$defs/node/items: recursive reference \"#/$defs/node\"".to_string()));
+        assert_eq!(import(json!({"$ref": "https://example.com/post.json"})),
+                   Err("invalid schema at #: only references within the document are supported, \
+                        got \"https://example.com/post.json\"".to_string()));
+        assert_eq!(import(json!({"type": "object", "properties": {"a/b": {"type": "null"}}})),
+                   Err("invalid schema at #/properties/a~1b: expected a `type` or an `enum`".to_string()));
+        assert_eq!(import(json!({"type": ["string", "integer"]})),
+                   Err("invalid schema at #: values of several types are only supported along with null".to_string()));
+        assert_eq!(import(json!({"type": "array"})), Err("invalid schema at #: an array needs `items`".to_string()));
+        assert_eq!(import(json!({"enum": [true]})),
+                   Err("invalid schema at #/enum: only enums of strings or of integers are supported".to_string()));
+    }
+}
@@ -0,0 +1,283 @@
+use std::collections::HashSet;
+use crate::logical::{union_tag_width, TydiLogicalType, TydiPhysicalStream};
+use crate::order::TydiFieldOrder;
+
+/// Separator of the parts of a generated identifier, which a [sanitize]d part cannot contain.
+pub(crate) const SEPARATOR: &str = "__";
+
+/// [name] as a part of a generated identifier: ASCII letters and digits separated by single underscores, starting with
+/// a letter.
+pub(crate) fn sanitize(name: &str) -> String {
+    let mut result = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            result.push(c);
+        } else if !result.is_empty() && !result.ends_with('_') {
+            result.push('_');
+        }
+    }
+    if result.ends_with('_') {
+        result.pop();
+    }
+    if result.is_empty() {
+        "unnamed".to_string()
+    } else if result.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("n{}", result)
+    } else {
+        result
+    }
+}
+
+/// [sanitize]d [parts] joined by [SEPARATOR], which differs for every distinct sequence of sanitized parts.
+pub(crate) fn join<S: AsRef<str>>(parts: &[S]) -> String {
+    parts.iter().map(|part| sanitize(part.as_ref())).collect::<Vec<_>>().join(SEPARATOR)
+}
+
+/// [path] followed by [rest].
+pub(crate) fn extended<S: AsRef<str>>(path: &[String], rest: &[S]) -> Vec<String> {
+    path.iter().cloned().chain(rest.iter().map(|part| part.as_ref().to_string())).collect()
+}
+
+/// [sanitize]d [names] of the fields of a group or the variants of a union, numbering those that clash with an earlier
+/// one when ignoring case and underscores, as some languages and the `PascalCase` of Chisel do.
+pub(crate) fn distinct<S: AsRef<str>>(names: &[S]) -> Vec<String> {
+    let key = |name: &str| name.replace('_', "").to_ascii_lowercase();
+    let mut taken = HashSet::new();
+    names.iter().map(|name| {
+        let name = sanitize(name.as_ref());
+        let mut result = name.clone();
+        let mut n = 1;
+        while !taken.insert(key(&result)) {
+            n += 1;
+            result = format!("{}_{}", name, n);
+        }
+        result
+    }).collect()
+}
+
+/// [sanitize]d paths of [streams], numbering the last part of those that clash with an earlier one as [distinct] does.
+pub(crate) fn stream_paths(streams: &[TydiPhysicalStream]) -> Vec<Vec<String>> {
+    let key = |path: &[String]| path.iter().map(|part| part.replace('_', "").to_ascii_lowercase()).collect::<Vec<_>>();
+    let mut taken = HashSet::new();
+    streams.iter().map(|stream| {
+        let mut path: Vec<String> = stream.path.iter().map(|part| sanitize(part)).collect();
+        if let Some(last) = path.pop() {
+            let mut part = last.clone();
+            let mut n = 1;
+            while !taken.insert(key(&[path.as_slice(), std::slice::from_ref(&part)].concat())) {
+                n += 1;
+                part = format!("{}_{}", last, n);
+            }
+            path.push(part);
+        }
+        path
+    }).collect()
+}
+
+/// Offsets of consecutive fields with [widths] in a region of [width] bits starting at [start].
+pub(crate) fn place(start: usize, width: usize, widths: &[usize], order: TydiFieldOrder) -> Vec<usize> {
+    let mut offset = 0;
+    widths.iter().map(|field_width| {
+        let result = match order {
+            TydiFieldOrder::LsbFirst => start + offset,
+            TydiFieldOrder::MsbFirst => start + width - offset - field_width,
+        };
+        offset += field_width;
+        result
+    }).collect()
+}
+
+/// A field of a group, or the tag or payload of a union, with the type a generator declared for it, taking the
+/// [width] bits at [offset] within its parent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Member<T> {
+    pub name: String,
+    pub declared: T,
+    pub offset: usize,
+    pub width: usize,
+}
+
+/// Syntax of a generator that declares a type for every group and union it comes across, see [declare].
+pub(crate) trait Declarations {
+    type Type;
+
+    /// Type of a field of [width] bits without structure.
+    fn bits(&mut self, width: usize) -> Self::Type;
+
+    /// Declares the tags of the [variants] of the union at [parts].
+    fn tags(&mut self, parts: &[String], variants: &[String]);
+
+    /// Declares the type of the group or union at [parts], which takes [width] bits, from its [members].
+    fn record(&mut self, parts: &[String], width: usize, members: Vec<Member<Self::Type>>) -> Self::Type;
+}
+
+/// Declares the types needed for [t] at [parts] with [syntax], returning the type of [t], or `None` if it has no bits.
+///
+/// Groups become records of their fields. Unions become records of a `tag` and a `payload`, along with the tag of every
+/// variant, as the variants cannot be overlaid in every language. Members are passed first field first, at the offsets
+/// they are packed at in [order], leaving out members without bits. Fields and variants are named [distinct]ly.
+pub(crate) fn declare<D: Declarations>(syntax: &mut D, parts: &[String], t: &TydiLogicalType, order: TydiFieldOrder)
+    -> Option<D::Type> {
+    let width = t.data_width();
+    if width == 0 {
+        return None;
+    }
+    let fields: Vec<(String, Option<D::Type>, usize)> = match t {
+        TydiLogicalType::Group(fields) => {
+            let names = distinct(&fields.iter().map(|(name, _)| name).collect::<Vec<_>>());
+            fields.iter().zip(names).map(|((_, field), name)| {
+                let field_parts = [parts, std::slice::from_ref(&name)].concat();
+                let declared = declare(syntax, &field_parts, field, order);
+                (name, declared, field.data_width())
+            }).collect()
+        }
+        TydiLogicalType::Union(variants) => {
+            syntax.tags(parts, &distinct(&variants.iter().map(|(name, _)| name).collect::<Vec<_>>()));
+            let tag_width = union_tag_width(variants.len());
+            let payload_width = width - tag_width;
+            vec![
+                ("tag".to_string(), (tag_width > 0).then(|| syntax.bits(tag_width)), tag_width),
+                ("payload".to_string(), (payload_width > 0).then(|| syntax.bits(payload_width)), payload_width),
+            ]
+        }
+        _ => return Some(syntax.bits(width)),
+    };
+
+    let widths: Vec<usize> = fields.iter().map(|(_, _, width)| *width).collect();
+    let members = fields.into_iter().zip(place(0, width, &widths, order))
+        .filter_map(|((name, declared, width), offset)| declared.map(|declared| Member { name, declared, offset, width }))
+        .collect();
+    Some(syntax.record(parts, width, members))
+}
+
+/// A field of an element that is accessed as a whole, at [offset] within the element, or the tag of a union if [tag].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Leaf {
+    pub path: Vec<String>,
+    pub offset: usize,
+    pub width: usize,
+    pub tag: bool,
+}
+
+/// Collects the leaves of [t], which takes the [t.data_width()] bits from [start].
+///
+/// The fields of groups are leaves or hold leaves of their own. Unions have a `tag` leaf, and the fields of every
+/// variant follow the tag as they do in the packed union, the rest of the payload being padding. Fields and variants
+/// are named [distinct]ly.
+pub(crate) fn leaves(t: &TydiLogicalType, path: Vec<String>, start: usize, order: TydiFieldOrder, result: &mut Vec<Leaf>) {
+    let width = t.data_width();
+    match t {
+        TydiLogicalType::Null | TydiLogicalType::Stream(_) => {}
+        TydiLogicalType::Bits(_) => result.push(Leaf { path, offset: start, width, tag: false }),
+        TydiLogicalType::Group(fields) => {
+            let widths: Vec<usize> = fields.iter().map(|(_, field)| field.data_width()).collect();
+            let names = distinct(&fields.iter().map(|(name, _)| name).collect::<Vec<_>>());
+            for (((_, field), name), offset) in fields.iter().zip(names).zip(place(start, width, &widths, order)) {
+                leaves(field, [path.clone(), vec![name]].concat(), offset, order, result);
+            }
+        }
+        TydiLogicalType::Union(variants) => {
+            let tag_width = union_tag_width(variants.len());
+            let tag_offset = place(start, width, &[tag_width], order)[0];
+            if tag_width > 0 {
+                let tag_path = [path.clone(), vec!["tag".to_string()]].concat();
+                result.push(Leaf { path: tag_path, offset: tag_offset, width: tag_width, tag: true });
+            }
+            let names = distinct(&variants.iter().map(|(name, _)| name).collect::<Vec<_>>());
+            for ((_, variant), name) in variants.iter().zip(names) {
+                let offset = place(start, width, &[tag_width, variant.data_width()], order)[1];
+                leaves(variant, [path.clone(), vec![name]].concat(), offset, order, result);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::logical::TydiLogicalType::{Bits, Null};
+
+    /// Stream of sensor readings with a flag, a value and a union, as used by the tests of the generators.
+    pub(crate) fn readings() -> TydiLogicalType {
+        TydiLogicalType::stream(TydiLogicalType::group(vec![
+            ("valid", Bits(1)),
+            ("value", Bits(16)),
+            ("unit", TydiLogicalType::union(vec![("none", Null), ("celsius", Bits(4)), ("kelvin", Bits(8))])),
+        ]), 1)
+    }
+
+    /// Declares records as the names and offsets of their members.
+    struct Outline(Vec<String>);
+
+    impl Declarations for Outline {
+        type Type = String;
+
+        fn bits(&mut self, width: usize) -> String {
+            format!("bits{}", width)
+        }
+
+        fn tags(&mut self, parts: &[String], variants: &[String]) {
+            self.0.push(format!("{} tags {}", parts.join("."), variants.join(" ")));
+        }
+
+        fn record(&mut self, parts: &[String], width: usize, members: Vec<Member<String>>) -> String {
+            let members: Vec<String> = members.iter().map(|m| format!("{}: {} @{}", m.name, m.declared, m.offset)).collect();
+            self.0.push(format!("{} ({}) {}", parts.join("."), width, members.join(", ")));
+            parts.join(".")
+        }
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(sanitize("in_reply_to"), "in_reply_to");
+        assert_eq!(sanitize("__hidden-field__"), "hidden_field");
+        assert_eq!(sanitize("1st place"), "n1st_place");
+        assert_eq!(sanitize("-"), "unnamed");
+        assert_ne!(join(&["a_b", "c"]), join(&["a", "b_c"]));
+        assert_eq!(join(&["posts", "in-reply-to"]), "posts__in_reply_to");
+        assert_eq!(distinct(&["a-b", "a_b", "AB", "a_b_2", "c"]), vec!["a_b", "a_b_2", "AB_3", "a_b_2_2", "c"]);
+
+        let streams = [
+            TydiLogicalType::stream(Bits(8), 1).split("a-b"),
+            TydiLogicalType::stream(Bits(8), 1).split("a_b"),
+        ].concat();
+        assert_eq!(stream_paths(&streams), vec![vec!["a_b"], vec!["a_b_2"]]);
+    }
+
+    #[test]
+    fn test_declare() {
+        let element = &readings().split("readings")[0].element;
+        let mut outline = Outline(vec![]);
+        assert_eq!(declare(&mut outline, &["r".to_string()], element, TydiFieldOrder::LsbFirst), Some("r".to_string()));
+        assert_eq!(outline.0, vec![
+            "r.unit tags none celsius kelvin",
+            "r.unit (10) tag: bits2 @0, payload: bits8 @2",
+            "r (27) valid: bits1 @0, value: bits16 @1, unit: r.unit @17",
+        ]);
+
+        let mut outline = Outline(vec![]);
+        declare(&mut outline, &["r".to_string()], element, TydiFieldOrder::MsbFirst);
+        assert_eq!(outline.0[2], "r (27) valid: bits1 @26, value: bits16 @10, unit: r.unit @0");
+        assert_eq!(declare(&mut outline, &[], &Null, TydiFieldOrder::LsbFirst), None);
+    }
+
+    #[test]
+    fn test_leaves() {
+        let mut result = Vec::new();
+        leaves(&readings().split("readings")[0].element, vec![], 0, TydiFieldOrder::LsbFirst, &mut result);
+        let found: Vec<(String, usize, usize)> = result.iter().map(|l| (l.path.join("."), l.offset, l.width)).collect();
+        assert_eq!(result.iter().filter(|l| l.tag).count(), 1);
+        assert_eq!(found, vec![
+            ("valid".to_string(), 0, 1),
+            ("value".to_string(), 1, 16),
+            ("unit.tag".to_string(), 17, 2),
+            ("unit.celsius".to_string(), 19, 4),
+            ("unit.kelvin".to_string(), 19, 8),
+        ]);
+
+        let mut result = Vec::new();
+        leaves(&readings().split("readings")[0].element, vec![], 0, TydiFieldOrder::MsbFirst, &mut result);
+        let offsets: Vec<usize> = result.iter().map(|l| l.offset).collect();
+        assert_eq!(offsets, vec![26, 10, 8, 4, 0]);
+    }
+}
@@ -1,9 +1,34 @@
-use std::fmt::{Debug, Display};
-use bytemuck::{bytes_of, cast, cast_slice, from_bytes_mut, NoUninit, Pod};
-use crate::binary::{FromTydiBinary, TydiBinary};
+use crate::binary::{FromTydiBinary, ToTydiBinary, TydiBinary};
+use crate::order::TydiBitOrder;
+use crate::cursor::TydiBitReader;
+use crate::error::TydiError;
+use crate::logical::TydiLogical;
+
+// Allows code generated by the derive macro to refer to this crate by name from within the crate itself.
+extern crate self as rust_tydi_packages;
+
+pub use tydi_derive_macro::Tydi;
 
 pub mod drilling;
 pub mod binary;
+pub mod transfer;
+pub mod complexity;
+pub mod logical;
+pub mod bundle;
+pub mod error;
+pub mod cursor;
+pub mod order;
+pub mod vcd;
+pub mod trace;
+pub mod memory;
+mod layout;
+pub mod vhdl;
+pub mod systemverilog;
+pub mod chisel;
+pub mod c_header;
+pub mod til;
+pub mod dynamic;
+pub mod json_schema;
 
 #[derive(Debug)]
 pub struct TydiStream<T>(pub Vec<TydiPacket<T>>);
@@ -18,38 +43,69 @@ pub struct TydiPacket<T> {
 }
 
 impl<T> TydiPacket<T> {
-    pub fn to_binary(self, size: usize) -> TydiBinary where T: Into<TydiBinary> {
-        let strobe: TydiBinary = self.data.is_some().into();
-        let last_bin: TydiBinary = self.last.into();
+    pub fn to_binary(self, size: usize) -> TydiBinary where T: ToTydiBinary {
+        self.to_binary_in(size, TydiBitOrder::PORTABLE)
+    }
+
+    /// Like [TydiPacket::to_binary], packing the packet in [order].
+    pub fn to_binary_in(self, size: usize, order: TydiBitOrder) -> TydiBinary where T: ToTydiBinary {
+        self.try_to_binary_in(size, order).expect("resulting binary not of expected size")
+    }
+
+    /// Like [TydiPacket::to_binary], returning an error if the data does not pack into [size] bits.
+    pub fn try_to_binary(self, size: usize) -> Result<TydiBinary, TydiError> where T: ToTydiBinary {
+        self.try_to_binary_in(size, TydiBitOrder::PORTABLE)
+    }
+
+    /// Like [TydiPacket::to_binary_in], returning an error if the data does not pack into [size] bits.
+    pub fn try_to_binary_in(self, size: usize, order: TydiBitOrder) -> Result<TydiBinary, TydiError> where T: ToTydiBinary {
+        let strobe = self.data.is_some().to_tydi_binary(order);
+        let last_bin = self.last.to_tydi_binary(order);
         // el.data.and_then(|data| { Some(data.into()) }).or(Some(TydiBinary { data: vec![], len: 0 }))
         let data_bin = if let Some(data) = self.data {
-            let binary = data.into();
-            assert_eq!(binary.len, size, "resulting binary not of expected size");
+            let binary = data.to_tydi_binary(order);
+            if binary.len != size {
+                return Err(TydiError::SizeMismatch { expected: size, actual: binary.len });
+            }
             binary
         } else {
-            let n_bytes = size.div_ceil(8);
-            TydiBinary { data: vec![0u8; n_bytes], len: size }
+            TydiBinary::zeros(size).in_order(order)
         };
-        strobe.concatenate(&last_bin).concatenate(&data_bin)
+        let mut result = TydiBinary::with_capacity(1 + last_bin.len + size).in_order(order);
+        result.append(&strobe);
+        result.append(&last_bin);
+        result.append(&data_bin);
+        Ok(result)
     }
 
     pub fn from_binary(val: TydiBinary, dim: usize) -> Self where T: FromTydiBinary {
-        let (strobe, res) = bool::from_tydi_binary(val);
-        let (last, res) = res.split(dim);
-        let last: Vec<bool> = last.into();
-
-        let data: Option<T> = if strobe {
-            let (item, _) = T::from_tydi_binary(res);
-            Some(item)
-        } else {
-            None
-        };
+        let mut reader = TydiBitReader::new(&val);
+        let strobe = reader.read::<bool>();
+        let last: Vec<bool> = reader.read_binary(dim).into();
+        let data: Option<T> = strobe.then(|| T::from_tydi_binary(reader.finish()).0);
         Self { data, last }
     }
 
+    /// Like [TydiPacket::from_binary], returning an error if [val] is not exactly as large as a packet of [T] with
+    /// dimensionality [dim], or its data is not a valid [T].
+    pub fn try_from_binary(val: TydiBinary, dim: usize) -> Result<Self, TydiError> where T: FromTydiBinary + TydiLogical {
+        let expected = 1 + dim + T::logical_type().data_width();
+        if val.len < expected || val.len > val.data.len() * 8 {
+            return Err(TydiError::Truncated { needed: expected, available: val.len.min(val.data.len() * 8) });
+        }
+        if val.len > expected {
+            return Err(TydiError::SizeMismatch { expected, actual: val.len });
+        }
+        let mut reader = TydiBitReader::new(&val);
+        let strobe = reader.read::<bool>();
+        let last: Vec<bool> = reader.read_binary(dim).into();
+        let data = if strobe { Some(T::try_from_tydi_binary(reader.finish())?.0) } else { None };
+        Ok(Self { data, last })
+    }
+
     pub fn map_data<B>(self, f: impl FnOnce(T) -> B) -> TydiPacket<B> {
         TydiPacket {
-            data: self.data.and_then(|x| Some(f(x))),
+            data: self.data.map(f),
             last: self.last,
         }
     }
@@ -164,7 +220,7 @@ impl<T: Clone> From<Vec<TydiVec<T>>> for TydiVec<T> {
         for (i, seq) in value.iter().enumerate() {
             let is_last_seq = i == value.len() - 1;
 
-            for (j, el) in seq.data.iter().enumerate() {
+            for el in seq.data.iter() {
                 result.push(TydiPacket {
                     data: el.data.clone(),
                     last: [el.last.clone(), vec![is_last_seq]].concat(),
@@ -182,7 +238,10 @@ impl<T: Clone> From<Vec<TydiVec<T>>> for TydiVec<T> {
 
 #[cfg(test)]
 mod tests {
+    use crate::cursor::TydiBitWriter;
     use crate::binary::TydiBinary;
+    use crate::logical::TydiLogical;
+    use crate::order::{TydiEndianness, TydiFieldOrder};
     use super::*;
 
     #[test]
@@ -223,20 +282,18 @@ mod tests {
 
         impl From<Comment> for TydiBinary {
             fn from(comment: Comment) -> TydiBinary {
-                let binaries: Vec<TydiBinary> = vec![
-                    comment.comment_id.into(),
-                    comment.author.into(),
-                    comment.likes.into(),
-                ];
-                binaries.iter().fold(TydiBinary::empty(), |acc, e| acc.concatenate(e)).clone()
+                let mut writer = TydiBitWriter::new();
+                writer.write(comment.comment_id).write(TydiBinary::from(comment.author)).write(comment.likes);
+                writer.finish()
             }
         }
 
         impl From<TydiBinary> for Comment {
             fn from(bin: TydiBinary) -> Self {
-                let (comment_id, remainder) = bin.split_for();
-                let (author_id, remainder) = remainder.split_for();
-                let (likes, remainder) = remainder.split_for();
+                let mut reader = TydiBitReader::new(&bin);
+                let comment_id = reader.read();
+                let author_id = reader.read();
+                let likes = reader.read();
 
                 Comment {
                     comment_id,
@@ -252,9 +309,6 @@ mod tests {
             }
         }
 
-        let le_value = 1u32.to_le_bytes();
-        let be_value = 2u32.to_be_bytes();
-
         let bin = TydiBinary::from(data);
         println!("{}", bin);
         println!("{:?}", bin);
@@ -263,10 +317,140 @@ mod tests {
         println!("done");
     }
 
+    #[test]
+    fn test_derived_packing() {
+        #[derive(Debug, PartialEq, Eq, Clone, Tydi)]
+        pub struct Author {
+            user_id: u32,
+            username: String,
+        }
+
+        #[derive(Debug, PartialEq, Eq, Clone, Tydi)]
+        pub struct Comment {
+            comment_id: u32,
+            author: Author,
+            content: String,
+            tags: Vec<String>,
+            likes: u16,
+            flagged: bool,
+        }
+
+        let comment = Comment {
+            comment_id: 7,
+            author: Author { user_id: 789, username: "CultureVulture".into() },
+            content: "Did you get a chance to see any flamenco shows?".into(),
+            tags: vec!["travel".into()],
+            likes: 10,
+            flagged: true,
+        };
+
+        let bin: TydiBinary = comment.clone().into();
+        assert_eq!(bin.len, 32 + 32 + 16 + 1);
+        let (comment_id, res) = u32::from_tydi_binary(bin.clone());
+        let (user_id, _) = u32::from_tydi_binary(res);
+        assert_eq!((comment_id, user_id), (7, 789));
+
+        let (reconstructed, res) = Comment::from_tydi_binary(bin);
+        assert_eq!(res.len, 0);
+        assert_eq!(reconstructed, Comment {
+            author: Author { username: "".into(), ..comment.author },
+            content: "".into(),
+            tags: vec![],
+            ..comment
+        });
+    }
+
+    #[test]
+    fn test_attribute_packing() {
+        #[derive(Debug, PartialEq, Eq, Clone, Tydi)]
+        pub struct Reaction {
+            #[tydi(bits = 12)]
+            likes: u32,
+            #[tydi(signed, bits = 20)]
+            delta: i32,
+            emoji: String,
+        }
+
+        let reaction = Reaction { likes: 4095, delta: -300, emoji: "".into() };
+        assert_eq!(Reaction::logical_type().data_width(), 32);
+        let bin: TydiBinary = reaction.clone().into();
+        assert_eq!(bin.len, 12 + 20);
+        assert_eq!(Reaction::from_tydi_binary(bin).0, reaction);
+    }
+
+    #[test]
+    fn test_inline_field_packing() {
+        #[derive(Debug, PartialEq, Eq, Clone, Tydi)]
+        pub struct Upload {
+            hash: [u8; 32],
+            position: (i16, i16),
+            #[tydi(max_len = 4)]
+            labels: Vec<u16>,
+            name: String,
+        }
+
+        let upload = Upload { hash: [7; 32], position: (-1, 12), labels: vec![3, 1], name: "".into() };
+        assert_eq!(Upload::logical_type().data_width(), 256 + 32 + 3 + 4 * 16);
+        let bin: TydiBinary = upload.clone().into();
+        assert_eq!(bin.len, 256 + 32 + 3 + 4 * 16);
+        assert_eq!(Upload::from_tydi_binary(bin).0, upload);
+    }
+
+    #[test]
+    fn test_fields_named_as_generated_locals() {
+        #[derive(Debug, PartialEq, Eq, Clone, Tydi)]
+        pub struct Locals {
+            width: usize,
+            #[tydi(max_len = 2)]
+            items: Vec<u8>,
+            reader: u8,
+            writer: u16,
+            #[tydi(bits = 4)]
+            binary: u8,
+            order: bool,
+            value: i32,
+        }
+
+        let locals = Locals { width: 7, items: vec![18], reader: 1, writer: 2, binary: 3, order: true, value: -4 };
+        for order in [TydiBitOrder::PORTABLE, TydiBitOrder::new(TydiEndianness::Big, TydiFieldOrder::MsbFirst)] {
+            let bin = locals.clone().to_tydi_binary(order);
+            assert_eq!(Locals::from_tydi_binary(bin).0, locals, "{:?}", order);
+        }
+    }
+
+    #[test]
+    fn test_drill_iter() {
+        use crate::drilling::TydiConvert;
+        use std::cell::Cell;
+
+        let posts = vec![
+            vec!["Travel".to_string(), "".to_string()],
+            vec![],
+            vec!["Rust".to_string()],
+        ];
+        let stream = posts.convert();
+        let tags: TydiStream<&String> = stream.drill_iter(|e| e.iter()).collect();
+        let owned_tags = stream.drill(|e| e);
+        assert_eq!(tags.0.len(), owned_tags.0.len());
+        for (borrowed, owned) in tags.0.iter().zip(owned_tags.0.iter()) {
+            assert_eq!(borrowed.data, owned.data.as_ref());
+            assert_eq!(borrowed.last, owned.last);
+        }
+
+        let chars: TydiStream<u8> = tags.drill_iter(|e| e.bytes()).collect();
+        assert_eq!(chars.0, owned_tags.drill(|e| e.into_bytes()).0);
+
+        // Parents are only visited when their packets are requested
+        let visited = Cell::new(0);
+        let first: Vec<_> = stream.drill_iter(|e| { visited.set(visited.get() + 1); e.iter() }).take(1).collect();
+        assert_eq!(first[0].data, Some(&"Travel".to_string()));
+        assert_eq!(visited.get(), 1);
+    }
+
     #[test]
     fn test_packing() {
         let num_bytes: [u8; 8] = [0xed, 0x1, 0x0, 0x0, 0x20, 0x7, 0x0, 0x0];
-        let num = u64::from_ne_bytes(num_bytes);
+        let num = u64::from_le_bytes(num_bytes);
         let packet = TydiPacket {
             data: Some(num),
             last: vec![true],
@@ -0,0 +1,424 @@
+use crate::complexity::TydiComplexity;
+use crate::binary::{bounded_length_width, TydiBinary};
+use crate::transfer::{index_from_binary, index_to_binary, index_width};
+use crate::cursor::TydiBitReader;
+use crate::error::TydiError;
+
+/// A Tydi logical type, describing the structure of data independent of how it is transferred.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TydiLogicalType {
+    /// A type with a single value, taking up no bits.
+    Null,
+    /// A value of the given number of bits.
+    Bits(usize),
+    /// All named fields at the same time, the first field in the least significant bits.
+    Group(Vec<(String, TydiLogicalType)>),
+    /// One of the named variants, selected by a tag.
+    Union(Vec<(String, TydiLogicalType)>),
+    /// A sequence of elements, transferred over a physical stream of its own.
+    Stream(Box<TydiLogicalStream>),
+}
+
+/// Whether the dimensions of a parent stream are repeated in a child stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TydiSynchronicity {
+    /// The child stream carries the `last` flags of the parent, and every parent element has exactly one sequence.
+    Sync,
+    /// The `last` flags of the parent are left out of the child stream.
+    Flatten,
+    /// Like [TydiSynchronicity::Sync], but the child is not required to transfer its data in step with the parent.
+    Desync,
+    /// Like [TydiSynchronicity::Flatten], but the child is not required to transfer its data in step with the parent.
+    FlatDesync,
+}
+
+/// Direction of a stream relative to its parent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TydiDirection {
+    Forward,
+    Reverse,
+}
+
+/// Properties of a logical stream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TydiLogicalStream {
+    pub element: TydiLogicalType,
+    /// Average number of elements per transfer, determining the number of lanes.
+    pub throughput: f64,
+    /// Number of nested sequence levels that are added to those of the parent.
+    pub dimensionality: usize,
+    pub synchronicity: TydiSynchronicity,
+    pub complexity: TydiComplexity,
+    pub direction: TydiDirection,
+    /// Data transferred along with every transfer, independent of the elements.
+    pub user: TydiLogicalType,
+    /// Keep the physical stream even if it carries no element or user data.
+    pub keep: bool,
+}
+
+impl TydiLogicalStream {
+    /// Creates a forward, synchronous stream with a throughput of one element per transfer.
+    pub fn new(element: TydiLogicalType, dimensionality: usize) -> Self {
+        Self {
+            element,
+            throughput: 1.0,
+            dimensionality,
+            synchronicity: TydiSynchronicity::Sync,
+            complexity: TydiComplexity::C1,
+            direction: TydiDirection::Forward,
+            user: TydiLogicalType::Null,
+            keep: false,
+        }
+    }
+
+    /// Whether this stream gets a physical stream of its own when split, see [TydiLogicalType::split].
+    pub(crate) fn is_physical(&self) -> bool {
+        self.keep || self.element.without_streams().data_width() > 0 || self.user.data_width() > 0
+    }
+}
+
+/// A physical stream resulting from splitting a logical type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TydiPhysicalStream {
+    /// Field names leading from the root to this stream, the first item being the name of the root.
+    pub path: Vec<String>,
+    /// Element type with all child streams removed.
+    pub element: TydiLogicalType,
+    pub lanes: usize,
+    pub dimensionality: usize,
+    pub complexity: TydiComplexity,
+    pub direction: TydiDirection,
+    pub user: TydiLogicalType,
+    /// Index of the stream this stream was split off from.
+    pub parent: Option<usize>,
+}
+
+impl TydiLogicalType {
+    /// Creates a group from named fields.
+    pub fn group(fields: Vec<(&str, TydiLogicalType)>) -> Self {
+        Self::Group(fields.into_iter().map(|(name, t)| (name.to_string(), t)).collect())
+    }
+
+    /// Creates a union from named variants.
+    pub fn union(variants: Vec<(&str, TydiLogicalType)>) -> Self {
+        Self::Union(variants.into_iter().map(|(name, t)| (name.to_string(), t)).collect())
+    }
+
+    /// Creates a default stream, see [TydiLogicalStream::new].
+    pub fn stream(element: TydiLogicalType, dimensionality: usize) -> Self {
+        Self::Stream(Box::new(TydiLogicalStream::new(element, dimensionality)))
+    }
+
+    /// This stream with [throughput] elements per transfer on average. Panics if this type is not a stream.
+    pub fn with_throughput(self, throughput: f64) -> Self {
+        self.map_stream(|stream| stream.throughput = throughput)
+    }
+
+    /// This stream with complexity [complexity]. Panics if this type is not a stream.
+    pub fn with_complexity(self, complexity: TydiComplexity) -> Self {
+        self.map_stream(|stream| stream.complexity = complexity)
+    }
+
+    fn map_stream(self, f: impl FnOnce(&mut TydiLogicalStream)) -> Self {
+        match self {
+            Self::Stream(mut stream) => {
+                f(&mut stream);
+                Self::Stream(stream)
+            }
+            other => panic!("stream properties cannot be set on {:?}", other),
+        }
+    }
+
+    /// Number of bits of this type that are transferred in the stream it is part of, i.e. without child streams.
+    pub fn data_width(&self) -> usize {
+        match self {
+            Self::Null | Self::Stream(_) => 0,
+            Self::Bits(n) => *n,
+            Self::Group(fields) => fields.iter().map(|(_, t)| t.data_width()).sum(),
+            Self::Union(variants) => {
+                union_tag_width(variants.len()) + variants.iter().map(|(_, t)| t.data_width()).max().unwrap_or(0)
+            }
+        }
+    }
+
+    /// This type with all streams replaced by [TydiLogicalType::Null].
+    pub fn without_streams(&self) -> Self {
+        let strip = |fields: &Vec<(String, TydiLogicalType)>| {
+            fields.iter().map(|(name, t)| (name.clone(), t.without_streams())).collect()
+        };
+        match self {
+            Self::Stream(_) => Self::Null,
+            Self::Group(fields) => Self::Group(strip(fields)),
+            Self::Union(variants) => Self::Union(strip(variants)),
+            other => other.clone(),
+        }
+    }
+
+    /// Splits this type into the physical streams that transfer it, named after [name].
+    ///
+    /// Streams of which the element and user types carry no data are left out unless they are marked to be kept,
+    /// their children then take their place.
+    pub fn split(&self, name: &str) -> Vec<TydiPhysicalStream> {
+        let mut result = Vec::new();
+        self.split_into(vec![name.to_string()], None, 0, 1.0, TydiDirection::Forward, &mut result);
+        result
+    }
+
+    fn split_into(&self, path: Vec<String>, parent: Option<usize>, parent_dim: usize, parent_throughput: f64,
+                  parent_direction: TydiDirection, result: &mut Vec<TydiPhysicalStream>) {
+        let fields = match self {
+            Self::Null | Self::Bits(_) => return,
+            Self::Group(fields) | Self::Union(fields) => fields,
+            Self::Stream(stream) => {
+                let dimensionality = match stream.synchronicity {
+                    TydiSynchronicity::Sync | TydiSynchronicity::Desync => parent_dim + stream.dimensionality,
+                    TydiSynchronicity::Flatten | TydiSynchronicity::FlatDesync => stream.dimensionality,
+                };
+                let throughput = parent_throughput * stream.throughput;
+                let direction = match (parent_direction, stream.direction) {
+                    (TydiDirection::Forward, d) => d,
+                    (TydiDirection::Reverse, TydiDirection::Forward) => TydiDirection::Reverse,
+                    (TydiDirection::Reverse, TydiDirection::Reverse) => TydiDirection::Forward,
+                };
+                let element = stream.element.without_streams();
+
+                let parent = if stream.is_physical() {
+                    result.push(TydiPhysicalStream {
+                        path: path.clone(),
+                        element,
+                        lanes: throughput.ceil().max(1.0) as usize,
+                        dimensionality,
+                        complexity: stream.complexity,
+                        direction,
+                        user: stream.user.clone(),
+                        parent,
+                    });
+                    Some(result.len() - 1)
+                } else {
+                    parent
+                };
+                stream.element.split_into(path, parent, dimensionality, throughput, direction, result);
+                return;
+            }
+        };
+        for (name, t) in fields {
+            let field_path = [path.clone(), vec![name.clone()]].concat();
+            t.split_into(field_path, parent, parent_dim, parent_throughput, parent_direction, result);
+        }
+    }
+}
+
+/// Number of bits needed to select one of [variants] union variants.
+pub fn union_tag_width(variants: usize) -> usize {
+    index_width(variants)
+}
+
+/// Tag selecting variant [index] of a union with [variants] variants.
+pub fn union_tag_to_binary(index: usize, variants: usize) -> TydiBinary {
+    index_to_binary(index, union_tag_width(variants))
+}
+
+/// Takes the tag of a union with [variants] variants from [value], returning the selected variant and the rest.
+pub fn union_tag_from_binary(value: TydiBinary, variants: usize) -> (usize, TydiBinary) {
+    let mut reader = TydiBitReader::new(&value);
+    let tag = index_from_binary(reader.read_binary(union_tag_width(variants)));
+    (tag, reader.finish())
+}
+
+/// Like [union_tag_from_binary], returning an error if [value] is too short or the tag selects none of the variants.
+pub fn try_union_tag_from_binary(value: TydiBinary, variants: usize) -> Result<(usize, TydiBinary), TydiError> {
+    let mut reader = TydiBitReader::new(&value);
+    let tag = index_from_binary(reader.try_read_binary(union_tag_width(variants))?);
+    if tag >= variants {
+        return Err(TydiError::InvalidTag { tag, variants });
+    }
+    Ok((tag, reader.finish()))
+}
+
+impl TydiPhysicalStream {
+    /// Name of the stream, its path joined with double underscores.
+    pub fn name(&self) -> String {
+        self.path.join("__")
+    }
+
+    /// Number of bits in a single element.
+    pub fn element_width(&self) -> usize {
+        self.element.data_width()
+    }
+
+    /// Number of bits of the `data` signal, spanning all lanes.
+    pub fn data_width(&self) -> usize {
+        self.element_width() * self.lanes
+    }
+}
+
+/// Types with a Tydi logical type.
+pub trait TydiLogical {
+    fn logical_type() -> TydiLogicalType;
+}
+
+macro_rules! impl_logical_bits {
+    ($($t:ty),*) => {
+        $(
+            impl TydiLogical for $t {
+                fn logical_type() -> TydiLogicalType {
+                    TydiLogicalType::Bits(size_of::<$t>() * 8)
+                }
+            }
+        )*
+    };
+}
+
+impl_logical_bits!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64, char);
+
+/// `usize` and `isize` are 64 bits wide on every host, so that their binaries do not depend on it.
+impl TydiLogical for usize {
+    fn logical_type() -> TydiLogicalType {
+        TydiLogicalType::Bits(64)
+    }
+}
+
+impl TydiLogical for isize {
+    fn logical_type() -> TydiLogicalType {
+        TydiLogicalType::Bits(64)
+    }
+}
+
+impl TydiLogical for bool {
+    fn logical_type() -> TydiLogicalType {
+        TydiLogicalType::Bits(1)
+    }
+}
+
+impl TydiLogical for String {
+    fn logical_type() -> TydiLogicalType {
+        TydiLogicalType::stream(TydiLogicalType::Bits(8), 1)
+    }
+}
+
+impl<T: TydiLogical> TydiLogical for Option<T> {
+    fn logical_type() -> TydiLogicalType {
+        TydiLogicalType::union(vec![("none", TydiLogicalType::Null), ("some", T::logical_type())])
+    }
+}
+
+/// Arrays are packed inline, as the concatenation of their items.
+impl<T: TydiLogical, const N: usize> TydiLogical for [T; N] {
+    fn logical_type() -> TydiLogicalType {
+        TydiLogicalType::Bits(N * T::logical_type().data_width())
+    }
+}
+
+/// Tuples are packed inline, as the concatenation of their fields.
+macro_rules! impl_logical_tuple {
+    ($($t:ident),+) => {
+        impl<$($t: TydiLogical),+> TydiLogical for ($($t,)+) {
+            fn logical_type() -> TydiLogicalType {
+                TydiLogicalType::Bits(0 $(+ $t::logical_type().data_width())+)
+            }
+        }
+    };
+}
+
+impl_logical_tuple!(A);
+impl_logical_tuple!(A, B);
+impl_logical_tuple!(A, B, C);
+impl_logical_tuple!(A, B, C, D);
+impl_logical_tuple!(A, B, C, D, E);
+impl_logical_tuple!(A, B, C, D, E, F);
+impl_logical_tuple!(A, B, C, D, E, F, G);
+impl_logical_tuple!(A, B, C, D, E, F, G, H);
+
+/// Logical type of a sequence of at most [max_len] items of [T] that is packed inline, see
+/// [crate::binary::pack_bounded].
+pub fn bounded_logical_type<T: TydiLogical>(max_len: usize) -> TydiLogicalType {
+    TydiLogicalType::group(vec![
+        ("length", TydiLogicalType::Bits(bounded_length_width(max_len))),
+        ("items", TydiLogicalType::Bits(max_len * T::logical_type().data_width())),
+    ])
+}
+
+impl<T: TydiLogical> TydiLogical for Vec<T> {
+    fn logical_type() -> TydiLogicalType {
+        TydiLogicalType::stream(T::logical_type(), 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use TydiLogicalType::*;
+
+    fn author() -> TydiLogicalType {
+        TydiLogicalType::group(vec![
+            ("user_id", Bits(32)),
+            ("username", String::logical_type()),
+        ])
+    }
+
+    fn post() -> TydiLogicalType {
+        TydiLogicalType::group(vec![
+            ("post_id", Bits(32)),
+            ("title", String::logical_type()),
+            ("author", author()),
+            ("created_at", Bits(64)),
+            ("tags", Vec::<String>::logical_type()),
+            ("comments", TydiLogicalType::stream(TydiLogicalType::group(vec![
+                ("comment_id", Bits(32)),
+                ("author", author()),
+                ("content", String::logical_type()),
+            ]), 1)),
+        ])
+    }
+
+    #[test]
+    fn test_split() {
+        let streams = TydiLogicalType::stream(post(), 1).split("posts");
+        let names: Vec<String> = streams.iter().map(|s| s.name()).collect();
+        assert_eq!(names, vec![
+            "posts",
+            "posts__title",
+            "posts__author__username",
+            "posts__tags",
+            "posts__comments",
+            "posts__comments__author__username",
+            "posts__comments__content",
+        ]);
+
+        let widths: Vec<usize> = streams.iter().map(|s| s.element_width()).collect();
+        assert_eq!(widths, vec![128, 8, 8, 8, 64, 8, 8]);
+        let dims: Vec<usize> = streams.iter().map(|s| s.dimensionality).collect();
+        assert_eq!(dims, vec![1, 2, 2, 3, 2, 3, 3]);
+        let parents: Vec<Option<usize>> = streams.iter().map(|s| s.parent).collect();
+        assert_eq!(parents, vec![None, Some(0), Some(0), Some(0), Some(0), Some(4), Some(4)]);
+    }
+
+    #[test]
+    fn test_stream_properties() {
+        let mut chars = TydiLogicalStream::new(Bits(8), 1);
+        chars.throughput = 4.0;
+        chars.synchronicity = TydiSynchronicity::Flatten;
+        chars.direction = TydiDirection::Reverse;
+        let mut outer = TydiLogicalStream::new(TydiLogicalType::group(vec![
+            ("id", Bits(10)),
+            ("name", Stream(Box::new(chars))),
+        ]), 1);
+        outer.throughput = 2.0;
+        outer.keep = true;
+
+        let streams = Stream(Box::new(outer)).split("root");
+        assert_eq!(streams[0].lanes, 2);
+        assert_eq!(streams[0].data_width(), 20);
+        assert_eq!(streams[1].lanes, 8);
+        assert_eq!(streams[1].dimensionality, 1);
+        assert_eq!(streams[1].direction, TydiDirection::Reverse);
+    }
+
+    #[test]
+    fn test_union_width() {
+        let union = TydiLogicalType::union(vec![("none", Null), ("small", Bits(3)), ("large", Bits(12))]);
+        assert_eq!(union.data_width(), 2 + 12);
+        let option = TydiLogicalType::union(vec![("none", Null), ("some", Bits(32))]);
+        assert_eq!(option.data_width(), 33);
+    }
+}
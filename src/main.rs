@@ -2,18 +2,22 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use std::error::Error;
 use chrono::{DateTime, Utc};
-use rust_tydi_packages::{binary::TydiBinary, TydiPacket, drilling::*, TydiStream, TydiBinaryStream};
-use rust_tydi_packages::binary::FromTydiBinary;
+use rust_tydi_packages::{binary::TydiBinary, impl_tydi_leaf, Tydi};
+use rust_tydi_packages::binary::{FromTydiBinary, ToTydiBinary};
+use rust_tydi_packages::bundle::TydiBundle;
+use rust_tydi_packages::error::TydiError;
+use rust_tydi_packages::logical::{TydiLogical, TydiLogicalType};
+use rust_tydi_packages::order::TydiBitOrder;
 // Define the data structures based on the JSON schema.
 // We use `serde::Deserialize` to automatically derive the deserialization logic.
 
 #[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Clone)]
-struct MyDate(DateTime<Utc>);
+pub struct MyDate(DateTime<Utc>);
 
 // Represents a single comment.
-#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Clone)]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Clone, Tydi)]
 #[serde(rename_all = "camelCase")]
-struct Comment {
+pub struct Comment {
     comment_id: u32,
     author: Author,
     content: String,
@@ -24,17 +28,17 @@ struct Comment {
 }
 
 // Represents the author of a post or comment.
-#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Clone)]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Clone, Tydi)]
 #[serde(rename_all = "camelCase")]
-struct Author {
+pub struct Author {
     user_id: u32,
     username: String,
 }
 
 // Represents a single post.
-#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Clone)]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Clone, Tydi)]
 #[serde(rename_all = "camelCase")]
-struct Post {
+pub struct Post {
     post_id: u32,
     title: String,
     content: String,
@@ -47,126 +51,42 @@ struct Post {
     comments: Vec<Comment>,
 }
 
-impl From<MyDate> for TydiBinary {
-    fn from(value: MyDate) -> Self {
-        let temp: u64 = value.0.timestamp_millis() as u64;
-        temp.into()
+impl ToTydiBinary for MyDate {
+    fn to_tydi_binary(self, order: TydiBitOrder) -> TydiBinary {
+        self.0.timestamp_millis().to_tydi_binary(order)
     }
 }
 
-impl FromTydiBinary for MyDate {
-    fn from_tydi_binary(value: TydiBinary) -> (Self, TydiBinary) {
-        let (int_value, res) = i64::from_tydi_binary(value);
-        let dt = DateTime::from_timestamp_millis(int_value).unwrap();
-        (MyDate(dt), res)
+impl From<MyDate> for TydiBinary {
+    fn from(value: MyDate) -> Self {
+        value.to_tydi_binary(TydiBitOrder::PORTABLE)
     }
 }
 
-// The root data structure, which is a vector of posts.
-#[derive(Debug, Deserialize)]
-struct Posts(Vec<Post>);
-
-struct MyTypeStreams {
-    subStream: Vec<TydiBinary>,
-    subStream2: Vec<TydiBinary>
-}
-
-struct MyTypeProcessed {
-    someProp: TydiBinary, // from bool
-    someOtherProp: TydiBinary, // from u8
-
-    streams: MyTypeStreams,
-}
-
-struct PhysicalStreamsTyped {
-    posts: TydiStream<Post>,
-    post_titles: TydiStream<u8>,
-    post_contents: TydiStream<u8>,
-    post_author_username: TydiStream<u8>,
-    post_tags: TydiStream<u8>,
-    post_comments: TydiStream<Comment>,
-    post_comment_author_username: TydiStream<u8>,
-    post_comment_content: TydiStream<u8>,
-}
-
-impl PhysicalStreamsTyped {
-    pub fn new(posts: Vec<Post>) -> PhysicalStreamsTyped {
-        let posts_tydi = posts.convert();
-        let titles_tydi = posts_tydi.drill(|e| e.title.as_bytes().to_vec());
-        let contents_tydi = posts_tydi.drill(|e| e.content.as_bytes().to_vec());
-        let tags_tydi = posts_tydi.drill(|e| e.tags.clone()).drill(|e| e.as_bytes().to_vec());
-        let comments_tydi = posts_tydi.drill(|e| e.comments.clone());
-        let comment_author_tydi = comments_tydi.drill(|e| e.author.username.as_bytes().to_vec());
-
-        PhysicalStreamsTyped {
-            post_titles: titles_tydi,
-            post_contents: contents_tydi,
-            post_author_username: posts_tydi.drill(|e| e.author.username.as_bytes().to_vec()),
-            post_tags: tags_tydi,
-            post_comment_author_username: comment_author_tydi,
-            post_comment_content: comments_tydi.drill(|e| e.content.as_bytes().to_vec()),
-            post_comments: comments_tydi,
-            posts: posts_tydi,
-        }
-    }
-
-    pub fn reverse(self) -> Vec<Post> {
-        let mut comments_recreated = self.post_comments;
-        comments_recreated.inject_string(|el| &mut el.author.username, self.post_comment_author_username);
-        comments_recreated.inject_string(|el| &mut el.content, self.post_comment_content);
-
-        let mut posts_recreated = self.posts;
-        posts_recreated.inject(|el| &mut el.comments, comments_recreated);
-        let tags_recreated = self.post_tags.solidify_into_strings();
-        posts_recreated.inject(|el| &mut el.tags, tags_recreated);
-        posts_recreated.inject_string(|el| &mut el.title, self.post_titles);
-        posts_recreated.inject_string(|el| &mut el.content, self.post_contents);
-        posts_recreated.inject_string(|el| &mut el.author.username, self.post_author_username);
-
-        posts_recreated.unpack()
+impl TydiLogical for MyDate {
+    fn logical_type() -> TydiLogicalType {
+        i64::logical_type()
     }
 }
 
-struct PhysicalStreamsBinary {
-    posts: TydiBinaryStream,
-    post_titles: TydiBinaryStream,
-    post_contents: TydiBinaryStream,
-    post_author_username: TydiBinaryStream,
-    post_tags: TydiBinaryStream,
-    post_comments: TydiBinaryStream,
-    post_comment_author_username: TydiBinaryStream,
-    post_comment_content: TydiBinaryStream,
-}
-
-impl PhysicalStreamsBinary {
-    pub fn new(posts: PhysicalStreamsTyped) -> PhysicalStreamsBinary {
-        Self {
-            posts: posts.posts.finish(256),
-            post_titles: posts.post_titles.finish(8),
-            post_contents: posts.post_contents.finish(8),
-            post_author_username: posts.post_author_username.finish(8),
-            post_tags: posts.post_tags.finish(8),
-            post_comments: posts.post_comments.finish(160),
-            post_comment_author_username: posts.post_comment_author_username.finish(8),
-            post_comment_content: posts.post_comment_content.finish(8),
-        }
+impl FromTydiBinary for MyDate {
+    fn from_tydi_binary(value: TydiBinary) -> (Self, TydiBinary) {
+        Self::try_from_tydi_binary(value).unwrap_or_else(|e| panic!("{}", e))
     }
 
-    pub fn reverse(self) -> PhysicalStreamsTyped {
-        PhysicalStreamsTyped {
-            posts: packets_from_binaries(self.posts, 1),
-            post_titles: packets_from_binaries(self.post_titles, 2),
-            post_contents: packets_from_binaries(self.post_contents, 2),
-            post_author_username: packets_from_binaries(self.post_author_username, 2),
-            post_tags: packets_from_binaries(self.post_tags, 3),
-            post_comments: packets_from_binaries(self.post_comments, 2),
-            post_comment_author_username: packets_from_binaries(self.post_comment_author_username, 3),
-            post_comment_content: packets_from_binaries(self.post_comment_content, 3),
-        }
-
+    /// Packed as milliseconds since the Unix epoch, which can lie outside the dates [DateTime] represents.
+    fn try_from_tydi_binary(value: TydiBinary) -> Result<(Self, TydiBinary), TydiError> {
+        let (millis, res) = i64::try_from_tydi_binary(value)?;
+        let dt = DateTime::from_timestamp_millis(millis).ok_or_else(|| TydiError::OutOfRange {
+            value: format!("{} ms since the Unix epoch", millis),
+            reason: "beyond the range of dates".to_string(),
+        })?;
+        Ok((MyDate(dt), res))
     }
 }
 
+impl_tydi_leaf!(MyDate);
+
 fn main() -> Result<(), Box<dyn Error>> {
     // This assumes the JSON file is named 'posts.json' and is in the same directory.
     let json_file_path = "posts.json";
@@ -189,173 +109,32 @@ fn main() -> Result<(), Box<dyn Error>> {
         println!("Number of Comments: {}\n", post.comments.len());
     }
 
-    let typed_streams = PhysicalStreamsTyped::new(posts.clone());
-    let binary_streams = PhysicalStreamsBinary::new(typed_streams);
+    let typed_streams = TydiBundle::new(posts.clone());
+    let binary_streams = typed_streams.finish();
 
-    println!("author stream binary: {:?}", binary_streams.post_comment_author_username.0.iter().map(|e| e.to_string()).collect::<Vec<String>>());
+    println!("author stream binary: {:?}", binary_streams.streams.comments.children.author.username.0.iter().map(|e| e.to_string()).collect::<Vec<String>>());
     println!("author stream native: {:?}", posts.iter().flat_map(|e| e.comments.clone()).flat_map(|e| e.author.username.as_bytes().iter().map(|e| format!("{:08b}", e)).collect::<Vec<_>>()).collect::<Vec<_>>());
 
-    println!("posts binary: {:?}", binary_streams.posts);
+    println!("posts binary: {:?}", binary_streams.root);
     let objs = binary_streams.reverse();
     let reconstructed_posts = objs.reverse();
     // comments_recreated.inject(|e| e.author.username, comment_author_recreated);
     // posts_recreated[0].data.unwrap().comments.push()
-    let my_var = 5;
 
     let json_recreated = serde_json::to_string(&reconstructed_posts).expect("Should have been able to serialize the reconstructed posts");
-    fs::write(recreation_file_path, json_recreated);
+    fs::write(recreation_file_path, json_recreated)?;
 
     Ok(())
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct PostNonVecs {
-    pub post_id: u32,
-    pub author: AuthorNonVecs,
-    pub created_at: MyDate,
-    pub updated_at: MyDate,
-    pub likes: u32,
-    pub shares: u32,
-}
-
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct PostVecs {
-    pub title: String,
-    pub content: String,
-    pub tags: Vec<String>,
-    pub comments: Vec<Comment>,
-}
-
-impl From<Post> for TydiBinary {
-    fn from(value: Post) -> Self {
-        let post_id: TydiBinary = value.post_id.into();
-        let author: TydiBinary = value.author.into();
-        let created_at: TydiBinary = value.created_at.into();
-        let updated_at: TydiBinary = value.updated_at.into();
-        let likes: TydiBinary = value.likes.into();
-        let shares: TydiBinary = value.shares.into();
-        post_id.concatenate(&author).concatenate(&created_at).concatenate(&updated_at).concatenate(&likes).concatenate(&shares)
-    }
-}
-
-impl From<TydiBinary> for Post {
-    fn from(value: TydiBinary) -> Self {
-        let (post_id, res) = u32::from_tydi_binary(value);
-        let (author, res) = Author::from_tydi_binary(res);
-        let (created_at, res) = MyDate::from_tydi_binary(res);
-        let (updated_at, res) = MyDate::from_tydi_binary(res);
-        let (likes, res) = u32::from_tydi_binary(res);
-        let (shares, res) = u32::from_tydi_binary(res);
-
-        Self {
-            post_id,
-            title: "".to_string(),
-            content: "".to_string(),
-            author,
-            created_at,
-            updated_at,
-            tags: vec![],
-            likes,
-            shares,
-            comments: vec![],
-        }
-    }
-}
-
-impl FromTydiBinary for Post {
-    fn from_tydi_binary(value: TydiBinary) -> (Self, TydiBinary) {
-        (value.into(), TydiBinary::empty())
-    }
-}
-
-impl From<Author> for TydiBinary {
-    fn from(value: Author) -> Self {
-        let author_id: TydiBinary = value.user_id.into();
-        author_id
-    }
-}
-
-impl FromTydiBinary for Author {
-    fn from_tydi_binary(value: TydiBinary) -> (Self, TydiBinary) {
-        let (user_id, res) = u32::from_tydi_binary(value);
-        let author = Self {
-            user_id,
-            username: "".to_string(),
-        };
-        (author, res)
-    }
-}
-
-impl FromTydiBinary for Comment {
-    fn from_tydi_binary(value: TydiBinary) -> (Self, TydiBinary) {
-        let (comment_id, res) = u32::from_tydi_binary(value);
-        let (author, res) = Author::from_tydi_binary(res);
-        let (created_at, res) = MyDate::from_tydi_binary(res);
-        let (likes, res) = u32::from_tydi_binary(res);
-        let author = Self {
-            comment_id,
-            author,
-            content: "".to_string(),
-            created_at,
-            likes,
-            in_reply_to_comment_id: None,
-        };
-        (author, res)
-    }
-}
-
-impl From<Comment> for TydiBinary {
-    fn from(value: Comment) -> Self {
-        let comment_id: TydiBinary = value.comment_id.into();
-        let author: TydiBinary = value.author.into();
-        let created_at: TydiBinary = value.created_at.into();
-        let likes: TydiBinary = value.likes.into();
-        comment_id.concatenate(&author).concatenate(&created_at).concatenate(&likes)
-    }
-}
-
-impl From<Post> for PostNonVecs { fn from(value: Post) -> Self { Self { post_id: value.post_id, author: value.author.into(), created_at: value.created_at, updated_at: value.updated_at, likes: value.likes, shares: value.shares } } }
-
-impl From<Post> for PostVecs { fn from(value: Post) -> Self { Self { title: value.title, content: value.content, tags: value.tags, comments: value.comments } } }
-
-
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct AuthorNonVecs {
-    pub user_id: u32,
-}
-
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct AuthorVecs {
-    pub username: String,
-}
-
-impl From<Author> for AuthorNonVecs { fn from(value: Author) -> Self { Self { user_id: value.user_id } } }
-
-impl From<Author> for AuthorVecs { fn from(value: Author) -> Self { Self { username: value.username } } }
-
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct CommentNonVecs {
-    pub comment_id: u32,
-    pub author: Author,
-    pub created_at: MyDate,
-    pub likes: u32,
-    pub in_reply_to_comment_id: Option<u32>,
-}
-
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct CommentVecs {
-    pub content: String,
-}
-
-impl From<Comment> for CommentNonVecs { fn from(value: Comment) -> Self { Self { comment_id: value.comment_id, author: value.author, created_at: value.created_at, likes: value.likes, in_reply_to_comment_id: value.in_reply_to_comment_id } } }
-
-impl From<Comment> for CommentVecs { fn from(value: Comment) -> Self { Self { content: value.content } } }
-
 #[cfg(test)]
 mod tests {
     use chrono::{DateTime, Timelike, Utc};
     use rust_tydi_packages::binary::{FromTydiBinary, TydiBinary};
-    use crate::MyDate;
+    use rust_tydi_packages::bundle::TydiBundle;
+    use rust_tydi_packages::dynamic::{DynamicTydi, TydiSchema};
+    use rust_tydi_packages::TydiBinaryStream;
+    use crate::{MyDate, Post};
 
     #[test]
     fn test_date_time_packing() {
@@ -364,5 +143,65 @@ mod tests {
         let binary: TydiBinary = dt.clone().into();
         let (reconstructed, _) = MyDate::from_tydi_binary(binary);
         assert_eq!(reconstructed, dt);
+
+        // Dates before the epoch are negative, and timestamps beyond the range of dates do not unpack
+        let before_epoch = MyDate(DateTime::from_timestamp_millis(-86_400_000).unwrap());
+        let binary: TydiBinary = before_epoch.clone().into();
+        assert_eq!(MyDate::from_tydi_binary(binary).0, before_epoch);
+        assert_eq!(MyDate::try_from_tydi_binary(TydiBinary::from(i64::MAX)).unwrap_err().to_string(),
+                   format!("{} ms since the Unix epoch is out of range: beyond the range of dates", i64::MAX));
+    }
+
+    fn post_schema() -> TydiSchema {
+        let id = TydiSchema::Integer { bits: 32, signed: false };
+        let author = TydiSchema::object(vec![("userId", id.clone()), ("username", TydiSchema::String)]);
+        let comment = TydiSchema::object(vec![
+            ("commentId", id.clone()),
+            ("author", author.clone()),
+            ("content", TydiSchema::String),
+            ("createdAt", TydiSchema::Timestamp),
+            ("likes", id.clone()),
+            ("inReplyToCommentId", TydiSchema::optional(id.clone())),
+        ]);
+        TydiSchema::object(vec![
+            ("postId", id.clone()),
+            ("title", TydiSchema::String),
+            ("content", TydiSchema::String),
+            ("author", author),
+            ("createdAt", TydiSchema::Timestamp),
+            ("updatedAt", TydiSchema::Timestamp),
+            ("tags", TydiSchema::array(TydiSchema::String)),
+            ("likes", id.clone()),
+            ("shares", id),
+            ("comments", TydiSchema::array(comment)),
+        ])
+    }
+
+    #[test]
+    fn test_dynamic_matches_derived() {
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string("posts.json").unwrap()).unwrap();
+        let posts: Vec<Post> = serde_json::from_value(json.clone()).unwrap();
+        let binary_bundle = TydiBundle::new(posts.clone()).finish();
+        let derived = binary_bundle.physical_streams("posts");
+
+        let dynamic = DynamicTydi::new(TydiSchema::array(post_schema()));
+        let encoded = dynamic.encode("posts", &json).unwrap();
+        assert_eq!(encoded.len(), derived.len());
+        for ((physical, binary), (derived_physical, derived_binary)) in encoded.iter().zip(&derived) {
+            // The streams are named after the JSON properties rather than the Rust fields
+            assert_eq!(physical.name().to_lowercase().replace('_', ""), derived_physical.name().to_lowercase().replace('_', ""));
+            assert_eq!((physical.element_width(), physical.dimensionality, physical.lanes),
+                       (derived_physical.element_width(), derived_physical.dimensionality, derived_physical.lanes));
+            assert_eq!(binary.0, derived_binary.0);
+        }
+
+        let streams: Vec<TydiBinaryStream> = encoded.into_iter().map(|(_, binary)| binary).collect();
+        assert_eq!(dynamic.decode(&streams).unwrap(), serde_json::to_value(&posts).unwrap());
+    }
+
+    #[test]
+    fn test_json_schema_matches_derived() {
+        let document = serde_json::from_str(&std::fs::read_to_string("posts.schema.json").unwrap()).unwrap();
+        assert_eq!(TydiSchema::from_json_schema(&document).unwrap(), TydiSchema::array(post_schema()));
     }
 }
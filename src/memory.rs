@@ -0,0 +1,290 @@
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use serde::Serialize;
+use crate::binary::TydiBinary;
+use crate::logical::TydiPhysicalStream;
+use crate::TydiBinaryStream;
+
+/// Memory initialization file formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TydiMemoryFormat {
+    /// Hexadecimal words, one per line, for `$readmemh`.
+    ReadMemH,
+    /// Binary words, one per line, for `$readmemb`.
+    ReadMemB,
+    /// Xilinx coefficient file, with hexadecimal words.
+    Coe,
+    /// Intel memory initialization file, with hexadecimal words.
+    Mif,
+}
+
+impl TydiMemoryFormat {
+    /// Usual extension of files in this format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::ReadMemH => "memh",
+            Self::ReadMemB => "memb",
+            Self::Coe => "coe",
+            Self::Mif => "mif",
+        }
+    }
+}
+
+/// How the packets of a stream are laid out in memory words.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TydiMemoryOptions {
+    pub format: TydiMemoryFormat,
+    /// Number of bits per word, or `None` for words as wide as the widest packet.
+    pub word_width: Option<usize>,
+    /// Whether every packet is zero-filled up to a whole number of words, so that every packet starts at a word of its
+    /// own. Otherwise the packets follow each other without gaps, and only the final word is zero-filled.
+    pub pad_packets: bool,
+}
+
+impl TydiMemoryOptions {
+    pub fn new(format: TydiMemoryFormat) -> Self {
+        Self { format, word_width: None, pad_packets: true }
+    }
+}
+
+/// How a stream ended up in memory, as recorded in a [TydiMemoryManifest].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TydiMemoryLayout {
+    pub format: TydiMemoryFormat,
+    pub word_width: usize,
+    /// Number of bits of the widest packet.
+    pub packet_width: usize,
+    pub packets: usize,
+    /// Number of words a packet takes up, if every packet starts at a word of its own.
+    pub words_per_packet: Option<usize>,
+    pub words: usize,
+}
+
+/// Sidecar of the memory files of a set of physical streams.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TydiMemoryManifest {
+    pub streams: Vec<TydiMemoryManifestEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TydiMemoryManifestEntry {
+    /// Name of the physical stream.
+    pub name: String,
+    /// Name of the memory file, relative to the manifest.
+    pub file: String,
+    pub dimensionality: usize,
+    pub lanes: usize,
+    #[serde(flatten)]
+    pub layout: TydiMemoryLayout,
+}
+
+/// Splits the packets of [stream] into memory words following [options].
+///
+/// The first word of a packet holds its first bits according to the [crate::order::TydiBitOrder] the packet was packed in, which are
+/// the least significant bits by default.
+pub fn memory_words(stream: &TydiBinaryStream, options: &TydiMemoryOptions) -> (Vec<TydiBinary>, TydiMemoryLayout) {
+    let packet_width = stream.0.iter().map(|packet| packet.len).max().unwrap_or(0);
+    let word_width = options.word_width.unwrap_or(packet_width).max(1);
+
+    let split = |binary: &TydiBinary, words: &mut Vec<TydiBinary>| {
+        let mut rest = binary.as_bits();
+        while !rest.is_empty() {
+            let (word, remainder) = rest.split_field(word_width.min(rest.len()));
+            let mut word = word.to_binary();
+            word.append(&TydiBinary::zeros(word_width - word.len).in_order(word.order));
+            words.push(word);
+            rest = remainder;
+        }
+    };
+
+    let mut words = Vec::new();
+    if options.pad_packets {
+        for packet in &stream.0 {
+            split(packet, &mut words);
+        }
+    } else {
+        let mut all = TydiBinary::with_capacity(stream.0.iter().map(|packet| packet.len).sum());
+        stream.0.iter().for_each(|packet| all.append(packet));
+        split(&all, &mut words);
+    }
+
+    let words_per_packet = options.pad_packets.then(|| packet_width.div_ceil(word_width));
+    let layout = TydiMemoryLayout {
+        format: options.format,
+        word_width,
+        packet_width,
+        packets: stream.0.len(),
+        words_per_packet,
+        words: words.len(),
+    };
+    (words, layout)
+}
+
+/// Digits of [word] with [bits_per_digit] bits each, the most significant digit first.
+fn word_digits(word: &TydiBinary, bits_per_digit: usize) -> String {
+    let bits = word.as_bits();
+    (0..bits.len().div_ceil(bits_per_digit)).rev().map(|digit| {
+        let start = digit * bits_per_digit;
+        let value = bits.read_bits(start, bits_per_digit.min(bits.len() - start));
+        char::from_digit(value as u32, 16).unwrap()
+    }).collect()
+}
+
+/// Writes the packets of [stream] as a memory initialization file, see [memory_words] for the layout, and flushes
+/// [writer].
+pub fn write_memory<W: Write>(mut writer: W, stream: &TydiBinaryStream, options: &TydiMemoryOptions) -> io::Result<TydiMemoryLayout> {
+    let (words, layout) = memory_words(stream, options);
+    let summary = format!("{} packets of {} bits in {} words of {} bits", layout.packets, layout.packet_width, layout.words, layout.word_width);
+    let hex = |word: &TydiBinary| word_digits(word, 4);
+
+    match options.format {
+        TydiMemoryFormat::ReadMemH | TydiMemoryFormat::ReadMemB => {
+            writeln!(writer, "// {}", summary)?;
+            for word in &words {
+                let digits = if options.format == TydiMemoryFormat::ReadMemH { hex(word) } else { word_digits(word, 1) };
+                writeln!(writer, "{}", digits)?;
+            }
+        }
+        TydiMemoryFormat::Coe => {
+            writeln!(writer, "; {}", summary)?;
+            writeln!(writer, "memory_initialization_radix=16;")?;
+            if words.is_empty() {
+                writeln!(writer, "memory_initialization_vector=;")?;
+            } else {
+                writeln!(writer, "memory_initialization_vector=")?;
+                for (i, word) in words.iter().enumerate() {
+                    writeln!(writer, "{}{}", hex(word), if i == words.len() - 1 { ";" } else { "," })?;
+                }
+            }
+        }
+        TydiMemoryFormat::Mif => {
+            writeln!(writer, "-- {}", summary)?;
+            writeln!(writer, "WIDTH={};", layout.word_width)?;
+            writeln!(writer, "DEPTH={};", layout.words)?;
+            writeln!(writer, "ADDRESS_RADIX=UNS;")?;
+            writeln!(writer, "DATA_RADIX=HEX;")?;
+            writeln!(writer, "CONTENT BEGIN")?;
+            for (i, word) in words.iter().enumerate() {
+                writeln!(writer, "\t{} : {};", i, hex(word))?;
+            }
+            writeln!(writer, "END;")?;
+        }
+    }
+    writer.flush()?;
+    Ok(layout)
+}
+
+/// [write_memory] into a string.
+pub fn to_memory(stream: &TydiBinaryStream, options: &TydiMemoryOptions) -> (String, TydiMemoryLayout) {
+    let mut result = Vec::new();
+    let layout = write_memory(&mut result, stream, options).expect("writing to memory does not fail");
+    (String::from_utf8(result).expect("memory files are ASCII"), layout)
+}
+
+/// Writes a memory file per physical stream into [directory], named after the stream, along with `manifest.json`
+/// describing them.
+pub fn write_memory_files(directory: &Path, streams: &[(TydiPhysicalStream, &TydiBinaryStream)], options: &TydiMemoryOptions) -> io::Result<TydiMemoryManifest> {
+    let mut entries = Vec::new();
+    for (stream, binaries) in streams {
+        let file = format!("{}.{}", stream.name(), options.format.extension());
+        let mut writer = BufWriter::new(File::create(directory.join(&file))?);
+        let layout = write_memory(&mut writer, binaries, options)?;
+        writer.into_inner().map_err(io::IntoInnerError::into_error)?;
+        entries.push(TydiMemoryManifestEntry {
+            name: stream.name(),
+            file,
+            dimensionality: stream.dimensionality,
+            lanes: stream.lanes,
+            layout,
+        });
+    }
+    let manifest = TydiMemoryManifest { streams: entries };
+    let json = serde_json::to_string_pretty(&manifest).map_err(io::Error::other)?;
+    std::fs::write(directory.join("manifest.json"), json)?;
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drilling::{TydiConvert, TydiPacktestToBinary};
+
+    fn bytes() -> TydiBinaryStream {
+        // Packets of 1 strobe, 1 last and 8 data bits
+        vec![0xABu8, 0x01].convert().finish(8)
+    }
+
+    #[test]
+    fn test_readmem() {
+        let (memh, layout) = to_memory(&bytes(), &TydiMemoryOptions::new(TydiMemoryFormat::ReadMemH));
+        assert_eq!(memh, "// 2 packets of 10 bits in 2 words of 10 bits\n2ad\n007\n");
+        assert_eq!(layout.words_per_packet, Some(1));
+
+        let options = TydiMemoryOptions { word_width: Some(8), ..TydiMemoryOptions::new(TydiMemoryFormat::ReadMemB) };
+        let (memb, layout) = to_memory(&bytes(), &options);
+        // Every packet takes two words, the first holding its least significant bits
+        assert_eq!(memb.lines().skip(1).collect::<Vec<_>>(), vec!["10101101", "00000010", "00000111", "00000000"]);
+        assert_eq!(layout.words, 4);
+
+        let options = TydiMemoryOptions { pad_packets: false, ..options };
+        let (memb, layout) = to_memory(&bytes(), &options);
+        assert_eq!(memb.lines().skip(1).collect::<Vec<_>>(), vec!["10101101", "00011110", "00000000"]);
+        assert_eq!(layout.words_per_packet, None);
+    }
+
+    #[test]
+    fn test_coe_and_mif() {
+        let options = TydiMemoryOptions { word_width: Some(16), ..TydiMemoryOptions::new(TydiMemoryFormat::Coe) };
+        let (coe, _) = to_memory(&bytes(), &options);
+        assert!(coe.ends_with("memory_initialization_radix=16;\nmemory_initialization_vector=\n02ad,\n0007;\n"));
+
+        let options = TydiMemoryOptions { format: TydiMemoryFormat::Mif, ..options };
+        let (mif, _) = to_memory(&bytes(), &options);
+        assert!(mif.contains("WIDTH=16;\nDEPTH=2;\n"));
+        assert!(mif.contains("CONTENT BEGIN\n\t0 : 02ad;\n\t1 : 0007;\nEND;\n"));
+
+        // The vector is terminated even without words
+        let options = TydiMemoryOptions::new(TydiMemoryFormat::Coe);
+        let (coe, layout) = to_memory(&TydiBinaryStream(vec![]), &options);
+        assert!(coe.ends_with("memory_initialization_radix=16;\nmemory_initialization_vector=;\n"), "{}", coe);
+        assert_eq!(layout.words, 0);
+    }
+
+    /// A writer that accepts everything until it is flushed, like a buffered writer to a full disk.
+    struct FailingFlush;
+
+    impl Write for FailingFlush {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Err(io::Error::other("no space left"))
+        }
+    }
+
+    #[test]
+    fn test_flush_errors() {
+        let options = TydiMemoryOptions::new(TydiMemoryFormat::ReadMemH);
+        let error = write_memory(FailingFlush, &bytes(), &options).unwrap_err();
+        assert_eq!(error.to_string(), "no space left");
+    }
+
+    #[test]
+    fn test_manifest() {
+        let directory = std::env::temp_dir().join(format!("tydi-memory-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let physical = crate::logical::TydiLogicalType::stream(crate::logical::TydiLogicalType::Bits(8), 1).split("bytes");
+        let binaries = bytes();
+        let manifest = write_memory_files(&directory, &[(physical[0].clone(), &binaries)], &TydiMemoryOptions::new(TydiMemoryFormat::ReadMemH)).unwrap();
+        assert_eq!(manifest.streams[0].file, "bytes.memh");
+        assert!(directory.join("bytes.memh").exists());
+        let json = std::fs::read_to_string(directory.join("manifest.json")).unwrap();
+        assert!(json.contains("\"packets\": 2"));
+        assert!(json.contains("\"format\": \"readmemh\""));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,113 @@
+/// Order of the bytes of a primitive within its field.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TydiEndianness {
+    /// The least significant byte comes first, at the least significant bits of the field.
+    #[default]
+    Little,
+    /// The most significant byte comes first, at the least significant bits of the field.
+    ///
+    /// Only fields of a whole number of bytes are swapped. Generated hardware types hold the bytes of a primitive as
+    /// packed, so these are swapped relative to its numeric value, while the accessors of generated C headers swap them
+    /// back.
+    Big,
+}
+
+/// Placement of consecutive fields within a binary.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TydiFieldOrder {
+    /// The first field is at the least significant bits, as in the Tydi specification.
+    #[default]
+    LsbFirst,
+    /// The first field is at the most significant bits, as in a VHDL record converted to a `std_logic_vector`.
+    MsbFirst,
+}
+
+/// Wire format of binaries, independent of the host.
+///
+/// The order applies to [crate::binary::TydiBinary::concatenate], [crate::binary::TydiBinary::append],
+/// [crate::binary::TydiBinary::split], [crate::cursor::TydiBitReader] and the conversions of primitives and `char`,
+/// and with that to everything that packs or unpacks through them. `Display` prints the most significant bit first, so
+/// it shows the first field on the right with [TydiFieldOrder::LsbFirst] and on the left with
+/// [TydiFieldOrder::MsbFirst].
+///
+/// Values are packed in [TydiBitOrder::PORTABLE] through `From`, and in any order through
+/// [crate::binary::ToTydiBinary] or a writer from [crate::cursor::TydiBitWriter::in_order]. Every binary records the
+/// order it was packed in, and is split and unpacked in that order. Generators of hardware types and headers take the
+/// order they describe as an argument.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TydiBitOrder {
+    pub endianness: TydiEndianness,
+    pub field_order: TydiFieldOrder,
+}
+
+impl TydiBitOrder {
+    /// Little endian with the first field at the least significant bits, the default.
+    pub const PORTABLE: Self = Self { endianness: TydiEndianness::Little, field_order: TydiFieldOrder::LsbFirst };
+
+    pub fn new(endianness: TydiEndianness, field_order: TydiFieldOrder) -> Self {
+        Self { endianness, field_order }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::{FromTydiBinary, ToTydiBinary, TydiBinary};
+    use crate::cursor::TydiBitReader;
+    use crate::TydiPacket;
+
+    const ORDERS: [TydiBitOrder; 4] = [
+        TydiBitOrder::PORTABLE,
+        TydiBitOrder { endianness: TydiEndianness::Big, field_order: TydiFieldOrder::LsbFirst },
+        TydiBitOrder { endianness: TydiEndianness::Little, field_order: TydiFieldOrder::MsbFirst },
+        TydiBitOrder { endianness: TydiEndianness::Big, field_order: TydiFieldOrder::MsbFirst },
+    ];
+
+    #[test]
+    fn test_bit_placement() {
+        let big = TydiBitOrder::new(TydiEndianness::Big, TydiFieldOrder::LsbFirst);
+        assert_eq!(TydiBinary::from(0x1234u16).data, vec![0x34, 0x12]);
+        assert_eq!(0x1234u16.to_tydi_binary(big).data, vec![0x12, 0x34]);
+
+        let msb_first = TydiBitOrder::new(TydiEndianness::Little, TydiFieldOrder::MsbFirst);
+        // The first field ends up in the most significant bits, as in a VHDL record
+        let binary = (0x5u8, true).to_tydi_binary(msb_first);
+        assert_eq!(binary.data, vec![0x0B, 0x00]);
+        assert_eq!(binary.to_string().replace(' ', ""), "0b000001011");
+        let mut reader = TydiBitReader::new(&binary);
+        assert_eq!(reader.peek_bits(8), 0x5);
+        assert_eq!(reader.read::<u8>(), 0x5);
+        assert!(reader.read::<bool>());
+    }
+
+    #[test]
+    fn test_roundtrip_in_all_orders() {
+        for order in ORDERS {
+            let value = (0xBEEFu16, 'ä', Some(-7i32), [1u8, 2, 3]);
+            let packet = TydiPacket { data: Some(value), last: vec![false, true] };
+            let binary = packet.clone().to_binary_in(16 + 32 + 33 + 24, order);
+            assert_eq!(binary.order, order);
+            assert_eq!(TydiPacket::from_binary(binary.clone(), 2), packet, "{:?}", order);
+            let (data, res) = <(u16, char, Option<i32>, [u8; 3])>::from_tydi_binary(binary.split(3).1);
+            assert_eq!((data, res.len), (value, 0), "{:?}", order);
+        }
+    }
+
+    #[test]
+    fn test_binaries_keep_their_order() {
+        let big_msb_first = TydiBitOrder::new(TydiEndianness::Big, TydiFieldOrder::MsbFirst);
+        let value = (0xBEEFu16, Some(-7i32));
+        let binary = value.to_tydi_binary(big_msb_first);
+        assert_eq!(binary.order, big_msb_first);
+        // Unpacking follows the binary, on other threads alike
+        assert_eq!(<(u16, Option<i32>)>::from_tydi_binary(binary.clone()).0, value);
+        let unpacked = std::thread::spawn(move || <(u16, Option<i32>)>::from_tydi_binary(binary).0).join().unwrap();
+        assert_eq!(unpacked, value);
+        // Packing in one order does not affect values packed afterwards
+        assert_eq!(TydiBinary::from(0xBEEFu16).order, TydiBitOrder::PORTABLE);
+
+        let mut mixed = TydiBinary::from(1u8);
+        let result = std::panic::catch_unwind(move || mixed.append(&2u8.to_tydi_binary(big_msb_first)));
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,191 @@
+use std::cmp::Reverse;
+use std::fmt::Write;
+use crate::layout::{declare, extended, join, stream_paths, Declarations, Member};
+use crate::logical::{TydiLogical, TydiLogicalType, TydiPhysicalStream};
+use crate::order::{TydiBitOrder, TydiFieldOrder};
+use crate::vcd::field_widths;
+
+/// Keywords of SystemVerilog that are likely to clash with field names, which cannot be used as simple identifiers.
+const RESERVED: &[&str] = &[
+    "always", "and", "assign", "automatic", "begin", "bit", "buf", "byte", "case", "class", "const", "default",
+    "disable", "do", "edge", "else", "end", "endcase", "endfunction", "endinterface", "endmodule", "endpackage",
+    "enum", "event", "extends", "final", "for", "force", "forever", "fork", "function", "generate", "genvar", "if",
+    "import", "initial", "inout", "input", "int", "integer", "interface", "join", "local", "localparam", "logic",
+    "longint", "module", "modport", "negedge", "new", "not", "null", "or", "output", "package", "packed", "parameter",
+    "posedge", "priority", "program", "property", "protected", "pure", "rand", "real", "reg", "repeat", "return",
+    "shortint", "signed", "static", "string", "struct", "super", "supply0", "supply1", "table", "task", "this", "time",
+    "tri", "type", "typedef", "union", "unique", "unsigned", "var", "virtual", "void", "wait", "while", "wire", "with",
+    "xor",
+];
+
+/// SystemVerilog identifier of the sanitized [parts] joined by `__`, made an escaped identifier if it is a keyword.
+pub fn sv_identifier<S: AsRef<str>>(parts: &[S]) -> String {
+    let name = join(parts);
+    if RESERVED.contains(&name.as_str()) { format!("\\{} ", name) } else { name }
+}
+
+/// Type of a bit vector of [width] bits, where a single bit is a plain `logic`.
+fn sv_bits(width: usize) -> String {
+    if width == 1 { "logic".to_string() } else { format!("logic [{}:0]", width - 1) }
+}
+
+/// Declarations of the packed structs of a package, collected while walking logical types.
+///
+/// Unions become structs of a `tag` and a `payload`, as the members of a packed union need to be equally wide.
+#[derive(Default)]
+struct Structs(String);
+
+impl Declarations for Structs {
+    type Type = String;
+
+    fn bits(&mut self, width: usize) -> String {
+        sv_bits(width)
+    }
+
+    /// Declares a parameter with the tag of every variant.
+    fn tags(&mut self, parts: &[String], variants: &[String]) {
+        for (i, name) in variants.iter().enumerate() {
+            let parameter = sv_identifier(&extended(parts, &[name.as_str(), "tag"])).to_uppercase();
+            writeln!(self.0, "  localparam int {} = {};", parameter, i).unwrap();
+        }
+    }
+
+    /// Declares a packed struct, whose first declared member takes the most significant bits.
+    fn record(&mut self, parts: &[String], _: usize, mut members: Vec<Member<String>>) -> String {
+        members.sort_by_key(|member| Reverse(member.offset));
+        let type_name = sv_identifier(&extended(parts, &["t"]));
+        writeln!(self.0, "  typedef struct packed {{").unwrap();
+        for member in members {
+            writeln!(self.0, "    {} {};", member.declared, sv_identifier(&[member.name])).unwrap();
+        }
+        writeln!(self.0, "  }} {};", type_name).unwrap();
+        type_name
+    }
+}
+
+/// Name of a width parameter of the stream at [path] in a package generated by [sv_package].
+fn width_parameter(path: &[String], signal: &str) -> String {
+    sv_identifier(&extended(path, &[signal, "width"])).to_uppercase()
+}
+
+/// Generates a SystemVerilog package named `{name}_pkg` for the [streams], as split off from a logical type named
+/// [name].
+///
+/// For every stream, the package holds parameters with the widths of its signals, and packed structs for its element
+/// and user types, as far as these are groups or unions. A struct holds its fields at the bits they are packed at by
+/// [crate::TydiPacket::to_binary] in [order], so a packed binary can be assigned to it as is.
+pub fn sv_package(name: &str, streams: &[TydiPhysicalStream], order: TydiBitOrder) -> String {
+    let order = order.field_order;
+    let mut declarations = Structs::default();
+    for (stream, path) in streams.iter().zip(stream_paths(streams)) {
+        writeln!(declarations.0, "  // {}: {} lane(s), dimensionality {}, complexity {}, {:?}",
+                 stream.name(), stream.lanes, stream.dimensionality, stream.complexity, stream.direction).unwrap();
+        for (signal, width) in field_widths(stream) {
+            writeln!(declarations.0, "  localparam int {} = {};", width_parameter(&path, signal), width).unwrap();
+        }
+        writeln!(declarations.0, "  localparam int {} = {};", width_parameter(&path, "user"), stream.user.data_width()).unwrap();
+        writeln!(declarations.0, "  localparam int {} = {};", width_parameter(&path, "element"), stream.element_width()).unwrap();
+
+        declare(&mut declarations, &extended(&path, &["element"]), &stream.element, order);
+        declare(&mut declarations, &extended(&path, &["user"]), &stream.user, order);
+    }
+    format!("package {};\n{}endpackage\n", sv_identifier(&[name, "pkg"]), declarations.0)
+}
+
+/// Generates an interface named `{stream}_if` for every stream of [streams], using the declarations of [sv_package].
+///
+/// The interfaces hold `valid`, `ready`, `data`, `last`, `stai`, `endi`, `strb` and `user`, leaving out signals without
+/// width, and have a `source` and a `sink` modport. `data` is a packed array with an entry per lane, lane 0 taking the
+/// bits it takes in [crate::transfer::TydiTransfer::to_binary]. Its entries are the packed element structs, if any.
+pub fn sv_interfaces(name: &str, streams: &[TydiPhysicalStream], order: TydiBitOrder) -> String {
+    let package = sv_identifier(&[name, "pkg"]);
+    let mut result = String::new();
+    for (stream, path) in streams.iter().zip(stream_paths(streams)) {
+        let lanes = match order.field_order {
+            TydiFieldOrder::LsbFirst => format!("[{}-1:0]", width_parameter(&path, "strb")),
+            TydiFieldOrder::MsbFirst => format!("[0:{}-1]", width_parameter(&path, "strb")),
+        };
+        let data = match &stream.element {
+            TydiLogicalType::Group(_) | TydiLogicalType::Union(_) =>
+                format!("{} {}", sv_identifier(&extended(&path, &["element", "t"])), lanes),
+            _ => format!("logic {}[{}-1:0]", lanes, width_parameter(&path, "element")),
+        };
+
+        let mut payload = Vec::new();
+        writeln!(result, "interface {};", sv_identifier(&extended(&path, &["if"]))).unwrap();
+        writeln!(result, "  import {}::*;", package).unwrap();
+        writeln!(result, "  logic valid;\n  logic ready;").unwrap();
+        if stream.element_width() > 0 {
+            writeln!(result, "  {} data;", data).unwrap();
+            payload.push("data");
+        }
+        let widths = field_widths(stream);
+        for (signal, width) in [("last", widths[3].1), ("stai", widths[1].1), ("endi", widths[2].1),
+            ("strb", widths[0].1), ("user", stream.user.data_width())] {
+            if width > 0 {
+                writeln!(result, "  logic [{}-1:0] {};", width_parameter(&path, signal), signal).unwrap();
+                payload.push(signal);
+            }
+        }
+        let payload = payload.join(", ");
+        writeln!(result, "  modport source (output valid, {}, input ready);", payload).unwrap();
+        writeln!(result, "  modport sink (input valid, {}, output ready);", payload).unwrap();
+        writeln!(result, "endinterface\n").unwrap();
+    }
+    result.pop();
+    result
+}
+
+/// [sv_package] followed by [sv_interfaces] for the physical streams of [T], named after [name].
+pub fn sv_package_for<T: TydiLogical>(name: &str, order: TydiBitOrder) -> String {
+    let streams = T::logical_type().split(name);
+    format!("{}\n{}", sv_package(name, &streams, order), sv_interfaces(name, &streams, order))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::tests::readings;
+    use crate::logical::TydiLogicalStream;
+    use crate::logical::TydiLogicalType::Bits;
+    use crate::order::TydiEndianness;
+
+    #[test]
+    fn test_identifiers() {
+        assert_eq!(sv_identifier(&["posts", "title"]), "posts__title");
+        assert_eq!(sv_identifier(&["_private", "1st"]), "private__n1st");
+        assert_eq!(sv_identifier(&["logic"]), "\\logic ");
+        assert_ne!(sv_identifier(&["a_b", "c"]), sv_identifier(&["a", "b_c"]));
+    }
+
+    #[test]
+    fn test_package() {
+        let package = sv_package("sensor", &readings().split("readings"), TydiBitOrder::PORTABLE);
+        assert!(package.starts_with("package sensor__pkg;\n"));
+        assert!(package.contains("  localparam int READINGS__LAST__WIDTH = 1;\n"));
+        assert!(package.contains("  localparam int READINGS__ELEMENT__UNIT__KELVIN__TAG = 2;\n"));
+        // The first field is declared last, as it takes the least significant bits
+        assert!(package.contains("  typedef struct packed {\n    logic [7:0] payload;\n    logic [1:0] tag;\n  \
+            } readings__element__unit__t;\n"));
+        assert!(package.contains("  typedef struct packed {\n    readings__element__unit__t unit;\n    \
+            logic [15:0] value;\n    logic valid;\n  } readings__element__t;\n"));
+        assert!(package.ends_with("endpackage\n"));
+
+        let msb_first = TydiBitOrder::new(TydiEndianness::Little, TydiFieldOrder::MsbFirst);
+        let package = sv_package("sensor", &readings().split("readings"), msb_first);
+        assert!(package.contains("    logic valid;\n    logic [15:0] value;\n    readings__element__unit__t unit;\n"));
+    }
+
+    #[test]
+    fn test_interfaces() {
+        let mut chars = TydiLogicalStream::new(Bits(8), 1);
+        chars.throughput = 4.0;
+        let streams = [readings().split("readings"), TydiLogicalType::Stream(Box::new(chars)).split("chars")].concat();
+        let interfaces = sv_interfaces("sensor", &streams, TydiBitOrder::PORTABLE);
+        assert!(interfaces.starts_with("interface readings__if;\n  import sensor__pkg::*;\n  logic valid;\n  logic ready;\n  \
+            readings__element__t [READINGS__STRB__WIDTH-1:0] data;\n  logic [READINGS__LAST__WIDTH-1:0] last;\n"));
+        assert!(interfaces.contains("  logic [CHARS__STRB__WIDTH-1:0][CHARS__ELEMENT__WIDTH-1:0] data;\n"));
+        assert!(interfaces.contains("  modport source (output valid, data, last, stai, endi, strb, input ready);\n  \
+            modport sink (input valid, data, last, stai, endi, strb, output ready);\nendinterface\n"));
+    }
+}
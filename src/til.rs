@@ -0,0 +1,595 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+use crate::complexity::TydiComplexity;
+use crate::error::TydiError;
+use crate::layout::{distinct, sanitize};
+use crate::logical::{TydiDirection, TydiLogical, TydiLogicalStream, TydiLogicalType, TydiSynchronicity};
+use crate::vhdl::TydiPortMode;
+
+/// Names with a meaning of their own in TIL, which fields are not named as.
+const KEYWORDS: &[&str] = &[
+    "namespace", "type", "interface", "streamlet", "impl", "import", "in", "out", "Null", "Bits", "Group", "Union",
+    "Stream",
+];
+
+/// [name] as the name of a field, with an underscore appended if it is a keyword followed by any underscores.
+fn escape(name: &str) -> String {
+    if KEYWORDS.contains(&name.trim_end_matches('_')) { format!("{}_", name) } else { name.to_string() }
+}
+
+/// Reverses [escape].
+fn unescape(mut name: String) -> String {
+    if name.ends_with('_') && KEYWORDS.contains(&name.trim_end_matches('_')) {
+        name.pop();
+    }
+    name
+}
+
+/// Declarations of a TIL namespace, collected while walking a logical type.
+#[derive(Default)]
+struct Declarations {
+    types: String,
+    names: HashSet<String>,
+}
+
+impl Declarations {
+    /// Name of the type at [path]: its [sanitize]d parts joined by `_`, numbered if that name was declared before.
+    fn name(&mut self, path: &[String]) -> String {
+        let name = path.iter().map(|part| sanitize(part)).collect::<Vec<_>>().join("_");
+        let mut result = name.clone();
+        let mut n = 1;
+        while !self.names.insert(result.clone()) {
+            n += 1;
+            result = format!("{}_{}", name, n);
+        }
+        result
+    }
+
+    /// The TIL expression of [t], declaring the groups, unions and streams in it as types named after [path].
+    ///
+    /// The element of a stream is named after the stream, followed by `element`. Types are declared before their
+    /// parents, so of the types at paths that give the same name, the first declared keeps it.
+    fn expression(&mut self, path: &[String], t: &TydiLogicalType) -> String {
+        match t {
+            TydiLogicalType::Null => "Null".to_string(),
+            TydiLogicalType::Bits(width) => format!("Bits({})", width),
+            TydiLogicalType::Group(fields) | TydiLogicalType::Union(fields) => {
+                let kind = if matches!(t, TydiLogicalType::Group(_)) { "Group" } else { "Union" };
+                let names = distinct(&fields.iter().map(|(name, _)| name).collect::<Vec<_>>());
+                let fields: Vec<String> = fields.iter().zip(names).map(|((_, field_type), field)| {
+                    let field_path = [path, std::slice::from_ref(&field)].concat();
+                    format!("        {}: {},\n", escape(&field), self.expression(&field_path, field_type))
+                }).collect();
+                let name = self.name(path);
+                writeln!(self.types, "    type {} = {}(\n{}    );", name, kind, fields.concat()).unwrap();
+                name
+            }
+            TydiLogicalType::Stream(stream) => {
+                let data = self.expression(&[path, &["element".to_string()]].concat(), &stream.element);
+                let user = self.expression(&[path, &["user".to_string()]].concat(), &stream.user);
+                let name = self.name(path);
+                let synchronicity = match stream.synchronicity {
+                    TydiSynchronicity::Sync => "Sync",
+                    TydiSynchronicity::Flatten => "Flatten",
+                    TydiSynchronicity::Desync => "Desync",
+                    TydiSynchronicity::FlatDesync => "FlatDesync",
+                };
+                writeln!(self.types, "    type {} = Stream(\n        data: {},\n        throughput: {:?},\n        \
+                    dimensionality: {},\n        synchronicity: {},\n        complexity: {},\n        \
+                    direction: {:?},\n        user: {},\n        keep: {},\n    );",
+                    name, data, stream.throughput, stream.dimensionality, synchronicity, stream.complexity.level(),
+                    stream.direction, user, stream.keep).unwrap();
+                name
+            }
+        }
+    }
+}
+
+/// Writes [t] as a TIL (Tydi intermediate language) type named [name] in the namespace [namespace], such as
+/// `my::example`.
+///
+/// Groups, unions and streams are declared as types of their own, named after the path of field names leading to them.
+/// Bits and nulls are written inline. Types are declared before they are used, [name] being declared last.
+///
+/// Fields are named [distinct]ly, as in the other generators, so names that are not TIL identifiers are [sanitize]d
+/// and fields keep their order. Fields named as TIL keywords get an underscore appended, which [parse_til] removes.
+pub fn til_namespace(namespace: &str, name: &str, t: &TydiLogicalType) -> String {
+    let name = sanitize(name);
+    let mut declarations = Declarations::default();
+    let root = declarations.expression(std::slice::from_ref(&name), t);
+    if root != name {
+        writeln!(declarations.types, "    type {} = {};", name, root).unwrap();
+    }
+    format!("namespace {} {{\n{}}}\n", namespace, declarations.types)
+}
+
+/// [til_namespace] for the logical type of [T], such as `Vec<Post>` for a stream of posts.
+///
+/// The throughput and complexity of the streams of sequence fields are set through `#[tydi(throughput = ..)]` and
+/// `#[tydi(complexity = ..)]` on those fields. Their dimensionality follows from the nesting of the sequences.
+pub fn til_for<T: TydiLogical>(namespace: &str, name: &str) -> String {
+    til_namespace(namespace, name, &T::logical_type())
+}
+
+/// A port of a streamlet or interface declared in TIL.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TydiPort {
+    pub name: String,
+    /// Side of the streams of the port the streamlet is on, [TydiPortMode::Source] for `out` ports and
+    /// [TydiPortMode::Sink] for `in` ports.
+    pub mode: TydiPortMode,
+    pub logical_type: TydiLogicalType,
+}
+
+/// The declarations of a TIL namespace, with references to other types resolved.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TydiNamespace {
+    /// Path of the namespace, such as `["my", "example"]`.
+    pub name: Vec<String>,
+    /// Types in order of declaration.
+    pub types: Vec<(String, TydiLogicalType)>,
+    /// Interfaces in order of declaration, along with their ports.
+    pub interfaces: Vec<(String, Vec<TydiPort>)>,
+    /// Streamlets in order of declaration, along with their ports. Their implementations are left out.
+    pub streamlets: Vec<(String, Vec<TydiPort>)>,
+}
+
+impl TydiNamespace {
+    /// The type declared as [name].
+    pub fn logical_type(&self, name: &str) -> Option<&TydiLogicalType> {
+        self.types.iter().find(|(n, _)| n == name).map(|(_, t)| t)
+    }
+
+    /// The ports of the streamlet declared as [name].
+    pub fn streamlet(&self, name: &str) -> Option<&[TydiPort]> {
+        self.streamlets.iter().find(|(n, _)| n == name).map(|(_, ports)| ports.as_slice())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Identifier(String),
+    Number(String),
+    Text(String),
+    Symbol(&'static str),
+    /// A character that is not part of the declarations, such as in the body of an implementation.
+    Other(char),
+}
+
+/// Splits [input] into tokens along with the lines they are on, leaving out `//` comments and `#..#` documentation.
+/// Only an unterminated string or documentation comment is an error.
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, TydiError> {
+    const SYMBOLS: &[&str] = &["::", "{", "}", "(", ")", ":", ";", ",", "="];
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut rest = input;
+    while let Some(c) = rest.chars().next() {
+        let (token, length) = if c.is_whitespace() {
+            line += (c == '\n') as usize;
+            (None, c.len_utf8())
+        } else if rest.starts_with("//") {
+            (None, rest.find('\n').unwrap_or(rest.len()))
+        } else if c == '#' || c == '"' {
+            let Some(end) = rest[1..].find(c) else {
+                return Err(TydiError::InvalidTil { line, reason: format!("unterminated {}", c) });
+            };
+            let text = &rest[1..end + 1];
+            line += text.matches('\n').count();
+            ((c == '"').then(|| Token::Text(text.to_string())), end + 2)
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let length = rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(rest.len());
+            (Some(Token::Identifier(rest[..length].to_string())), length)
+        } else if c.is_ascii_digit() {
+            let length = rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rest.len());
+            (Some(Token::Number(rest[..length].to_string())), length)
+        } else if let Some(symbol) = SYMBOLS.iter().find(|s| rest.starts_with(**s)) {
+            (Some(Token::Symbol(symbol)), symbol.len())
+        } else {
+            (Some(Token::Other(c)), c.len_utf8())
+        };
+        tokens.extend(token.map(|token| (token, line)));
+        rest = &rest[length..];
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser over the tokens of a TIL document.
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    /// The namespaces parsed so far, the final one being the one that is being parsed.
+    namespaces: Vec<TydiNamespace>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn error(&self, reason: String) -> TydiError {
+        let line = self.tokens.get(self.position).or(self.tokens.last()).map_or(1, |(_, line)| *line);
+        TydiError::InvalidTil { line, reason }
+    }
+
+    fn next(&mut self, expected: &str) -> Result<Token, TydiError> {
+        let token = self.peek().cloned().ok_or_else(|| self.error(format!("expected {}, found the end", expected)))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn unexpected<T>(&mut self, expected: &str) -> Result<T, TydiError> {
+        self.position -= 1;
+        Err(self.error(format!("expected {}, found {:?}", expected, self.tokens[self.position].0)))
+    }
+
+    /// Consumes [symbol] if it is next.
+    fn eat(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol);
+        self.position += found as usize;
+        found
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), TydiError> {
+        if self.eat(symbol) { Ok(()) } else { Err(self.error(format!("expected `{}`", symbol))) }
+    }
+
+    fn identifier(&mut self) -> Result<String, TydiError> {
+        match self.next("a name")? {
+            Token::Identifier(name) => Ok(name),
+            _ => self.unexpected("a name"),
+        }
+    }
+
+    fn number<T: std::str::FromStr>(&mut self) -> Result<T, TydiError> {
+        match self.next("a number")? {
+            Token::Number(n) => n.parse().or_else(|_| self.unexpected("a number")),
+            _ => self.unexpected("a number"),
+        }
+    }
+
+    /// Names separated by `::`.
+    fn path(&mut self) -> Result<Vec<String>, TydiError> {
+        let mut path = vec![self.identifier()?];
+        while self.eat("::") {
+            path.push(self.identifier()?);
+        }
+        Ok(path)
+    }
+
+    /// Parenthesized [item]s separated by commas, allowing a trailing comma.
+    fn list<T>(&mut self, mut item: impl FnMut(&mut Self) -> Result<T, TydiError>) -> Result<Vec<T>, TydiError> {
+        self.expect("(")?;
+        let mut items = Vec::new();
+        while !self.eat(")") {
+            items.push(item(self)?);
+            if !self.eat(",") {
+                self.expect(")")?;
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    /// Skips tokens up to and including the next `;` outside of braces.
+    fn skip_statement(&mut self) -> Result<(), TydiError> {
+        let mut depth = 0;
+        loop {
+            match self.next("`;`")? {
+                Token::Symbol("{") => depth += 1,
+                Token::Symbol("}") if depth > 0 => depth -= 1,
+                Token::Symbol(";") if depth == 0 => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    fn namespace(&self) -> &TydiNamespace {
+        self.namespaces.last().unwrap()
+    }
+
+    /// The declarations of the namespace at [path] among those parsed so far, or the current namespace if [path] is
+    /// empty.
+    fn resolve(&self, path: &[String]) -> Result<&TydiNamespace, TydiError> {
+        if path.is_empty() {
+            return Ok(self.namespace());
+        }
+        self.namespaces.iter().rev().find(|namespace| namespace.name == path)
+            .ok_or_else(|| self.error(format!("unknown namespace `{}`", path.join("::"))))
+    }
+
+    fn type_expression(&mut self) -> Result<TydiLogicalType, TydiError> {
+        let path = self.path()?;
+        match path.join("::").as_str() {
+            "Null" => Ok(TydiLogicalType::Null),
+            "Bits" => {
+                let widths = self.list(Self::number)?;
+                match widths[..] {
+                    [width] if width > 0 => Ok(TydiLogicalType::Bits(width)),
+                    _ => Err(self.error("`Bits` takes a single, positive width".to_string())),
+                }
+            }
+            "Group" => Ok(TydiLogicalType::Group(self.fields()?)),
+            "Union" => Ok(TydiLogicalType::Union(self.fields()?)),
+            "Stream" => self.stream(),
+            _ => {
+                let (name, namespace) = path.split_last().unwrap();
+                self.resolve(namespace)?.logical_type(name).cloned()
+                    .ok_or_else(|| self.error(format!("unknown type `{}`", path.join("::"))))
+            }
+        }
+    }
+
+    fn fields(&mut self) -> Result<Vec<(String, TydiLogicalType)>, TydiError> {
+        let fields = self.list(|parser| {
+            let name = unescape(parser.identifier()?);
+            parser.expect(":")?;
+            Ok((name, parser.type_expression()?))
+        })?;
+        match fields.iter().enumerate().find(|(i, (name, _))| fields[..*i].iter().any(|(n, _)| n == name)) {
+            Some((_, (name, _))) => Err(self.error(format!("field `{}` is declared twice", name))),
+            None => Ok(fields),
+        }
+    }
+
+    /// The properties of a `Stream`, of which only `data` is required.
+    fn stream(&mut self) -> Result<TydiLogicalType, TydiError> {
+        let mut stream = TydiLogicalStream::new(TydiLogicalType::Null, 0);
+        let mut seen = Vec::new();
+        self.list(|parser| {
+            let property = parser.identifier()?;
+            parser.expect(":")?;
+            match property.as_str() {
+                "data" => stream.element = parser.type_expression()?,
+                "user" => stream.user = parser.type_expression()?,
+                "throughput" => {
+                    stream.throughput = parser.number()?;
+                    if stream.throughput <= 0.0 {
+                        return Err(parser.error("the throughput has to be positive".to_string()));
+                    }
+                }
+                "dimensionality" => stream.dimensionality = parser.number()?,
+                "complexity" => {
+                    // Only the major level of complexities such as `4.1` matters
+                    let level: String = parser.number()?;
+                    stream.complexity = level.split('.').next().and_then(|major| major.parse().ok())
+                        .and_then(TydiComplexity::from_level)
+                        .ok_or_else(|| parser.error(format!("invalid complexity {}", level)))?;
+                }
+                "synchronicity" | "direction" | "keep" => {
+                    let value = parser.identifier()?;
+                    match (property.as_str(), value.as_str()) {
+                        ("synchronicity", "Sync") => stream.synchronicity = TydiSynchronicity::Sync,
+                        ("synchronicity", "Flatten") => stream.synchronicity = TydiSynchronicity::Flatten,
+                        ("synchronicity", "Desync") => stream.synchronicity = TydiSynchronicity::Desync,
+                        ("synchronicity", "FlatDesync") => stream.synchronicity = TydiSynchronicity::FlatDesync,
+                        ("direction", "Forward") => stream.direction = TydiDirection::Forward,
+                        ("direction", "Reverse") => stream.direction = TydiDirection::Reverse,
+                        ("keep", "true") => stream.keep = true,
+                        ("keep", "false") => stream.keep = false,
+                        _ => return Err(parser.error(format!("invalid {} `{}`", property, value))),
+                    }
+                }
+                _ => return Err(parser.error(format!("unknown stream property `{}`", property))),
+            }
+            if seen.contains(&property) {
+                return Err(parser.error(format!("stream property `{}` is set twice", property)));
+            }
+            seen.push(property);
+            Ok(())
+        })?;
+        if !seen.iter().any(|property| property == "data") {
+            return Err(self.error("a stream needs `data`".to_string()));
+        }
+        Ok(TydiLogicalType::Stream(Box::new(stream)))
+    }
+
+    /// Ports in parentheses, or the name of an interface or streamlet to take the ports of.
+    fn ports(&mut self) -> Result<Vec<TydiPort>, TydiError> {
+        if !matches!(self.peek(), Some(Token::Symbol("("))) {
+            let path = self.path()?;
+            let (name, namespace) = path.split_last().unwrap();
+            let namespace = self.resolve(namespace)?;
+            let ports = namespace.interfaces.iter().chain(&namespace.streamlets).find(|(n, _)| n == name);
+            return ports.map(|(_, ports)| ports.clone())
+                .ok_or_else(|| self.error(format!("unknown interface `{}`", path.join("::"))));
+        }
+        self.list(|parser| {
+            let name = parser.identifier()?;
+            parser.expect(":")?;
+            let mode = match parser.identifier()?.as_str() {
+                "in" => TydiPortMode::Sink,
+                "out" => TydiPortMode::Source,
+                _ => return parser.unexpected("`in` or `out`"),
+            };
+            Ok(TydiPort { name, mode, logical_type: parser.type_expression()? })
+        })
+    }
+
+    fn declaration(&mut self) -> Result<(), TydiError> {
+        let keyword = self.identifier()?;
+        match keyword.as_str() {
+            "impl" | "import" => return self.skip_statement(),
+            "type" | "interface" | "streamlet" => {}
+            _ => return self.unexpected("a declaration"),
+        }
+        let name = self.identifier()?;
+        let namespace = self.namespace();
+        let declared = [namespace.types.iter().map(|(n, _)| n).collect::<Vec<_>>(),
+            namespace.interfaces.iter().chain(&namespace.streamlets).map(|(n, _)| n).collect()].concat();
+        if declared.contains(&&name) {
+            return Err(self.error(format!("`{}` is declared twice", name)));
+        }
+        self.expect("=")?;
+        match keyword.as_str() {
+            "type" => {
+                let t = self.type_expression()?;
+                self.namespaces.last_mut().unwrap().types.push((name, t));
+            }
+            "interface" => {
+                let ports = self.ports()?;
+                self.namespaces.last_mut().unwrap().interfaces.push((name, ports));
+            }
+            "streamlet" => {
+                let ports = self.ports()?;
+                self.namespaces.last_mut().unwrap().streamlets.push((name, ports));
+                // The properties of the streamlet, such as its implementation
+                if matches!(self.peek(), Some(Token::Symbol("{"))) {
+                    return self.skip_statement();
+                }
+            }
+            _ => unreachable!(),
+        }
+        self.expect(";")
+    }
+}
+
+/// Parses the namespaces of a TIL document, such as written by [til_namespace].
+///
+/// Types, interfaces and streamlets are taken from the declarations; implementations and imports are skipped. Names
+/// have to be declared before they are used, either in the same namespace or, qualified with its path, in an earlier
+/// namespace. Stream properties other than `data` may be left out, taking the defaults of [TydiLogicalStream::new]
+/// and a dimensionality of 0.
+pub fn parse_til(input: &str) -> Result<Vec<TydiNamespace>, TydiError> {
+    let mut parser = Parser { tokens: tokenize(input)?, position: 0, namespaces: Vec::new() };
+    while parser.peek().is_some() {
+        if parser.identifier()? != "namespace" {
+            return parser.unexpected("`namespace`");
+        }
+        let name = parser.path()?;
+        parser.namespaces.push(TydiNamespace { name, ..Default::default() });
+        parser.expect("{")?;
+        while !parser.eat("}") {
+            parser.declaration()?;
+        }
+    }
+    Ok(parser.namespaces)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logical::TydiLogicalType::{Bits, Null};
+    use crate::Tydi;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Default, Tydi)]
+    struct Article {
+        id: u32,
+        #[tydi(throughput = 4.0, complexity = 7)]
+        title: String,
+        #[tydi(throughput = 2)]
+        tags: Vec<String>,
+    }
+
+    #[test]
+    fn test_bits_and_unions() {
+        assert_eq!(til_namespace("sensor", "byte", &Bits(8)), "namespace sensor {\n    type byte = Bits(8);\n}\n");
+
+        let unit = TydiLogicalType::union(vec![("none", Null), ("celsius", Bits(4))]);
+        assert_eq!(til_namespace("sensor", "unit", &unit),
+                   "namespace sensor {\n    type unit = Union(\n        none: Null,\n        celsius: Bits(4),\n    );\n}\n");
+    }
+
+    #[test]
+    fn test_streams() {
+        let mut chars = TydiLogicalStream::new(Bits(8), 1);
+        chars.synchronicity = TydiSynchronicity::Flatten;
+        let t = TydiLogicalType::group(vec![("id", Bits(32)), ("name", TydiLogicalType::Stream(Box::new(chars)))]);
+        let til = til_namespace("my::example", "person", &t);
+        assert_eq!(til, "namespace my::example {\n    type person_name = Stream(\n        data: Bits(8),\n        \
+            throughput: 1.0,\n        dimensionality: 1,\n        synchronicity: Flatten,\n        complexity: 1,\n        \
+            direction: Forward,\n        user: Null,\n        keep: false,\n    );\n    type person = Group(\n        \
+            id: Bits(32),\n        name: person_name,\n    );\n}\n");
+    }
+
+    #[test]
+    fn test_derived_attributes() {
+        let til = til_for::<Vec<Article>>("blog", "articles");
+        assert!(til.contains("    type articles_element_title = Stream(\n        data: Bits(8),\n        throughput: 4.0,\n"));
+        assert!(til.contains("        complexity: 7,\n"));
+        assert!(til.contains("    type articles_element_tags = Stream(\n        data: articles_element_tags_element,\n        \
+            throughput: 2.0,\n"));
+        assert!(til.contains("    type articles_element = Group(\n        id: Bits(32),\n        title: articles_element_title,\n        \
+            tags: articles_element_tags,\n    );\n"));
+        assert!(til.ends_with("    type articles = Stream(\n        data: articles_element,\n        throughput: 1.0,\n        \
+            dimensionality: 1,\n        synchronicity: Sync,\n        complexity: 1,\n        direction: Forward,\n        \
+            user: Null,\n        keep: false,\n    );\n}\n"));
+
+        // The lanes of the physical streams follow the throughput, the tags themselves carry no data
+        let streams = Vec::<Article>::logical_type().split("articles");
+        assert_eq!(streams.iter().map(|s| s.lanes).collect::<Vec<_>>(), vec![1, 4, 2]);
+    }
+
+    #[test]
+    fn test_parse_roundtrip() {
+        let t = Vec::<Article>::logical_type();
+        let namespaces = parse_til(&til_for::<Vec<Article>>("blog::posts", "articles")).unwrap();
+        assert_eq!(namespaces.len(), 1);
+        assert_eq!(namespaces[0].name, vec!["blog", "posts"]);
+        assert_eq!(namespaces[0].logical_type("articles"), Some(&t));
+        assert_eq!(namespaces[0].types.len(), 5);
+
+        // Fields whose paths give the same name, and fields named as keywords
+        let pair = TydiLogicalType::group(vec![("x", Bits(1)), ("y", Bits(2))]);
+        let t = TydiLogicalType::group(vec![
+            ("a_b", pair.clone()),
+            ("a", TydiLogicalType::group(vec![("b", pair)])),
+            ("type", Bits(3)),
+        ]);
+        let til = til_namespace("clashes", "t", &t);
+        assert!(til.contains("    type t_a_b = Group(\n"));
+        assert!(til.contains("    type t_a_b_2 = Group(\n"));
+        assert!(til.contains("        b: t_a_b_2,\n"));
+        assert!(til.contains("        type_: Bits(3),\n"));
+        assert_eq!(parse_til(&til).unwrap()[0].logical_type("t"), Some(&t));
+
+        // Names that are not identifiers, as imported from a JSON Schema, come back sanitized
+        let comment = |reply: &str, name: &str| TydiLogicalType::group(vec![
+            (reply, TydiLogicalType::union(vec![("none", Null), ("some", Bits(32))])),
+            (name, TydiLogicalType::stream(Bits(8), 1)),
+        ]);
+        let til = til_namespace("blog", "comment-v2", &comment("in-reply-to", "$name"));
+        assert!(til.contains("    type comment_v2_in_reply_to = Union(\n"));
+        assert!(til.contains("    type comment_v2 = Group(\n"));
+        assert_eq!(parse_til(&til).unwrap()[0].logical_type("comment_v2"), Some(&comment("in_reply_to", "name")));
+    }
+
+    #[test]
+    fn test_parse_streamlets() {
+        let namespaces = parse_til("// Shared types
+            namespace common { type byte = Bits(8); }
+            namespace sensor {
+                #A stream of bytes, with a
+                documentation comment#
+                type bytes = Stream(data: common::byte, dimensionality: 1, complexity: 4.1, keep: true,);
+                interface pass = (input: in bytes, output: out bytes);
+                streamlet filter = pass;
+                streamlet merge = (a: in bytes, b: in Stream(data: Null), merged: out bytes) { impl: \"./vhdl\" };
+                impl merge_impl = merge { a -- merged; };
+            }").unwrap();
+        let sensor = &namespaces[1];
+        let mut bytes = TydiLogicalStream::new(Bits(8), 1);
+        bytes.complexity = TydiComplexity::C4;
+        bytes.keep = true;
+        let bytes = TydiLogicalType::Stream(Box::new(bytes));
+        assert_eq!(sensor.logical_type("bytes"), Some(&bytes));
+        assert_eq!(sensor.streamlet("filter").unwrap(), sensor.interfaces[0].1.as_slice());
+        let merge = sensor.streamlet("merge").unwrap();
+        assert_eq!(merge.iter().map(|port| (port.name.as_str(), port.mode)).collect::<Vec<_>>(),
+                   vec![("a", TydiPortMode::Sink), ("b", TydiPortMode::Sink), ("merged", TydiPortMode::Source)]);
+        assert_eq!(merge[1].logical_type, TydiLogicalType::stream(Null, 0));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |input: &str| parse_til(input).unwrap_err().to_string();
+        assert_eq!(error("namespace a {\n  type b = c;\n}"), "invalid TIL at line 2: unknown type `c`");
+        assert_eq!(error("namespace a {\n  type b = Bits(0);\n}"), "invalid TIL at line 2: `Bits` takes a single, positive width");
+        assert_eq!(error("namespace a { type b = Stream(dimensionality: 1); }"), "invalid TIL at line 1: a stream needs `data`");
+        assert_eq!(error("namespace a { type b = Null; type b = Null; }"), "invalid TIL at line 1: `b` is declared twice");
+        assert_eq!(error("namespace a {\n  type b = Group(x: Null, x: Null);"), "invalid TIL at line 2: field `x` is declared twice");
+        assert_eq!(error("namespace a { type b = Null }"), "invalid TIL at line 1: expected `;`");
+        assert_eq!(error("namespace a { port b = Null; }"), "invalid TIL at line 1: expected a declaration, found Identifier(\"port\")");
+        assert_eq!(error("namespace a { type b = Bits(8) $ }"), "invalid TIL at line 1: expected `;`");
+        assert_eq!(error("namespace a {\n  #documentation\n}"), "invalid TIL at line 2: unterminated #");
+    }
+}
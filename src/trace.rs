@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+use crate::binary::TydiBinary;
+use crate::cursor::TydiBitWriter;
+use crate::error::TydiError;
+use crate::logical::TydiPhysicalStream;
+use crate::order::TydiBitOrder;
+use crate::vcd::field_widths;
+use crate::TydiBinaryStream;
+
+/// Values of the signals of a simulation, sampled once per clock cycle.
+///
+/// Signals are named by their scopes and name joined with dots, such as `tydi.posts.valid`, and are looked up by any
+/// suffix of whole components, such as `posts.valid`. Values are bit strings with the most significant bit first, in
+/// which `x` and `z` stand for undefined bits. Values shorter than their signal are extended with zeros.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TydiTrace {
+    /// Names of the signals, each with the index of its value in a sample. Aliases share the same value.
+    names: Vec<(String, usize)>,
+    samples: Vec<Vec<String>>,
+}
+
+/// Whether the signal [name] is referred to by [suffix].
+fn matches(name: &str, suffix: &str) -> bool {
+    name == suffix || name.strip_suffix(suffix).is_some_and(|scope| scope.ends_with('.'))
+}
+
+/// Tokens of a VCD file, along with the line they are on.
+struct Tokens<'a> {
+    tokens: Box<dyn Iterator<Item = (usize, &'a str)> + 'a>,
+    line: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(input: &'a str) -> Self {
+        let tokens = input.lines().enumerate().flat_map(|(i, line)| line.split_whitespace().map(move |t| (i + 1, t)));
+        Self { tokens: Box::new(tokens), line: 0 }
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        let (line, token) = self.tokens.next()?;
+        self.line = line;
+        Some(token)
+    }
+
+    fn error(&self, reason: impl Into<String>) -> TydiError {
+        TydiError::InvalidTrace { line: self.line, reason: reason.into() }
+    }
+
+    /// The next token, which has to be there.
+    fn expect(&mut self, what: &str) -> Result<&'a str, TydiError> {
+        self.next().ok_or_else(|| self.error(format!("expected {}, found the end of the file", what)))
+    }
+
+    /// Skips all tokens up to and including the next `$end`.
+    fn skip_to_end(&mut self) -> Result<(), TydiError> {
+        while self.expect("$end")? != "$end" {}
+        Ok(())
+    }
+}
+
+/// Converts a value of a CSV trace, in decimal, `0x` hexadecimal or `0b` binary, into a bit string.
+fn csv_bits(value: &str) -> Option<String> {
+    let value = value.to_lowercase();
+    if value == "x" || value == "z" {
+        Some(value)
+    } else if let Some(binary) = value.strip_prefix("0b") {
+        binary.chars().all(|c| "01xz".contains(c)).then(|| binary.to_string())
+    } else if let Some(hex) = value.strip_prefix("0x") {
+        hex.chars().map(|c| match c {
+            'x' | 'z' => Some(c.to_string().repeat(4)),
+            _ => c.to_digit(16).map(|digit| format!("{:04b}", digit)),
+        }).collect()
+    } else {
+        value.parse::<u128>().ok().map(|value| format!("{:b}", value))
+    }
+}
+
+impl TydiTrace {
+    /// Parses a Value Change Dump, sampling all signals at every rising edge of the signal named [clock].
+    ///
+    /// Signals are sampled before the changes at the time of the edge, as a register clocked by [clock] would.
+    pub fn from_vcd(input: &str, clock: &str) -> Result<Self, TydiError> {
+        let mut tokens = Tokens::new(input);
+        let mut scopes: Vec<&str> = Vec::new();
+        let mut ids: HashMap<&str, usize> = HashMap::new();
+        let mut names = Vec::new();
+
+        loop {
+            let token = tokens.next().ok_or_else(|| tokens.error("missing $enddefinitions"))?;
+            match token {
+                "$scope" => {
+                    tokens.expect("a scope type")?;
+                    scopes.push(tokens.expect("a scope name")?);
+                    tokens.skip_to_end()?;
+                }
+                "$upscope" => {
+                    scopes.pop();
+                    tokens.skip_to_end()?;
+                }
+                "$var" => {
+                    tokens.expect("a variable type")?;
+                    tokens.expect("a width")?;
+                    let id = tokens.expect("an identifier")?;
+                    let name = tokens.expect("a name")?;
+                    // The bit range that may follow the name is left out
+                    tokens.skip_to_end()?;
+                    let next = ids.len();
+                    let index = *ids.entry(id).or_insert(next);
+                    names.push(([scopes.as_slice(), &[name]].concat().join("."), index));
+                }
+                "$enddefinitions" => {
+                    tokens.skip_to_end()?;
+                    break;
+                }
+                _ if token.starts_with('$') => tokens.skip_to_end()?,
+                _ => return Err(tokens.error(format!("unexpected {} in the header", token))),
+            }
+        }
+
+        let clock_index = names.iter().find(|(name, _)| matches(name, clock)).map(|(_, index)| *index)
+            .ok_or_else(|| TydiError::MissingSignal { name: clock.to_string() })?;
+        let mut state = vec!["x".to_string(); ids.len()];
+        let mut changes: Vec<(usize, String)> = Vec::new();
+        let mut samples = Vec::new();
+        let mut flush = |state: &mut Vec<String>, changes: &mut Vec<(usize, String)>| {
+            let rising = state[clock_index] == "0" && changes.iter().any(|(index, value)| *index == clock_index && value == "1");
+            if rising {
+                samples.push(state.clone());
+            }
+            for (index, value) in changes.drain(..) {
+                state[index] = value;
+            }
+        };
+
+        while let Some(token) = tokens.next() {
+            let mut change = |value: &str, id: &str, tokens: &Tokens| {
+                let index = *ids.get(id).ok_or_else(|| tokens.error(format!("unknown identifier {}", id)))?;
+                changes.push((index, value.to_lowercase()));
+                Ok::<(), TydiError>(())
+            };
+            match token.chars().next() {
+                Some('#') => {
+                    token[1..].parse::<u64>().map_err(|_| tokens.error(format!("invalid time {}", token)))?;
+                    flush(&mut state, &mut changes);
+                }
+                Some('$') if token == "$comment" => tokens.skip_to_end()?,
+                Some('$') => {}
+                Some('b' | 'B') => {
+                    let id = tokens.expect("an identifier")?;
+                    change(&token[1..], id, &tokens)?;
+                }
+                Some('r' | 'R') => {
+                    tokens.expect("an identifier")?;
+                }
+                Some('0' | '1' | 'x' | 'X' | 'z' | 'Z') => change(&token[..1], &token[1..], &tokens)?,
+                _ => return Err(tokens.error(format!("unexpected {}", token))),
+            }
+        }
+        flush(&mut state, &mut changes);
+
+        Ok(Self { names, samples })
+    }
+
+    /// Parses a trace of comma-separated values, with a header row of signal names followed by one row of values per
+    /// clock cycle. Values are decimal, `0x` hexadecimal or `0b` binary, or `x` if undefined.
+    pub fn from_csv(input: &str) -> Result<Self, TydiError> {
+        let mut lines = input.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+        let Some((_, header)) = lines.next() else {
+            return Ok(Self { names: Vec::new(), samples: Vec::new() });
+        };
+        let names: Vec<(String, usize)> = header.split(',').enumerate().map(|(i, name)| (name.trim().to_string(), i)).collect();
+
+        let mut samples = Vec::new();
+        for (i, line) in lines {
+            let error = |reason: String| TydiError::InvalidTrace { line: i + 1, reason };
+            let values: Vec<&str> = line.split(',').map(str::trim).collect();
+            if values.len() != names.len() {
+                return Err(error(format!("expected {} values, found {}", names.len(), values.len())));
+            }
+            let sample = values.iter()
+                .map(|value| csv_bits(value).ok_or_else(|| error(format!("invalid value {}", value))))
+                .collect::<Result<_, _>>()?;
+            samples.push(sample);
+        }
+        Ok(Self { names, samples })
+    }
+
+    /// Number of sampled clock cycles.
+    pub fn cycles(&self) -> usize {
+        self.samples.len()
+    }
+
+    fn index(&self, name: &str) -> Option<usize> {
+        self.names.iter().find(|(signal, _)| matches(signal, name)).map(|(_, index)| *index)
+    }
+
+    /// Value of the signal [name] in [cycle], most significant bit first.
+    pub fn value(&self, cycle: usize, name: &str) -> Option<&str> {
+        Some(self.samples.get(cycle)?[self.index(name)?].as_str())
+    }
+
+    /// The transfers of [stream], taken from the cycles in which its `valid` and `ready` signals are both high.
+    ///
+    /// The signals are looked up by the name of the stream followed by the name of the signal, such as
+    /// `posts.valid`, as written by [crate::vcd::write_vcd]. A stream without `ready` is taken to be always ready.
+    /// Undefined bits are read as zeros. The signals are placed in the binaries as [order] prescribes, which is the
+    /// order of the binaries the trace was written from. The binaries decode through
+    /// [crate::drilling::packets_from_binaries] or [crate::transfer::packets_from_lane_binaries], depending on the
+    /// number of lanes.
+    pub fn transfers(&self, stream: &TydiPhysicalStream, order: TydiBitOrder) -> Result<TydiBinaryStream, TydiError> {
+        let name = stream.name();
+        let signal = |signal: &str| {
+            let full = format!("{}.{}", name, signal);
+            self.index(&full).ok_or(TydiError::MissingSignal { name: full })
+        };
+        let valid = signal("valid")?;
+        let ready = signal("ready").ok();
+        let fields = field_widths(stream).into_iter()
+            .filter(|(_, width)| *width > 0)
+            .map(|(field, width)| Ok((signal(field)?, width)))
+            .collect::<Result<Vec<_>, TydiError>>()?;
+
+        let high = |value: &str| value.ends_with('1');
+        Ok(TydiBinaryStream(self.samples.iter()
+            .filter(|sample| high(&sample[valid]) && ready.is_none_or(|ready| high(&sample[ready])))
+            .map(|sample| {
+                let mut writer = TydiBitWriter::in_order(order);
+                for (index, width) in &fields {
+                    writer.write_binary(&bits_to_binary(&sample[*index], *width).in_order(order));
+                }
+                writer.finish()
+            })
+            .collect()))
+    }
+}
+
+/// The lowest [width] bits of the bit string [value], in which undefined bits are zero.
+fn bits_to_binary(value: &str, width: usize) -> TydiBinary {
+    let mut bits: Vec<bool> = value.chars().rev().take(width).map(|c| c == '1').collect();
+    bits.resize(width, false);
+    bits.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bundle::{TydiBinaryBundle, TydiBundle};
+    use crate::complexity::TydiComplexity;
+    use crate::drilling::packets_from_binaries;
+    use crate::logical::TydiLogicalType;
+    use crate::order::{TydiEndianness, TydiFieldOrder};
+    use crate::transfer::tests::strings;
+    use crate::vcd::tests::physical_strings;
+    use crate::vcd::{to_vcd, TydiHandshake, TydiVcdOptions};
+    use crate::{Tydi, TydiStream};
+
+    #[derive(Debug, PartialEq, Eq, Clone, Tydi)]
+    pub struct Author {
+        user_id: u32,
+        username: String,
+        aliases: Vec<String>,
+    }
+
+    #[test]
+    fn test_vcd_roundtrip() {
+        let (physical, binaries) = physical_strings();
+        let options = TydiVcdOptions { handshake: TydiHandshake::Pattern(vec![true, false, false]), ..TydiVcdOptions::default() };
+        let vcd = to_vcd(&[(physical.clone(), &binaries)], &options);
+
+        let trace = TydiTrace::from_vcd(&vcd, "clk").unwrap();
+        assert_eq!(trace.cycles(), 3 * binaries.0.len() - 2 + 1);
+        assert_eq!(trace.value(0, "strings.data"), Some("01001000"));
+        let transfers = trace.transfers(&physical, TydiBitOrder::PORTABLE).unwrap();
+        assert_eq!(transfers.0, binaries.0);
+        let recreated: TydiStream<u8> = packets_from_binaries(transfers, 2);
+        assert_eq!(recreated.solidify_into_strings().unpack(), strings());
+    }
+
+    #[test]
+    fn test_bundle_from_vcd() {
+        let authors = vec![
+            Author { user_id: 1, username: "Ferris".into(), aliases: vec!["Crab".into(), "".into()] },
+            Author { user_id: 2, username: "".into(), aliases: vec![] },
+        ];
+        let binary = TydiBundle::new(authors.clone()).finish();
+        let physical = binary.physical_streams("authors");
+        let vcd = to_vcd(&physical, &TydiVcdOptions::default());
+
+        let trace = TydiTrace::from_vcd(&vcd, "tydi.clk").unwrap();
+        let transfers = physical.iter()
+            .map(|(stream, _)| trace.transfers(stream, TydiBitOrder::PORTABLE)).collect::<Result<_, _>>().unwrap();
+        let recreated = TydiBinaryBundle::<Author>::from_physical_streams(transfers);
+        assert_eq!(recreated.reverse().reverse(), authors);
+    }
+
+    #[test]
+    fn test_msb_first_from_vcd() {
+        let authors = vec![
+            Author { user_id: 0x1234, username: "Ferris".into(), aliases: vec!["Crab".into()] },
+            Author { user_id: 2, username: "".into(), aliases: vec![] },
+        ];
+        let big_msb_first = TydiBitOrder::new(TydiEndianness::Big, TydiFieldOrder::MsbFirst);
+        let binary = TydiBundle::new(authors.clone()).finish_with(TydiComplexity::C8, big_msb_first);
+        let physical = binary.physical_streams("authors");
+        let vcd = to_vcd(&physical, &TydiVcdOptions::default());
+
+        let trace = TydiTrace::from_vcd(&vcd, "tydi.clk").unwrap();
+        // The bytes of the user id are swapped in the data signal
+        assert_eq!(trace.value(0, "authors.data"), Some(format!("{:032b}", 0x3412_0000u32).as_str()));
+        let transfers: Vec<TydiBinaryStream> = physical.iter()
+            .map(|(stream, _)| trace.transfers(stream, big_msb_first)).collect::<Result<_, _>>().unwrap();
+        for ((_, expected), transfers) in physical.iter().zip(&transfers) {
+            assert_eq!(&transfers.0, &expected.0);
+        }
+        let recreated = TydiBinaryBundle::<Author>::from_physical_streams(transfers);
+        assert_eq!(recreated.reverse().reverse(), authors);
+    }
+
+    #[test]
+    fn test_csv_trace() {
+        let csv = "cycle, bytes.valid, bytes.ready, bytes.strb, bytes.last, bytes.data\n\
+                   0, 1, 0, 1, 0, 0x41\n\
+                   1, 1, 1, 1, 0, 0x41\n\
+                   2, 0, 1, x, x, x\n\
+                   3, 1, 1, 1, 0b1, 66\n";
+        let trace = TydiTrace::from_csv(csv).unwrap();
+        let physical = TydiLogicalType::stream(TydiLogicalType::Bits(8), 1).split("bytes");
+        let bytes: TydiStream<u8> = packets_from_binaries(trace.transfers(&physical[0], TydiBitOrder::PORTABLE).unwrap(), 1);
+        assert_eq!(bytes.solidify_into_strings().unpack(), vec!["AB"]);
+
+        assert_eq!(TydiTrace::from_csv("a, b\n1, 2, 3").unwrap_err(),
+                   TydiError::InvalidTrace { line: 2, reason: "expected 2 values, found 3".into() });
+        assert_eq!(TydiTrace::from_csv("a\n0xg").unwrap_err(), TydiError::InvalidTrace { line: 2, reason: "invalid value 0xg".into() });
+        let other = TydiLogicalType::stream(TydiLogicalType::Bits(8), 1).split("other");
+        assert_eq!(trace.transfers(&other[0], TydiBitOrder::PORTABLE).unwrap_err(), TydiError::MissingSignal { name: "other.valid".into() });
+    }
+}
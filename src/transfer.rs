@@ -0,0 +1,385 @@
+use crate::binary::{FromTydiBinary, ToTydiBinary, TydiBinary};
+use crate::complexity::TydiComplexity;
+use crate::cursor::TydiBitWriter;
+use crate::error::TydiError;
+use crate::order::TydiBitOrder;
+use crate::{TydiBinaryStream, TydiPacket, TydiStream};
+
+/// A single transfer on a physical stream with `N` element lanes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TydiTransfer<T> {
+    /// One entry per lane, `None` for lanes with a low strobe.
+    pub lanes: Vec<Option<T>>,
+    /// Start index, the first lane that holds valid data.
+    pub stai: usize,
+    /// End index, the last lane that holds valid data.
+    pub endi: usize,
+    /// The `last` flags, shared by all lanes or per lane depending on the complexity of the stream.
+    pub last: TydiLast,
+}
+
+/// The `last` flags of a transfer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TydiLast {
+    /// A single set of flags shared by all lanes, belonging to the element in lane `endi` (C < 8).
+    Shared(Vec<bool>),
+    /// One set of flags per lane (C8).
+    PerLane(Vec<Vec<bool>>),
+}
+
+impl TydiLast {
+    /// The flags of the element in lane [endi], which closes the sequences of the transfer. Missing flags are empty.
+    pub fn of_final(&self, endi: usize) -> &[bool] {
+        match self {
+            TydiLast::Shared(last) => last,
+            TydiLast::PerLane(lanes) => lanes.get(endi).map_or(&[], Vec::as_slice),
+        }
+    }
+
+    /// Whether any of the flags is asserted.
+    pub fn any(&self) -> bool {
+        match self {
+            TydiLast::Shared(last) => last.iter().any(|&l| l),
+            TydiLast::PerLane(lanes) => lanes.iter().flatten().any(|&l| l),
+        }
+    }
+}
+
+/// Number of bits required for the `stai` and `endi` signals of a stream with [lanes] lanes.
+pub fn index_width(lanes: usize) -> usize {
+    if lanes <= 1 { 0 } else { (usize::BITS - (lanes - 1).leading_zeros()) as usize }
+}
+
+pub(crate) fn index_to_binary(value: usize, width: usize) -> TydiBinary {
+    let bits: Vec<bool> = (0..width).map(|i| (value >> i) & 1 == 1).collect();
+    bits.into()
+}
+
+pub(crate) fn index_from_binary(value: TydiBinary) -> usize {
+    let bits: Vec<bool> = value.into();
+    bits.iter().enumerate().fold(0, |acc, (i, &bit)| acc | ((bit as usize) << i))
+}
+
+impl<T> TydiTransfer<T> {
+    /// Strobe signal, one bit per lane.
+    pub fn strb(&self) -> Vec<bool> {
+        self.lanes.iter().map(|lane| lane.is_some()).collect()
+    }
+
+    /// Packs the transfer as `strb | stai | endi | last | lane 0 | .. | lane N-1`, starting at the least significant bit.
+    ///
+    /// Per-lane `last` flags are stored lane after lane. With a single lane the `stai` and `endi` fields have no
+    /// width, so the result equals [TydiPacket::to_binary].
+    pub fn to_binary(self, size: usize) -> TydiBinary where T: ToTydiBinary {
+        self.to_binary_in(size, TydiBitOrder::PORTABLE)
+    }
+
+    /// Like [TydiTransfer::to_binary], packing the transfer in [order].
+    pub fn to_binary_in(self, size: usize, order: TydiBitOrder) -> TydiBinary where T: ToTydiBinary {
+        let idx_width = index_width(self.lanes.len());
+        let strb = self.strb();
+        let stai = index_to_binary(self.stai, idx_width).in_order(order);
+        let endi = index_to_binary(self.endi, idx_width).in_order(order);
+        let last = match self.last {
+            TydiLast::Shared(last) => last,
+            TydiLast::PerLane(lanes) => lanes.concat(),
+        };
+
+        let mut writer = TydiBitWriter::in_order(order);
+        writer.write(strb).write(stai).write(endi).write(last);
+        for lane in self.lanes {
+            if let Some(data) = lane {
+                let binary = data.to_tydi_binary(order);
+                assert_eq!(binary.len, size, "resulting binary not of expected size");
+                writer.write(binary);
+            } else {
+                writer.write_zeros(size);
+            }
+        }
+        writer.finish()
+    }
+
+    /// Unpacks a transfer created by [TydiTransfer::to_binary] for a stream of complexity [c].
+    pub fn from_binary(val: TydiBinary, lanes: usize, dim: usize, size: usize, c: TydiComplexity) -> Self where T: FromTydiBinary {
+        Self::try_from_binary(val, lanes, dim, size, c).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Like [TydiTransfer::from_binary], returning an error if [val] is not exactly as large as a transfer with
+    /// [lanes] lanes of [size] bits, or the data of a lane is not a valid [T].
+    pub fn try_from_binary(val: TydiBinary, lanes: usize, dim: usize, size: usize, c: TydiComplexity)
+        -> Result<Self, TydiError> where T: FromTydiBinary {
+        let idx_width = index_width(lanes);
+        let last_sets = if c.per_lane_last() { lanes } else { 1 };
+        let expected = lanes + 2 * idx_width + dim * last_sets + lanes * size;
+        if val.len < expected || val.len > val.data.len() * 8 {
+            return Err(TydiError::Truncated { needed: expected, available: val.len.min(val.data.len() * 8) });
+        }
+        if val.len > expected {
+            return Err(TydiError::SizeMismatch { expected, actual: val.len });
+        }
+        let (strb, res) = val.split(lanes);
+        let strb: Vec<bool> = strb.into();
+        let (stai, res) = res.split(idx_width);
+        let (endi, res) = res.split(idx_width);
+        let (last, mut res) = res.split(dim * last_sets);
+        let last: Vec<bool> = last.into();
+
+        let mut data = Vec::with_capacity(lanes);
+        for active in strb {
+            let (lane_bin, remainder) = res.split(size);
+            res = remainder;
+            data.push(if active { Some(T::try_from_tydi_binary(lane_bin)?.0) } else { None });
+        }
+
+        Ok(Self {
+            lanes: data,
+            stai: index_from_binary(stai),
+            endi: index_from_binary(endi),
+            last: match (c.per_lane_last(), dim) {
+                (false, _) => TydiLast::Shared(last),
+                (true, 0) => TydiLast::PerLane(vec![vec![]; lanes]),
+                (true, _) => TydiLast::PerLane(last.chunks(dim).map(|l| l.to_vec()).collect()),
+            },
+        })
+    }
+}
+
+impl<T: Clone> TydiStream<T> {
+    /// Packs the packets of this stream into transfers of [lanes] element lanes, following the rules of complexity [c].
+    ///
+    /// Elements fill the lanes from lane 0 onwards. Below C8 the `last` flags are shared by the whole transfer, so a
+    /// transfer is closed when all lanes are in use or when an element asserts any of its `last` flags, and packets
+    /// without data (empty sequences) get a transfer of their own with all strobes low. At C8 every lane has its own
+    /// `last` flags, so transfers are only closed when all lanes are in use and empty sequences take up a lane with a
+    /// low strobe.
+    pub fn to_transfers(&self, lanes: usize, c: TydiComplexity) -> Vec<TydiTransfer<T>> {
+        assert!(lanes > 0, "a physical stream needs at least one lane");
+        let stream = self.normalize(c);
+        let d = stream.0.first().map(|el| el.last.len()).unwrap_or(0);
+        let mut result = Vec::new();
+        let mut current: Vec<Option<T>> = Vec::with_capacity(lanes);
+        let mut current_last: Vec<Vec<bool>> = Vec::with_capacity(lanes);
+
+        let close = |current: &mut Vec<Option<T>>, current_last: &mut Vec<Vec<bool>>, last: Option<Vec<bool>>| {
+            let endi = current.len().saturating_sub(1);
+            current.resize(lanes, None);
+            let last = match last {
+                Some(shared) => TydiLast::Shared(shared),
+                None => {
+                    current_last.resize(lanes, vec![false; d]);
+                    TydiLast::PerLane(std::mem::replace(current_last, Vec::with_capacity(lanes)))
+                }
+            };
+            TydiTransfer { lanes: std::mem::replace(current, Vec::with_capacity(lanes)), stai: 0, endi, last }
+        };
+
+        for packet in stream.0.into_iter() {
+            if c.per_lane_last() {
+                current.push(packet.data);
+                current_last.push(packet.last);
+                if current.len() == lanes {
+                    result.push(close(&mut current, &mut current_last, None));
+                }
+                continue;
+            }
+            match packet.data {
+                Some(data) => {
+                    current.push(Some(data));
+                    if packet.last.iter().any(|&l| l) || current.len() == lanes {
+                        result.push(close(&mut current, &mut current_last, Some(packet.last)));
+                    }
+                }
+                None => {
+                    if !current.is_empty() {
+                        result.push(close(&mut current, &mut current_last, Some(vec![false; d])));
+                    }
+                    result.push(close(&mut current, &mut current_last, Some(packet.last)));
+                }
+            }
+        }
+        if !current.is_empty() {
+            let last = if c.per_lane_last() { None } else { Some(vec![false; d]) };
+            result.push(close(&mut current, &mut current_last, last));
+        }
+        result
+    }
+
+    /// Recreates the packets from a sequence of transfers, the inverse of [TydiStream::to_transfers].
+    ///
+    /// Lanes without data and without any `last` flag carry no information and are dropped.
+    pub fn from_transfers(transfers: Vec<TydiTransfer<T>>) -> Self {
+        let mut result = Vec::new();
+        for transfer in transfers {
+            let range = transfer.stai..=transfer.endi;
+            let lanes: Vec<(usize, Option<T>)> = transfer.lanes.into_iter().enumerate()
+                .filter(|(i, _)| range.contains(i))
+                .collect();
+
+            let shared = match transfer.last {
+                TydiLast::Shared(shared) => shared,
+                TydiLast::PerLane(per_lane) => {
+                    for (i, data) in lanes {
+                        let last = per_lane.get(i).cloned().unwrap_or_default();
+                        if data.is_some() || last.iter().any(|&l| l) {
+                            result.push(TydiPacket { data, last });
+                        }
+                    }
+                    continue;
+                }
+            };
+            let active: Vec<T> = lanes.into_iter().filter_map(|(_, lane)| lane).collect();
+            if active.is_empty() {
+                if shared.iter().any(|&l| l) {
+                    result.push(TydiPacket { data: None, last: shared });
+                }
+                continue;
+            }
+
+            let n = active.len();
+            for (i, data) in active.into_iter().enumerate() {
+                let last = if i == n - 1 { shared.clone() } else { vec![false; shared.len()] };
+                result.push(TydiPacket { data: Some(data), last });
+            }
+        }
+        TydiStream(result)
+    }
+}
+
+/// Decodes binaries created by `finish_lanes` for a stream of complexity [c] back into a stream of packets.
+pub fn packets_from_lane_binaries<T: FromTydiBinary + Clone>(value: TydiBinaryStream, lanes: usize, dim: usize, size: usize, c: TydiComplexity) -> TydiStream<T> {
+    try_packets_from_lane_binaries(value, lanes, dim, size, c).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Like [packets_from_lane_binaries], returning an error if any of the binaries is not a transfer of [T].
+pub fn try_packets_from_lane_binaries<T: FromTydiBinary + Clone>(value: TydiBinaryStream, lanes: usize, dim: usize, size: usize,
+                                                               c: TydiComplexity) -> Result<TydiStream<T>, TydiError> {
+    let transfers = value.0.into_iter()
+        .map(|el| TydiTransfer::try_from_binary(el, lanes, dim, size, c))
+        .collect::<Result<_, _>>()?;
+    Ok(TydiStream::from_transfers(transfers))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::drilling::{TydiConvert, TydiPacktestToBinary};
+    use crate::order::{TydiBitOrder, TydiEndianness, TydiFieldOrder};
+
+    /// Strings of more than eight bytes, none and a few, as used by the tests of streams of sequences.
+    pub(crate) fn strings() -> Vec<String> {
+        vec!["Hello, world!".to_string(), "".to_string(), "Tydi".to_string()]
+    }
+
+    /// The bytes of [strings], as a stream of dimensionality 2.
+    pub(crate) fn strings_stream() -> TydiStream<u8> {
+        strings().convert().drill(|e| e.into_bytes())
+    }
+
+    #[test]
+    fn test_index_width() {
+        assert_eq!(index_width(1), 0);
+        assert_eq!(index_width(2), 1);
+        assert_eq!(index_width(8), 3);
+        assert_eq!(index_width(9), 4);
+        assert_eq!(index_width(16), 4);
+    }
+
+    #[test]
+    fn test_lane_packing() {
+        let stream = strings_stream();
+        let transfers = stream.to_transfers(8, TydiComplexity::C7);
+        // "Hello, world!" needs two transfers, the empty string one and "Tydi" one.
+        assert_eq!(transfers.len(), 4);
+        assert_eq!(transfers[0].strb(), vec![true; 8]);
+        assert_eq!(transfers[0].last, TydiLast::Shared(vec![false, false]));
+        assert_eq!(transfers[1].endi, 4);
+        assert_eq!(transfers[1].last, TydiLast::Shared(vec![false, true]));
+        assert_eq!(transfers[2].strb(), vec![false; 8]);
+        assert_eq!(transfers[3].lanes[3], Some(b'i'));
+        assert_eq!(transfers[3].lanes[4], None);
+        assert_eq!(transfers[3].last, TydiLast::Shared(vec![true, true]));
+
+        let recreated = TydiStream::from_transfers(transfers);
+        assert_eq!(recreated.0, stream.0);
+    }
+
+    #[test]
+    fn test_per_lane_last() {
+        let stream = strings_stream();
+        let transfers = stream.to_transfers(8, TydiComplexity::C8);
+        // 13 + 1 (empty sequence) + 4 elements fill the lanes of three transfers
+        assert_eq!(transfers.len(), 3);
+        assert_eq!(transfers[1].lanes[5], None);
+        let TydiLast::PerLane(last) = &transfers[1].last else { panic!("expected per-lane last flags") };
+        assert_eq!(last[5], vec![false, true]);
+        assert_eq!(transfers[1].lanes[6], Some(b'T'));
+        assert_eq!(transfers[2].endi, 1);
+
+        let recreated = TydiStream::from_transfers(transfers);
+        assert_eq!(recreated.0, stream.0);
+
+        let binaries = stream.finish_lanes(8, 8, TydiComplexity::C8, TydiBitOrder::PORTABLE);
+        assert_eq!(binaries.0[0].len, 8 + 3 + 3 + 8 * 2 + 8 * 8);
+        let recreated: TydiStream<u8> = packets_from_lane_binaries(binaries, 8, 2, 8, TydiComplexity::C8);
+        assert_eq!(recreated.0, stream.0);
+    }
+
+    #[test]
+    fn test_single_lane_per_lane_last() {
+        let stream = strings_stream();
+        let transfers = stream.to_transfers(1, TydiComplexity::C8);
+        assert!(transfers.iter().all(|t| matches!(t.last, TydiLast::PerLane(_))));
+        assert_eq!(TydiStream::from_transfers(transfers).0, stream.0);
+
+        let binaries = stream.finish_lanes(8, 1, TydiComplexity::C8, TydiBitOrder::PORTABLE);
+        let recreated: TydiStream<u8> = packets_from_lane_binaries(binaries, 1, 2, 8, TydiComplexity::C8);
+        assert_eq!(recreated.0, stream.0);
+    }
+
+    #[test]
+    fn test_single_lane_matches_packets() {
+        let stream = strings_stream();
+        let lane_binaries = stream.finish_lanes(8, 1, TydiComplexity::C4, TydiBitOrder::PORTABLE);
+        let packet_binaries = stream.finish(8);
+        assert_eq!(lane_binaries.0, packet_binaries.0);
+    }
+
+    #[test]
+    fn test_lane_binary_roundtrip() {
+        let stream = strings_stream();
+        let binaries = stream.finish_lanes(8, 16, TydiComplexity::C4, TydiBitOrder::PORTABLE);
+        // strb + stai + endi + last + data
+        assert_eq!(binaries.0[0].len, 16 + 4 + 4 + 2 + 16 * 8);
+        let recreated: TydiStream<u8> = packets_from_lane_binaries(binaries, 16, 2, 8, TydiComplexity::C4);
+        assert_eq!(recreated.0, stream.0);
+        assert_eq!(recreated.solidify_into_strings().unpack(), strings());
+
+        let msb_first = TydiBitOrder::new(TydiEndianness::Little, TydiFieldOrder::MsbFirst);
+        let binaries = stream.finish_lanes(8, 16, TydiComplexity::C4, msb_first);
+        assert_eq!(binaries.0[0].order, msb_first);
+        let recreated: TydiStream<u8> = packets_from_lane_binaries(binaries, 16, 2, 8, TydiComplexity::C4);
+        assert_eq!(recreated.0, stream.0);
+    }
+
+    #[test]
+    fn test_corrupt_lane_binaries() {
+        let stream = strings_stream();
+        let binaries = stream.finish_lanes(8, 4, TydiComplexity::C4, TydiBitOrder::PORTABLE);
+        let transfer = binaries.0[0].clone();
+        assert_eq!(TydiTransfer::<u8>::try_from_binary(transfer.clone(), 4, 2, 8, TydiComplexity::C4).unwrap(),
+                   TydiTransfer::from_binary(transfer.clone(), 4, 2, 8, TydiComplexity::C4));
+
+        let (truncated, _) = transfer.split(transfer.len - 1);
+        assert_eq!(TydiTransfer::<u8>::try_from_binary(truncated, 4, 2, 8, TydiComplexity::C4).unwrap_err(),
+                   TydiError::Truncated { needed: transfer.len, available: transfer.len - 1 });
+        // Per-lane last flags take more bits than the transfer holds
+        assert!(try_packets_from_lane_binaries::<u8>(binaries, 4, 2, 8, TydiComplexity::C8).is_err());
+
+        // A strobe, stai and endi for two lanes of 32 bits, the first of which is not a valid code point
+        let mut lanes = TydiBinary::from(vec![true, false, false, false]);
+        lanes.append(&TydiBinary::from(0xD800u32));
+        lanes.append(&TydiBinary::zeros(32));
+        assert_eq!(TydiTransfer::<char>::try_from_binary(lanes, 2, 0, 32, TydiComplexity::C4).unwrap_err(),
+                   TydiError::InvalidChar(0xD800));
+    }
+}
@@ -0,0 +1,265 @@
+use std::io;
+use std::io::Write;
+use crate::binary::TydiBinary;
+use crate::cursor::TydiBitReader;
+use crate::logical::TydiPhysicalStream;
+use crate::transfer::index_width;
+use crate::TydiBinaryStream;
+
+/// When the sink of a stream accepts transfers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TydiHandshake {
+    /// `ready` is high in every cycle, so a transfer is accepted in every cycle in which `valid` is high.
+    AlwaysReady,
+    /// `ready` follows the pattern, one entry per clock cycle, repeating it when it runs out.
+    Pattern(Vec<bool>),
+}
+
+impl TydiHandshake {
+    /// Whether the sink is ready in [cycle].
+    pub fn ready(&self, cycle: usize) -> bool {
+        match self {
+            Self::AlwaysReady => true,
+            Self::Pattern(pattern) => pattern[cycle % pattern.len()],
+        }
+    }
+}
+
+/// Options of a Value Change Dump.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TydiVcdOptions {
+    /// Clock period in units of the [TydiVcdOptions::timescale], at least 2.
+    pub clock_period: u64,
+    /// Unit of time, such as `1ns`.
+    pub timescale: String,
+    /// Handshake of every stream in the dump.
+    pub handshake: TydiHandshake,
+    /// Name of the scope holding the clock and the scopes of the streams.
+    pub top: String,
+}
+
+impl Default for TydiVcdOptions {
+    fn default() -> Self {
+        Self { clock_period: 10, timescale: "1ns".to_string(), handshake: TydiHandshake::AlwaysReady, top: "tydi".to_string() }
+    }
+}
+
+/// A signal in the dump, along with the value it was last dumped with.
+struct Signal {
+    id: String,
+    name: &'static str,
+    width: usize,
+    value: Option<String>,
+}
+
+/// The signals of a physical stream, in the order they are packed into a transfer binary.
+struct StreamSignals<'a> {
+    stream: &'a TydiPhysicalStream,
+    binaries: &'a TydiBinaryStream,
+    /// Index of the next transfer to present.
+    next: usize,
+    valid: Signal,
+    ready: Signal,
+    /// `strb`, `stai`, `endi`, `last` and `data`, of which those without width are left out.
+    fields: Vec<Signal>,
+}
+
+/// Short identifier of the signal with index [index], made up of printable ASCII characters.
+fn signal_id(mut index: usize) -> String {
+    let mut id = String::new();
+    loop {
+        id.push((b'!' + (index % 94) as u8) as char);
+        index /= 94;
+        if index == 0 {
+            return id;
+        }
+        index -= 1;
+    }
+}
+
+/// Widths of the signals of [stream], in the order of [crate::transfer::TydiTransfer::to_binary].
+pub(crate) fn field_widths(stream: &TydiPhysicalStream) -> [(&'static str, usize); 5] {
+    let last_sets = if stream.complexity.per_lane_last() { stream.lanes } else { 1 };
+    let idx_width = index_width(stream.lanes);
+    [
+        ("strb", stream.lanes),
+        ("stai", idx_width),
+        ("endi", idx_width),
+        ("last", stream.dimensionality * last_sets),
+        ("data", stream.data_width()),
+    ]
+}
+
+/// The value change of [signal], or `None` if it keeps its value.
+fn change(signal: &mut Signal, value: String) -> Option<String> {
+    if signal.value.as_ref() == Some(&value) {
+        return None;
+    }
+    let line = if signal.width == 1 { format!("{}{}", value, signal.id) } else { format!("b{} {}", value, signal.id) };
+    signal.value = Some(value);
+    Some(line)
+}
+
+/// Bits of [binary], most significant first.
+fn vcd_bits(binary: &TydiBinary) -> String {
+    let bits = binary.as_bits();
+    (0..bits.len()).rev().map(|i| if bits.get(i) { '1' } else { '0' }).collect()
+}
+
+/// Writes the transfers of the [streams] as a Value Change Dump, to be viewed next to the waveform of a simulation.
+///
+/// Each stream gets a scope named after it, holding `valid`, `ready`, `strb`, `stai`, `endi`, `last` and `data`,
+/// leaving out signals without width. The binaries of a stream are its transfers, as created by
+/// [crate::drilling::TydiPacktestToBinary::finish_lanes], or by [crate::drilling::TydiPacktestToBinary::finish] for
+/// streams with a single lane. User signals are not part of the binaries, and thus not of the dump.
+///
+/// Every stream presents its next transfer in every cycle, and holds it until the sink is ready following
+/// [TydiVcdOptions::handshake]. The signals change at the start of a cycle, and are sampled by the rising clock edge
+/// halfway through it. Outside transfers the payload signals are `x`. The dump ends one cycle after the last transfer.
+pub fn write_vcd<W: Write>(mut writer: W, streams: &[(TydiPhysicalStream, &TydiBinaryStream)], options: &TydiVcdOptions) -> io::Result<()> {
+    assert!(options.clock_period >= 2, "the clock period should be at least two time units");
+    if let TydiHandshake::Pattern(pattern) = &options.handshake {
+        assert!(pattern.iter().any(|&ready| ready), "a handshake pattern should be ready in some cycle");
+    }
+
+    let mut index = 0;
+    let mut next_signal = |name: &'static str, width: usize| {
+        index += 1;
+        Signal { id: signal_id(index), name, width, value: None }
+    };
+    let clock = signal_id(0);
+    let mut signals: Vec<StreamSignals> = streams.iter().map(|(stream, binaries)| StreamSignals {
+        stream,
+        binaries,
+        next: 0,
+        valid: next_signal("valid", 1),
+        ready: next_signal("ready", 1),
+        fields: field_widths(stream).into_iter()
+            .filter(|(_, width)| *width > 0)
+            .map(|(name, width)| next_signal(name, width))
+            .collect(),
+    }).collect();
+
+    writeln!(writer, "$version rust-tydi-packages $end")?;
+    writeln!(writer, "$timescale {} $end", options.timescale)?;
+    writeln!(writer, "$scope module {} $end", options.top)?;
+    writeln!(writer, "$var wire 1 {} clk $end", clock)?;
+    for stream in &signals {
+        writeln!(writer, "$scope module {} $end", stream.stream.name())?;
+        for signal in [&stream.valid, &stream.ready].into_iter().chain(&stream.fields) {
+            writeln!(writer, "$var wire {} {} {} $end", signal.width, signal.id, signal.name)?;
+        }
+        writeln!(writer, "$upscope $end")?;
+    }
+    writeln!(writer, "$upscope $end")?;
+    writeln!(writer, "$enddefinitions $end")?;
+
+    let half = options.clock_period / 2;
+    let mut cycle = 0;
+    loop {
+        let done = signals.iter().all(|s| s.next >= s.binaries.0.len());
+        let mut changes = vec![format!("0{}", clock)];
+        for stream in signals.iter_mut() {
+            let ready = options.handshake.ready(cycle);
+            let transfer = stream.binaries.0.get(stream.next);
+            changes.extend(change(&mut stream.valid, if transfer.is_some() { "1" } else { "0" }.to_string()));
+            changes.extend(change(&mut stream.ready, if ready { "1" } else { "0" }.to_string()));
+
+            let mut reader = transfer.map(TydiBitReader::new);
+            for (name, width) in field_widths(stream.stream) {
+                if width == 0 {
+                    continue;
+                }
+                let value = match reader.as_mut() {
+                    Some(reader) => vcd_bits(&reader.read_binary(width)),
+                    None => "x".to_string(),
+                };
+                let signal = stream.fields.iter_mut().find(|signal| signal.name == name).unwrap();
+                changes.extend(change(signal, value));
+            }
+
+            if transfer.is_some() && ready {
+                stream.next += 1;
+            }
+        }
+
+        let time = cycle as u64 * options.clock_period;
+        writeln!(writer, "#{}", time)?;
+        if cycle == 0 {
+            writeln!(writer, "$dumpvars")?;
+        }
+        for line in changes {
+            writeln!(writer, "{}", line)?;
+        }
+        if cycle == 0 {
+            writeln!(writer, "$end")?;
+        }
+        writeln!(writer, "#{}", time + half)?;
+        writeln!(writer, "1{}", clock)?;
+
+        if done {
+            writeln!(writer, "#{}", time + options.clock_period)?;
+            return Ok(());
+        }
+        cycle += 1;
+    }
+}
+
+/// [write_vcd] into a string.
+pub fn to_vcd(streams: &[(TydiPhysicalStream, &TydiBinaryStream)], options: &TydiVcdOptions) -> String {
+    let mut result = Vec::new();
+    write_vcd(&mut result, streams, options).expect("writing to memory does not fail");
+    String::from_utf8(result).expect("the dump is ASCII")
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::drilling::TydiPacktestToBinary;
+    use crate::logical::TydiLogicalType;
+    use crate::transfer::tests::strings_stream;
+
+    /// The physical stream named `strings` of [strings_stream], along with its binaries of one byte per transfer.
+    pub(crate) fn physical_strings() -> (TydiPhysicalStream, TydiBinaryStream) {
+        let physical = TydiLogicalType::stream(TydiLogicalType::stream(TydiLogicalType::Bits(8), 1), 1).split("strings");
+        (physical[0].clone(), strings_stream().finish(8))
+    }
+
+    #[test]
+    fn test_signal_ids() {
+        assert_eq!(signal_id(0), "!");
+        assert_eq!(signal_id(93), "~");
+        assert_eq!(signal_id(94), "!!");
+        assert_ne!(signal_id(94 + 94), signal_id(94));
+    }
+
+    #[test]
+    fn test_always_ready_dump() {
+        let (stream, binaries) = physical_strings();
+        let vcd = to_vcd(&[(stream, &binaries)], &TydiVcdOptions::default());
+        assert!(vcd.contains("$scope module strings $end"));
+        assert!(vcd.contains("$var wire 2 % last $end"));
+        assert!(vcd.contains("$var wire 8 & data $end"));
+        // 'H' in the first cycle, the empty string without strobe in the fourteenth, and the final 'i' in the eighteenth
+        assert!(vcd.contains("#0\n$dumpvars\n0!\n1\"\n1#\n1$\nb00 %\nb01001000 &\n$end"));
+        assert!(vcd.contains("#120\n0!\nb10 %\nb00100001 &\n#125\n1!\n#130\n0!\n0$\nb00000000 &\n#135"));
+        assert!(vcd.contains("#170\n0!\nb11 %\nb01101001 &\n#175\n1!\n#180\n0!\n0\"\nx$\nbx %\nbx &\n#185\n1!\n#190\n"));
+        assert!(vcd.ends_with("#190\n"));
+    }
+
+    #[test]
+    fn test_backpressure_dump() {
+        let (stream, binaries) = physical_strings();
+        let options = TydiVcdOptions {
+            clock_period: 2,
+            handshake: TydiHandshake::Pattern(vec![false, true]),
+            ..TydiVcdOptions::default()
+        };
+        let vcd = to_vcd(&[(stream.clone(), &binaries), (stream, &binaries)], &options);
+        // Both streams hold every transfer for two cycles
+        assert!(vcd.contains("#2\n0!\n1#\n1(\n#3"));
+        assert!(vcd.contains("#4\n0!\n0#\nb01100101 &\n0(\nb01100101 +\n#5"));
+        assert!(vcd.contains("#68\n0!\n0#\nb11 %\nb01101001 &\n0(\nb11 *\nb01101001 +\n#69"));
+        assert!(vcd.ends_with("#73\n1!\n#74\n"));
+    }
+}
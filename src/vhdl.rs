@@ -0,0 +1,312 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use crate::layout::{declare, extended, join, sanitize, stream_paths, Declarations, Member};
+use crate::logical::{TydiDirection, TydiLogical, TydiPhysicalStream};
+use crate::order::TydiBitOrder;
+use crate::vcd::field_widths;
+
+/// Reserved words of VHDL-2008, which cannot be used as basic identifiers.
+const RESERVED: &[&str] = &[
+    "abs", "access", "after", "alias", "all", "and", "architecture", "array", "assert", "assume", "assume_guarantee",
+    "attribute", "begin", "block", "body", "buffer", "bus", "case", "component", "configuration", "constant",
+    "context", "cover", "default", "disconnect", "downto", "else", "elsif", "end", "entity", "exit", "fairness",
+    "file", "for", "force", "function", "generate", "generic", "group", "guarded", "if", "impure", "in", "inertial",
+    "inout", "is", "label", "library", "linkage", "literal", "loop", "map", "mod", "nand", "new", "next", "nor",
+    "not", "null", "of", "on", "open", "or", "others", "out", "package", "parameter", "port", "postponed",
+    "procedure", "process", "property", "protected", "pure", "range", "record", "register", "reject", "release",
+    "rem", "report", "restrict", "restrict_guarantee", "return", "rol", "ror", "select", "sequence", "severity",
+    "shared", "signal", "sla", "sll", "sra", "srl", "strong", "subtype", "then", "to", "transport", "type",
+    "unaffected", "units", "until", "use", "variable", "vmode", "vprop", "vunit", "wait", "when", "while", "with",
+    "xnor", "xor",
+];
+
+/// Basic VHDL identifier of the sanitized [parts] joined by `_`, or an extended identifier if that is a reserved word.
+///
+/// Different parts may give the same basic identifier, which [Identifiers] resolves within a declarative region.
+pub fn vhdl_identifier<S: AsRef<str>>(parts: &[S]) -> String {
+    let name = parts.iter().map(|part| sanitize(part.as_ref())).collect::<Vec<_>>().join("_");
+    if RESERVED.contains(&name.to_ascii_lowercase().as_str()) { format!("\\{}\\", join(parts)) } else { name }
+}
+
+/// Names declared in one declarative region, which VHDL compares ignoring case.
+///
+/// Parts are named by [vhdl_identifier], unless they clash with parts named before, which then get the extended
+/// identifier of the parts joined by `__`. Basic identifiers cannot contain `__`, so that one is free.
+#[derive(Default)]
+struct Identifiers {
+    names: HashMap<Vec<String>, String>,
+    taken: HashSet<String>,
+}
+
+impl Identifiers {
+    /// Name of [parts], which is the same every time it is asked for.
+    fn name<S: AsRef<str>>(&mut self, parts: &[S]) -> String {
+        let parts: Vec<String> = parts.iter().map(|part| sanitize(part.as_ref())).collect();
+        if let Some(name) = self.names.get(&parts) {
+            return name.clone();
+        }
+        let mut name = vhdl_identifier(&parts);
+        if !name.starts_with('\\') && !self.taken.insert(name.to_ascii_lowercase()) {
+            name = format!("\\{}\\", join(&parts));
+        }
+        self.names.insert(parts, name.clone());
+        name
+    }
+}
+
+/// Side of the physical streams an entity is on, which determines the directions of the ports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TydiPortMode {
+    /// Drives `valid` and the payload of forward streams.
+    Source,
+    /// Drives `ready` of forward streams.
+    Sink,
+}
+
+/// Type of a bit vector of [width] bits, where a single bit is a `std_logic`.
+fn vhdl_bits(width: usize) -> String {
+    if width == 1 { "std_logic".to_string() } else { format!("std_logic_vector({} downto 0)", width - 1) }
+}
+
+/// Range of the [width] bits starting at [offset], or the single bit at [offset].
+fn vhdl_range(offset: usize, width: usize) -> String {
+    if width == 1 { format!("{}", offset) } else { format!("{} downto {}", offset + width - 1, offset) }
+}
+
+/// Declarations and conversion functions of the records of a package, collected while walking logical types.
+///
+/// Unions become records of a `tag` and a `payload`, as VHDL has no way to overlay the variants.
+#[derive(Default)]
+struct Records {
+    names: Identifiers,
+    declarations: String,
+    functions: String,
+    bodies: String,
+}
+
+impl Declarations for Records {
+    type Type = String;
+
+    fn bits(&mut self, width: usize) -> String {
+        vhdl_bits(width)
+    }
+
+    /// Declares a constant with the tag of every variant.
+    fn tags(&mut self, parts: &[String], variants: &[String]) {
+        for (i, name) in variants.iter().enumerate() {
+            let constant = self.names.name(&extended(parts, &[name.as_str(), "tag"]));
+            writeln!(self.declarations, "  constant {} : natural := {};", constant, i).unwrap();
+        }
+    }
+
+    /// Declares a record type with functions converting it from and to a `std_logic_vector`.
+    fn record(&mut self, parts: &[String], width: usize, members: Vec<Member<String>>) -> String {
+        let type_name = self.names.name(&extended(parts, &["t"]));
+        let from_slv = self.names.name(&[&["to".to_string()], parts].concat());
+        writeln!(self.declarations, "  type {} is record", type_name).unwrap();
+        for member in &members {
+            writeln!(self.declarations, "    {} : {};", vhdl_identifier(&[&member.name]), member.declared).unwrap();
+        }
+        writeln!(self.declarations, "  end record;").unwrap();
+
+        writeln!(self.functions, "  function to_slv(value : {}) return std_logic_vector;", type_name).unwrap();
+        writeln!(self.functions, "  function {}(value : std_logic_vector) return {};", from_slv, type_name).unwrap();
+
+        let mut to_body = String::new();
+        let mut from_body = String::new();
+        for member in &members {
+            let range = vhdl_range(member.offset, member.width);
+            let field = vhdl_identifier(&[&member.name]);
+            if member.declared.starts_with("std_logic") {
+                writeln!(to_body, "    result({}) := value.{};", range, field).unwrap();
+                writeln!(from_body, "    result.{} := bits({});", field, range).unwrap();
+            } else {
+                let from_field = self.names.name(&[&["to".to_string()], parts, std::slice::from_ref(&member.name)].concat());
+                writeln!(to_body, "    result({}) := to_slv(value.{});", range, field).unwrap();
+                writeln!(from_body, "    result.{} := {}(bits({}));", field, from_field, range).unwrap();
+            }
+        }
+        write!(self.bodies, "  function to_slv(value : {}) return std_logic_vector is\n    \
+            variable result : std_logic_vector({} downto 0);\n  begin\n{}    return result;\n  end function;\n\n",
+            type_name, width - 1, to_body).unwrap();
+        write!(self.bodies, "  function {}(value : std_logic_vector) return {} is\n    \
+            alias bits : std_logic_vector(value'length - 1 downto 0) is value;\n    variable result : {};\n  \
+            begin\n{}    return result;\n  end function;\n\n",
+            from_slv, type_name, type_name, from_body).unwrap();
+        type_name
+    }
+}
+
+/// Generates a VHDL package named `{name}_pkg` for the [streams], as split off from a logical type named [name].
+///
+/// For every stream, the package holds constants with the widths of its signals, and records with conversion functions
+/// for its element and user types, as far as these are groups or unions. The conversions place the fields as they are
+/// packed by [crate::TydiPacket::to_binary] in [order]: the first field at the least significant bits, or at the most
+/// significant bits with [crate::order::TydiFieldOrder::MsbFirst].
+pub fn vhdl_package(name: &str, streams: &[TydiPhysicalStream], order: TydiBitOrder) -> String {
+    let (constants, records) = declare_package(streams, order);
+    let package = vhdl_identifier(&[name, "pkg"]);
+    let mut result = String::new();
+    writeln!(result, "library ieee;\nuse ieee.std_logic_1164.all;\n").unwrap();
+    writeln!(result, "package {} is", package).unwrap();
+    write!(result, "{}", constants).unwrap();
+    if !records.declarations.is_empty() {
+        write!(result, "\n{}\n{}", records.declarations, records.functions).unwrap();
+    }
+    writeln!(result, "end package;").unwrap();
+    if !records.bodies.is_empty() {
+        write!(result, "\npackage body {} is\n{}end package body;\n", package, records.bodies.trim_end_matches('\n').to_owned() + "\n")
+            .unwrap();
+    }
+    result
+}
+
+/// Width constants and records of the package of [streams], named in the order they are declared in.
+fn declare_package(streams: &[TydiPhysicalStream], order: TydiBitOrder) -> (String, Records) {
+    let order = order.field_order;
+    let mut constants = String::new();
+    let mut records = Records::default();
+    for (stream, path) in streams.iter().zip(stream_paths(streams)) {
+        writeln!(constants, "  -- {}: {} lane(s), dimensionality {}, complexity {}, {:?}",
+                 stream.name(), stream.lanes, stream.dimensionality, stream.complexity, stream.direction).unwrap();
+        for (signal, width) in field_widths(stream) {
+            let constant = records.names.name(&extended(&path, &[signal, "width"]));
+            writeln!(constants, "  constant {} : natural := {};", constant, width).unwrap();
+        }
+        let user_width = records.names.name(&extended(&path, &["user", "width"]));
+        writeln!(constants, "  constant {} : natural := {};", user_width, stream.user.data_width()).unwrap();
+        let element_width = records.names.name(&extended(&path, &["element", "width"]));
+        writeln!(constants, "  constant {} : natural := {};", element_width, stream.element_width()).unwrap();
+
+        declare(&mut records, &extended(&path, &["element"]), &stream.element, order);
+        declare(&mut records, &extended(&path, &["user"]), &stream.user, order);
+    }
+    (constants, records)
+}
+
+/// Generates the port clause of an entity on the [mode] side of the [streams], using the width constants of
+/// [vhdl_package].
+///
+/// Every stream gets `valid`, `ready`, `data`, `last`, `stai`, `endi`, `strb` and `user` ports prefixed with its name,
+/// leaving out ports without width. Lanes are placed in `data` as in [crate::transfer::TydiTransfer::to_binary]. Ports
+/// are named apart from the names of the package, which the entity is expected to use.
+pub fn vhdl_ports(streams: &[TydiPhysicalStream], mode: TydiPortMode) -> String {
+    // The order of the fields does not affect the names
+    let mut names = declare_package(streams, TydiBitOrder::PORTABLE).1.names;
+    let mut ports = Vec::new();
+    for (stream, path) in streams.iter().zip(stream_paths(streams)) {
+        let drives_payload = (mode == TydiPortMode::Source) == (stream.direction == TydiDirection::Forward);
+        let (out, inp) = if drives_payload { ("out", "in") } else { ("in", "out") };
+
+        ports.push(format!("{} : {} std_logic", names.name(&extended(&path, &["valid"])), out));
+        ports.push(format!("{} : {} std_logic", names.name(&extended(&path, &["ready"])), inp));
+        let widths = field_widths(stream);
+        let signals = [("data", widths[4].1), ("last", widths[3].1), ("stai", widths[1].1), ("endi", widths[2].1),
+            ("strb", widths[0].1), ("user", stream.user.data_width())];
+        for (signal, width) in signals {
+            if width == 0 {
+                continue;
+            }
+            let constant = names.name(&extended(&path, &[signal, "width"]));
+            ports.push(format!("{} : {} std_logic_vector({} - 1 downto 0)",
+                               names.name(&extended(&path, &[signal])), out, constant));
+        }
+    }
+    format!("port (\n  {}\n);\n", ports.join(";\n  "))
+}
+
+/// [vhdl_package] for the physical streams of [T], named after [name].
+pub fn vhdl_package_for<T: TydiLogical>(name: &str, order: TydiBitOrder) -> String {
+    vhdl_package(name, &T::logical_type().split(name), order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::tests::readings;
+    use crate::logical::{TydiLogicalStream, TydiLogicalType};
+    use crate::order::{TydiEndianness, TydiFieldOrder};
+    use crate::logical::TydiLogicalType::Bits;
+
+    #[test]
+    fn test_identifiers() {
+        assert_eq!(vhdl_identifier(&["title"]), "title");
+        assert_eq!(vhdl_identifier(&["signal"]), "\\signal\\");
+        assert_eq!(vhdl_identifier(&["_private-field"]), "private_field");
+        assert_eq!(vhdl_identifier(&["posts", "title"]), "posts_title");
+        assert_eq!(vhdl_identifier(&["assume", "guarantee"]), "\\assume__guarantee\\");
+
+        let mut names = Identifiers::default();
+        assert_eq!(names.name(&["a_b", "c"]), "a_b_c");
+        // Clashes ignoring case get an extended identifier, which keeps the parts apart
+        assert_eq!(names.name(&["a", "b_c"]), "\\a__b_c\\");
+        assert_eq!(names.name(&["A_B_C"]), "\\A_B_C\\");
+        assert_eq!(names.name(&["a_b", "c"]), "a_b_c");
+        assert_eq!(names.name(&["signal"]), "\\signal\\");
+    }
+
+    #[test]
+    fn test_package() {
+        let package = vhdl_package("sensor", &readings().split("readings"), TydiBitOrder::PORTABLE);
+        assert!(package.starts_with("library ieee;\nuse ieee.std_logic_1164.all;\n\npackage sensor_pkg is\n"));
+        assert!(package.contains("  constant readings_last_width : natural := 1;\n"));
+        assert!(package.contains("  constant readings_element_width : natural := 27;\n"));
+        assert!(package.contains("  constant readings_element_unit_kelvin_tag : natural := 2;\n"));
+        assert!(package.contains("  type readings_element_unit_t is record\n    tag : std_logic_vector(1 downto 0);\n    \
+            payload : std_logic_vector(7 downto 0);\n  end record;\n"));
+        assert!(package.contains("  type readings_element_t is record\n    valid : std_logic;\n    \
+            value : std_logic_vector(15 downto 0);\n    unit : readings_element_unit_t;\n  end record;\n"));
+        assert!(package.contains("    result(0) := value.valid;\n    result(16 downto 1) := value.value;\n    \
+            result(26 downto 17) := to_slv(value.unit);\n"));
+        assert!(package.contains("    result.unit := to_readings_element_unit(bits(26 downto 17));\n"));
+        assert!(!package.contains("__"));
+
+        let msb_first = TydiBitOrder::new(TydiEndianness::Little, TydiFieldOrder::MsbFirst);
+        let package = vhdl_package("sensor", &readings().split("readings"), msb_first);
+        assert!(package.contains("    result(26) := value.valid;\n    result(25 downto 10) := value.value;\n"));
+    }
+
+    #[test]
+    fn test_clashing_names() {
+        let clashing = TydiLogicalType::stream(TydiLogicalType::group(vec![
+            ("in-range", Bits(1)),
+            ("in_range", Bits(2)),
+            ("In_Range", Bits(3)),
+        ]), 1);
+        let package = vhdl_package("sensor", &clashing.split("readings"), TydiBitOrder::PORTABLE);
+        assert!(package.contains("    in_range : std_logic;\n    in_range_2 : std_logic_vector(1 downto 0);\n    \
+            In_Range_3 : std_logic_vector(2 downto 0);\n"));
+
+        // Different paths joined by `_` give the same identifier
+        let pair = TydiLogicalType::group(vec![("x", Bits(1)), ("y", Bits(1))]);
+        let joined = TydiLogicalType::stream(TydiLogicalType::group(vec![
+            ("a_b", pair.clone()),
+            ("a", TydiLogicalType::group(vec![("b", pair)])),
+        ]), 1);
+        let package = vhdl_package("sensor", &joined.split("r"), TydiBitOrder::PORTABLE);
+        assert!(package.contains("  type r_element_a_b_t is record\n"));
+        assert!(package.contains("  type \\r__element__a__b__t\\ is record\n"));
+        assert!(package.contains("    b : \\r__element__a__b__t\\;\n"));
+        assert!(package.contains("    result.b := \\to__r__element__a__b\\(bits(1 downto 0));\n"));
+    }
+
+    #[test]
+    fn test_ports() {
+        let mut chars = TydiLogicalStream::new(Bits(8), 1);
+        chars.throughput = 4.0;
+        chars.direction = TydiDirection::Reverse;
+        let streams = TydiLogicalType::Stream(Box::new(chars)).split("chars");
+        let ports = vhdl_ports(&streams, TydiPortMode::Source);
+        assert_eq!(ports, "port (\n  chars_valid : in std_logic;\n  chars_ready : out std_logic;\n  \
+            chars_data : in std_logic_vector(chars_data_width - 1 downto 0);\n  \
+            chars_last : in std_logic_vector(chars_last_width - 1 downto 0);\n  \
+            chars_stai : in std_logic_vector(chars_stai_width - 1 downto 0);\n  \
+            chars_endi : in std_logic_vector(chars_endi_width - 1 downto 0);\n  \
+            chars_strb : in std_logic_vector(chars_strb_width - 1 downto 0)\n);\n");
+        assert!(vhdl_ports(&streams, TydiPortMode::Sink).contains("chars_valid : out std_logic;\n"));
+
+        // Ports do not hide the constants of the package
+        let streams = TydiLogicalType::stream(Bits(8), 1).split("chars_data_width");
+        assert!(vhdl_ports(&streams, TydiPortMode::Source).contains("chars_data_width_valid : out std_logic;\n"));
+    }
+}
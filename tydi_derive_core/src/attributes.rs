@@ -0,0 +1,98 @@
+use syn::{Field, Lit, LitInt};
+use crate::{is_sequence, wrapped_type};
+
+/// Options of a field, set through `#[tydi(..)]` attributes.
+#[derive(Default)]
+pub struct FieldAttributes {
+    /// Number of bits the field is packed into, instead of its native width.
+    pub bits: Option<usize>,
+    /// Whether the field is packed in two's complement, and sign-extended when unpacking.
+    pub signed: bool,
+    /// Maximum length of a `Vec` that is packed inline instead of transferred over a child stream.
+    pub max_len: Option<usize>,
+    /// Average number of elements per transfer of the stream of a sequence.
+    pub throughput: Option<f64>,
+    /// Complexity level of the stream of a sequence.
+    pub complexity: Option<u8>,
+}
+
+impl FieldAttributes {
+    pub fn parse(field: &Field) -> syn::Result<Self> {
+        let mut result = Self::default();
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("tydi")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("bits") {
+                    let bits: LitInt = meta.value()?.parse()?;
+                    let bits = bits.base10_parse::<usize>()?;
+                    if bits == 0 {
+                        return Err(meta.error("a field takes at least one bit"));
+                    }
+                    result.bits = Some(bits);
+                    Ok(())
+                } else if meta.path.is_ident("signed") {
+                    result.signed = true;
+                    Ok(())
+                } else if meta.path.is_ident("max_len") {
+                    let max_len: LitInt = meta.value()?.parse()?;
+                    result.max_len = Some(max_len.base10_parse::<usize>()?);
+                    Ok(())
+                } else if meta.path.is_ident("throughput") {
+                    let throughput = match meta.value()?.parse()? {
+                        Lit::Float(lit) => lit.base10_parse::<f64>()?,
+                        Lit::Int(lit) => lit.base10_parse::<f64>()?,
+                        _ => return Err(meta.error("the throughput should be a number")),
+                    };
+                    if throughput <= 0.0 {
+                        return Err(meta.error("the throughput should be positive"));
+                    }
+                    result.throughput = Some(throughput);
+                    Ok(())
+                } else if meta.path.is_ident("complexity") {
+                    let complexity: LitInt = meta.value()?.parse()?;
+                    let complexity = complexity.base10_parse::<u8>()?;
+                    if !(1..=8).contains(&complexity) {
+                        return Err(meta.error("the complexity should be between 1 and 8"));
+                    }
+                    result.complexity = Some(complexity);
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported tydi attribute, expected `bits`, `signed`, `max_len`, `throughput` or `complexity`"))
+                }
+            })?;
+        }
+
+        if result.signed && result.bits.is_none() {
+            return Err(syn::Error::new_spanned(field, "`signed` requires `bits` to be set"));
+        }
+        if result.bits.is_some() && is_sequence(&field.ty) {
+            return Err(syn::Error::new_spanned(field, "`bits` cannot be set on sequences"));
+        }
+        if result.max_len.is_some() && wrapped_type(&field.ty, "Vec").is_none() {
+            return Err(syn::Error::new_spanned(field, "`max_len` can only be set on a `Vec`"));
+        }
+        let streamed = is_sequence(&field.ty) && result.max_len.is_none();
+        if (result.throughput.is_some() || result.complexity.is_some()) && !streamed {
+            return Err(syn::Error::new_spanned(field, "`throughput` and `complexity` can only be set on sequences with a stream of their own"));
+        }
+        Ok(result)
+    }
+
+    /// Attributes of a field, which should have been checked through [validate] beforehand.
+    pub fn of(field: &Field) -> Self {
+        Self::parse(field).unwrap_or_default()
+    }
+}
+
+/// Checks the attributes of all [fields], combining all errors into one.
+pub fn validate<'a>(fields: impl IntoIterator<Item = &'a Field>) -> syn::Result<()> {
+    let mut errors: Option<syn::Error> = None;
+    for field in fields {
+        if let Err(error) = FieldAttributes::parse(field) {
+            match errors.as_mut() {
+                Some(errors) => errors.combine(error),
+                None => errors = Some(error),
+            }
+        }
+    }
+    errors.map_or(Ok(()), Err)
+}
@@ -0,0 +1,166 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Field, ItemStruct, Type};
+use crate::{generics_marker, option_inner, wrapped_type};
+use crate::attributes::FieldAttributes;
+
+/// Types that never contain child streams, and thus are left out of the generated stream structs. The same holds for
+/// options, arrays and tuples of these types.
+const PRIMITIVES: [&str; 16] = [
+    "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize", "f32", "f64", "bool", "char",
+];
+
+pub(crate) fn is_primitive(ty: &Type) -> bool {
+    if let Some(inner) = option_inner(ty) {
+        is_primitive(inner)
+    } else if let Type::Array(array) = ty {
+        is_primitive(&array.elem)
+    } else if let Type::Tuple(tuple) = ty {
+        !tuple.elems.is_empty() && tuple.elems.iter().all(is_primitive)
+    } else if let Type::Path(type_path) = ty {
+        type_path.path.get_ident().is_some_and(|ident| PRIMITIVES.iter().any(|p| ident == p))
+    } else {
+        false
+    }
+}
+
+/// Whether [field] has child streams, which get a member in the generated stream structs.
+pub(crate) fn has_child_streams(field: &Field) -> bool {
+    !is_primitive(&field.ty) && FieldAttributes::of(field).max_len.is_none()
+}
+
+/// The logical type of [field], taking its attributes into account. The `throughput` and `complexity` attributes set
+/// the properties of the stream of a sequence.
+pub(crate) fn field_logical_type(field: &Field) -> TokenStream {
+    let field_type = &field.ty;
+    let attributes = FieldAttributes::of(field);
+    if let Some(bits) = attributes.bits {
+        quote! { ::rust_tydi_packages::logical::TydiLogicalType::Bits(#bits) }
+    } else if let (Some(max_len), Some(item_type)) = (attributes.max_len, wrapped_type(field_type, "Vec")) {
+        quote! { ::rust_tydi_packages::logical::bounded_logical_type::<#item_type>(#max_len) }
+    } else {
+        let throughput = attributes.throughput.map(|throughput| quote! { .with_throughput(#throughput) });
+        let complexity = attributes.complexity.map(|level| {
+            let level = format_ident!("C{}", level);
+            quote! { .with_complexity(::rust_tydi_packages::complexity::TydiComplexity::#level) }
+        });
+        quote! { <#field_type as ::rust_tydi_packages::logical::TydiLogical>::logical_type()#throughput #complexity }
+    }
+}
+
+/// Generates `TydiLogical` and `TydiDrill` for the struct, along with the structs holding its child streams.
+///
+/// The logical type is a group of all [fields]. Every field that is not a primitive gets a member in the
+/// `XxxStreams` and `XxxBinaryStreams` structs, holding the child streams of that field's type. Sequences are drilled
+/// into and nested structs recurse through their own `TydiDrill` implementation.
+pub fn generate_bundle(input: &ItemStruct, fields: &[&Field]) -> TokenStream {
+    let struct_name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let streams_name = format_ident!("{}Streams", struct_name);
+    let binary_streams_name = format_ident!("{}BinaryStreams", struct_name);
+    let (marker, marker_init) = generics_marker(struct_name, &input.generics);
+
+    let logical_fields = fields.iter().map(|f| {
+        let field_name = f.ident.as_ref().unwrap();
+        let name = field_name.to_string();
+        let name = name.trim_start_matches("r#");
+        let logical = field_logical_type(f);
+        quote! { (#name, #logical) }
+    });
+
+    let stream_fields: Vec<&&Field> = fields.iter().filter(|f| has_child_streams(f)).collect();
+    let names: Vec<_> = stream_fields.iter().map(|f| &f.ident).collect();
+    let types: Vec<_> = stream_fields.iter().map(|f| &f.ty).collect();
+    let without_streams = fields.iter().map(|f| {
+        let field_name = &f.ident;
+        let field_type = &f.ty;
+        if has_child_streams(f) {
+            quote! { #field_name: <#field_type as ::rust_tydi_packages::bundle::TydiDrill>::without_streams(&self.#field_name) }
+        } else {
+            quote! { #field_name: self.#field_name.clone() }
+        }
+    });
+
+    quote! {
+        impl #impl_generics ::rust_tydi_packages::logical::TydiLogical for #struct_name #ty_generics #where_clause {
+            fn logical_type() -> ::rust_tydi_packages::logical::TydiLogicalType {
+                ::rust_tydi_packages::logical::TydiLogicalType::group(vec![#(#logical_fields),*])
+            }
+        }
+
+        pub struct #streams_name #impl_generics #where_clause {
+            #(pub #names: <#types as ::rust_tydi_packages::bundle::TydiDrill>::Streams,)*
+            #marker
+        }
+
+        pub struct #binary_streams_name #impl_generics #where_clause {
+            #(pub #names: <#types as ::rust_tydi_packages::bundle::TydiDrill>::BinaryStreams,)*
+            #marker
+        }
+
+        impl #impl_generics ::rust_tydi_packages::bundle::TydiDrill for #struct_name #ty_generics #where_clause {
+            type Streams = #streams_name #ty_generics;
+            type BinaryStreams = #binary_streams_name #ty_generics;
+
+            fn drill_streams(stream: &::rust_tydi_packages::TydiStream<&Self>) -> Self::Streams {
+                #streams_name {
+                    #(#names: <#types as ::rust_tydi_packages::bundle::TydiDrill>::drill_streams(&stream.project(|e| &e.#names)),)*
+                    #marker_init
+                }
+            }
+
+            fn without_streams(&self) -> Self {
+                Self {
+                    #(#without_streams,)*
+                }
+            }
+
+            fn inject_streams(stream: &mut ::rust_tydi_packages::TydiStream<Self>, streams: Self::Streams) {
+                #(
+                    stream.inject_field(|e| &mut e.#names, |s| <#types as ::rust_tydi_packages::bundle::TydiDrill>::inject_streams(s, streams.#names));
+                )*
+            }
+
+            fn finish_streams(streams: &Self::Streams, c: ::rust_tydi_packages::complexity::TydiComplexity, order: ::rust_tydi_packages::order::TydiBitOrder)
+                -> Self::BinaryStreams {
+                #binary_streams_name {
+                    #(#names: <#types as ::rust_tydi_packages::bundle::TydiDrill>::finish_streams(&streams.#names, c, order),)*
+                    #marker_init
+                }
+            }
+
+            fn streams_from_binary(binary: Self::BinaryStreams, dim: usize) -> Self::Streams {
+                #streams_name {
+                    #(#names: <#types as ::rust_tydi_packages::bundle::TydiDrill>::streams_from_binary(binary.#names, dim),)*
+                    #marker_init
+                }
+            }
+
+            #[allow(unused_variables)]
+            fn collect_binary_streams<'a>(binary: &'a Self::BinaryStreams, result: &mut Vec<&'a ::rust_tydi_packages::TydiBinaryStream>) {
+                #(<#types as ::rust_tydi_packages::bundle::TydiDrill>::collect_binary_streams(&binary.#names, result);)*
+            }
+
+            #[allow(unused_variables)]
+            fn take_binary_streams<I: Iterator<Item = ::rust_tydi_packages::TydiBinaryStream>>(streams: &mut I) -> Self::BinaryStreams {
+                #binary_streams_name {
+                    #(#names: <#types as ::rust_tydi_packages::bundle::TydiDrill>::take_binary_streams(streams),)*
+                    #marker_init
+                }
+            }
+
+            fn finish_items(items: &::rust_tydi_packages::TydiStream<Self>, c: ::rust_tydi_packages::complexity::TydiComplexity, order: ::rust_tydi_packages::order::TydiBitOrder)
+                -> Option<::rust_tydi_packages::TydiBinaryStream> {
+                ::rust_tydi_packages::bundle::finish_data_items(items, c, order)
+            }
+
+            fn items_from_binary(items: Option<::rust_tydi_packages::TydiBinaryStream>, _streams: &Self::Streams, dim: usize) -> ::rust_tydi_packages::TydiStream<Self> {
+                ::rust_tydi_packages::bundle::data_items_from_binary(items, dim)
+            }
+
+            fn placeholder() -> Self {
+                ::rust_tydi_packages::bundle::data_placeholder()
+            }
+        }
+    }
+}
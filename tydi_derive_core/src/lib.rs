@@ -1,45 +1,111 @@
 extern crate proc_macro;
 
 mod tests;
+mod packing;
+mod bundle;
+mod union;
+mod attributes;
 
 use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, parse2, ItemStruct, Data, DeriveInput, Fields, Ident, Type};
+use syn::{parse2, Field, GenericArgument, Generics, Item, Fields, Ident, PathArguments, Type};
+use crate::attributes::FieldAttributes;
+use crate::packing::generate_packing;
+use crate::bundle::generate_bundle;
+use crate::union::generate_union;
+
+/// Whether [ty] is a sequence, which is transferred over a physical stream of its own.
+fn is_sequence(ty: &Type) -> bool {
+    if let Type::Path(type_path) = ty {
+        type_path.path.segments.last().is_some_and(|segment| {
+            segment.ident == "Vec" || segment.ident == "String" ||  segment.ident == "str"
+        })
+    } else {
+        false
+    }
+}
+
+/// Whether [field] is a sequence that is transferred over a physical stream of its own, rather than packed inline
+/// through `#[tydi(max_len = ..)]`.
+fn is_streamed_sequence(field: &Field) -> bool {
+    is_sequence(&field.ty) && FieldAttributes::of(field).max_len.is_none()
+}
+
+/// The type wrapped by [ty] if it is an `Option`.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    wrapped_type(ty, "Option")
+}
+
+/// The first type argument of [ty] if it is a [wrapper], such as `Option` or `Vec`.
+fn wrapped_type<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+    let Type::Path(type_path) = ty else { return None };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != wrapper {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first()? {
+            GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// A member for the structs generated alongside the type [name] that uses all of its [generics], as the generated
+/// structs can leave out the fields in which a parameter appears. Returned along with its initializer, both empty if
+/// there are no generics.
+fn generics_marker(name: &Ident, generics: &Generics) -> (TokenStream, TokenStream) {
+    if generics.params.is_empty() {
+        return (quote! {}, quote! {});
+    }
+    let (_, ty_generics, _) = generics.split_for_impl();
+    (
+        quote! { pub _marker: ::core::marker::PhantomData<fn() -> #name #ty_generics>, },
+        quote! { _marker: ::core::marker::PhantomData, },
+    )
+}
 
 pub fn tydi_derive_impl(input: TokenStream) -> TokenStream {
     // Parse the input tokens into a syntax tree
     // let input = parse_macro_input!(input as DeriveInput);
 
     // proc_marco2 version of "parse_macro_input!(input as ItemFn)"
-    let input = match parse2::<ItemStruct>(input) {
-        Ok(syntax_tree) => syntax_tree,
+    let input = match parse2::<Item>(input) {
+        Ok(Item::Struct(syntax_tree)) => syntax_tree,
+        // Enums are transferred as a union of their variants
+        Ok(Item::Enum(syntax_tree)) => {
+            let fields = syntax_tree.variants.iter().flat_map(|v| v.fields.iter());
+            if let Err(error) = attributes::validate(fields) {
+                return error.to_compile_error();
+            }
+            return generate_union(&syntax_tree)
+        },
+        Ok(item) => return syn::Error::new_spanned(item, "Tydi can only be derived for structs and enums").to_compile_error(),
         Err(error) => return error.to_compile_error(),
     };
 
+    if let Err(error) = attributes::validate(input.fields.iter()) {
+        return error.to_compile_error();
+    }
+
     let struct_name = &input.ident; // e.g., User
 
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let (marker, marker_init) = generics_marker(struct_name, &input.generics);
 
     // Collect fields for the non-Vec struct and Vec-only struct
+    let mut all_fields = Vec::new();
     let mut non_vec_fields = Vec::new();
     let mut vec_fields = Vec::new();
 
     if let Fields::Named(fields_named) = &input.fields {
         for field in &fields_named.named {
-            let field_name = field.ident.as_ref().expect("Expected named field");
-            let field_type = &field.ty;
-
-            // Check if the type is a Vec
-            let is_vec = if let Type::Path(type_path) = field_type {
-                let str_repr = type_path.path.segments.iter().map(|x| x.ident.to_string()).collect::<Vec<_>>().join(".");
-                type_path.path.segments.last().map_or(false, |segment| {
-                    segment.ident == "Vec" || segment.ident == "String" ||  segment.ident == "str"
-                })
-            } else {
-                false
-            };
+            all_fields.push(field);
 
-            if is_vec {
+            // Optional sequences stay with the non-Vec fields, as their valid bit is packed with those, and so do
+            // sequences that are packed inline
+            if is_streamed_sequence(field) {
                 vec_fields.push(field);
             } else {
                 non_vec_fields.push(field);
@@ -64,8 +130,9 @@ pub fn tydi_derive_impl(input: TokenStream) -> TokenStream {
     let non_vec_struct_name = Ident::new(&format!("{}NonVecs", struct_name), struct_name.span());
     let generated_non_vec_struct = quote! {
         #[derive(Debug, PartialEq, Eq, Clone)] // Add common derives
-        pub struct #non_vec_struct_name #ty_generics #where_clause {
+        pub struct #non_vec_struct_name #impl_generics #where_clause {
             #(#non_vec_fields_tokens)*
+            #marker
         }
     };
 
@@ -73,8 +140,9 @@ pub fn tydi_derive_impl(input: TokenStream) -> TokenStream {
     let vec_struct_name = Ident::new(&format!("{}Vecs", struct_name), struct_name.span());
     let generated_vec_struct = quote! {
         #[derive(Debug, PartialEq, Eq, Clone)] // Add common derives
-        pub struct #vec_struct_name #ty_generics #where_clause {
+        pub struct #vec_struct_name #impl_generics #where_clause {
             #(#vec_fields_tokens)*
+            #marker
         }
     };
 
@@ -89,7 +157,8 @@ pub fn tydi_derive_impl(input: TokenStream) -> TokenStream {
         impl #impl_generics From<#struct_name #ty_generics> for #non_vec_struct_name #ty_generics #where_clause {
             fn from(value: #struct_name #ty_generics) -> Self {
                     Self {
-                        #(#field_assignments),*
+                        #(#field_assignments,)*
+                        #marker_init
                     }
                 }
             }
@@ -107,7 +176,8 @@ pub fn tydi_derive_impl(input: TokenStream) -> TokenStream {
             impl #impl_generics From<#struct_name #ty_generics> for #vec_struct_name #ty_generics #where_clause {
                 fn from(value: #struct_name #ty_generics) -> Self {
                     Self {
-                        #(#field_assignments),*
+                        #(#field_assignments,)*
+                        #marker_init
                     }
                 }
             }
@@ -115,13 +185,21 @@ pub fn tydi_derive_impl(input: TokenStream) -> TokenStream {
     };
 
 
+    // Generate the conversions to and from binaries
+    let packing_impl = generate_packing(&input, &non_vec_fields, &vec_fields);
+
+    // Generate the logical type and the physical stream bundle
+    let bundle_impl = generate_bundle(&input, &all_fields);
+
     // Combine all generated tokens
     let expanded = quote! {
         #generated_non_vec_struct
         #generated_vec_struct
         #original_to_non_vec_impl
         #original_to_vec_impl
+        #packing_impl
+        #bundle_impl
     };
 
-    expanded.into()
+    expanded
 }
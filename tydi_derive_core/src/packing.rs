@@ -0,0 +1,116 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Field, Ident, ItemStruct};
+use crate::{is_sequence, option_inner, wrapped_type};
+use crate::attributes::FieldAttributes;
+
+/// Whether [field] is an `Option` of a sequence, of which only the valid bit is packed.
+fn is_optional_sequence(field: &Field) -> bool {
+    option_inner(&field.ty).is_some_and(is_sequence)
+}
+
+/// Converts the [value] of [field] into a binary, packed in the `TydiBitOrder` named `order`.
+pub fn pack_field(field: &Field, value: TokenStream) -> TokenStream {
+    let attributes = FieldAttributes::of(field);
+    if let Some(bits) = attributes.bits {
+        let signed = attributes.signed;
+        quote! { ::rust_tydi_packages::binary::TydiBitField::to_bit_field(#value, #bits, #signed, order) }
+    } else if let Some(max_len) = attributes.max_len {
+        quote! { ::rust_tydi_packages::binary::pack_bounded(#value, #max_len, order) }
+    } else if is_optional_sequence(field) {
+        quote! { ::rust_tydi_packages::binary::ToTydiBinary::to_tydi_binary(#value.is_some(), order) }
+    } else {
+        quote! { ::rust_tydi_packages::binary::ToTydiBinary::to_tydi_binary(#value, order) }
+    }
+}
+
+/// Reads the value of [field] into [binding] from the `TydiBitReader` named `reader`, returning early with the error
+/// if the bits do not hold a valid value. The [binding] must not clash with the locals of the generated code, so it
+/// cannot be the name of the field.
+pub fn unpack_field(field: &Field, binding: &Ident) -> TokenStream {
+    let field_type = &field.ty;
+    let attributes = FieldAttributes::of(field);
+    if let Some(bits) = attributes.bits {
+        let signed = attributes.signed;
+        quote! {
+            let #binding: #field_type = reader.try_read_bit_field(#bits, #signed)?;
+        }
+    } else if let (Some(max_len), Some(item_type)) = (attributes.max_len, wrapped_type(field_type, "Vec")) {
+        quote! {
+            let (#binding, _): (#field_type, _) = ::rust_tydi_packages::binary::try_unpack_bounded(
+                reader.try_read_binary(::rust_tydi_packages::logical::bounded_logical_type::<#item_type>(#max_len).data_width())?,
+                #max_len,
+            )?;
+        }
+    } else if is_optional_sequence(field) {
+        quote! {
+            let #binding = reader.try_read::<bool>()?.then(Default::default);
+        }
+    } else {
+        quote! {
+            let #binding = reader.try_read::<#field_type>()?;
+        }
+    }
+}
+
+/// Generates `ToTydiBinary for Struct`, `From<Struct> for TydiBinary` and `FromTydiBinary for Struct`.
+///
+/// Only the [non_vec_fields] are packed, in order of declaration and placed as the `TydiBitOrder` they are packed in
+/// prescribes. Nested types are packed through their own implementations of these traits. When unpacking, the
+/// [vec_fields] are left at their default value, to be filled from their own physical streams. Optional sequences are
+/// packed as just their valid bit, and unpacked as an empty sequence if it is set. Fields with `#[tydi(bits = ..)]` are
+/// packed into that number of bits, and sequences with `#[tydi(max_len = ..)]` are packed inline.
+pub fn generate_packing(input: &ItemStruct, non_vec_fields: &[&Field], vec_fields: &[&Field]) -> TokenStream {
+    let struct_name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let pack_fields = non_vec_fields.iter().map(|f| {
+        let field_name = &f.ident;
+        pack_field(f, quote! { self.#field_name })
+    });
+
+    // Unpacked fields are bound apart from their names, which may be those of the locals of the generated code
+    let bindings: Vec<Ident> = (0..non_vec_fields.len()).map(|i| format_ident!("__tydi_field_{}", i)).collect();
+    let unpack_fields = non_vec_fields.iter().zip(&bindings).map(|(f, binding)| unpack_field(f, binding));
+
+    let non_vec_names = non_vec_fields.iter().map(|f| &f.ident);
+    let vec_names = vec_fields.iter().map(|f| &f.ident);
+
+    quote! {
+        impl #impl_generics ::rust_tydi_packages::binary::ToTydiBinary for #struct_name #ty_generics #where_clause {
+            fn to_tydi_binary(self, order: ::rust_tydi_packages::order::TydiBitOrder) -> ::rust_tydi_packages::binary::TydiBinary {
+                #[allow(unused_mut)]
+                let mut writer = ::rust_tydi_packages::cursor::TydiBitWriter::in_order(order);
+                #(
+                    let binary: ::rust_tydi_packages::binary::TydiBinary = #pack_fields;
+                    writer.write_binary(&binary);
+                )*
+                writer.finish()
+            }
+        }
+
+        impl #impl_generics From<#struct_name #ty_generics> for ::rust_tydi_packages::binary::TydiBinary #where_clause {
+            fn from(value: #struct_name #ty_generics) -> Self {
+                ::rust_tydi_packages::binary::ToTydiBinary::to_tydi_binary(value, ::rust_tydi_packages::order::TydiBitOrder::PORTABLE)
+            }
+        }
+
+        impl #impl_generics ::rust_tydi_packages::binary::FromTydiBinary for #struct_name #ty_generics #where_clause {
+            fn from_tydi_binary(value: ::rust_tydi_packages::binary::TydiBinary) -> (Self, ::rust_tydi_packages::binary::TydiBinary) {
+                Self::try_from_tydi_binary(value).unwrap_or_else(|e| panic!("{}", e))
+            }
+
+            fn try_from_tydi_binary(value: ::rust_tydi_packages::binary::TydiBinary)
+                -> Result<(Self, ::rust_tydi_packages::binary::TydiBinary), ::rust_tydi_packages::error::TydiError> {
+                #[allow(unused_mut)]
+                let mut reader = ::rust_tydi_packages::cursor::TydiBitReader::new(&value);
+                #(#unpack_fields)*
+                let unpacked = Self {
+                    #(#non_vec_names: #bindings,)*
+                    #(#vec_names: Default::default(),)*
+                };
+                Ok((unpacked, reader.finish()))
+            }
+        }
+    }
+}
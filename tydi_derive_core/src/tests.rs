@@ -1,7 +1,6 @@
 #![cfg(test)]
 
 use crate::{tydi_derive_impl};
-use proc_macro2::TokenStream;
 use quote::quote;
 
 #[test]
@@ -61,3 +60,81 @@ fn author() {
     println!("{}", after_str);
     println!("done");
 }
+
+#[test]
+fn event() {
+    let input = quote! {
+        enum Event {
+            Ping,
+            Liked(u32),
+            Tagged(u32, Vec<String>),
+            Commented { post_id: u32, author: Author, content: String },
+        }
+    };
+
+    let after = tydi_derive_impl(input).to_string();
+    assert!(after.contains("(\"Ping\" , :: rust_tydi_packages :: logical :: TydiLogicalType :: Null)"));
+    assert!(after.contains("(\"Tagged\" , :: rust_tydi_packages :: logical :: TydiLogicalType :: group (vec ! [(\"field_0\" , \
+        < u32 as :: rust_tydi_packages :: logical :: TydiLogical > :: logical_type ()) , (\"field_1\" , \
+        < Vec < String > as :: rust_tydi_packages :: logical :: TydiLogical > :: logical_type ())]))"));
+    assert!(after.contains("try_union_tag_from_binary (value , 4usize)"));
+    // Named fields are bound apart from their names, which may be those of the locals of the generated code
+    assert!(after.contains("Event :: Commented { post_id : __tydi_field_0 , author : __tydi_field_1 , content : _ } =>"));
+    // Only the fields with child streams get a member in the streams structs
+    assert!(after.contains("pub struct EventStreams { \
+        pub tagged_1 : < Vec < String > as :: rust_tydi_packages :: bundle :: TydiDrill > :: Streams , \
+        pub commented_author : < Author as :: rust_tydi_packages :: bundle :: TydiDrill > :: Streams , \
+        pub commented_content : < String as :: rust_tydi_packages :: bundle :: TydiDrill > :: Streams , }"));
+    // Inactive variants do not require the field types to implement Default
+    assert!(after.contains("let absent = < Author as :: rust_tydi_packages :: bundle :: TydiDrill > :: placeholder () ;"));
+    assert!(!after.contains("as Default"));
+}
+
+#[test]
+fn invalid_attributes() {
+    let input = quote! {
+        struct Reaction {
+            #[tydi(signed)]
+            delta: i32,
+            #[tydi(bytes = 2)]
+            likes: u32,
+        }
+    };
+
+    let after = tydi_derive_impl(input).to_string();
+    assert!(after.contains("`signed` requires `bits` to be set"));
+    assert!(after.contains("unsupported tydi attribute"));
+}
+
+#[test]
+fn stream_attributes() {
+    let input = quote! {
+        struct Article {
+            #[tydi(throughput = 4.0, complexity = 7)]
+            title: String,
+            #[tydi(complexity = 9)]
+            tags: Vec<String>,
+            #[tydi(throughput = 2)]
+            likes: u32,
+        }
+    };
+
+    let after = tydi_derive_impl(input).to_string();
+    assert!(after.contains("the complexity should be between 1 and 8"));
+    assert!(after.contains("`throughput` and `complexity` can only be set on sequences with a stream of their own"));
+}
+
+#[test]
+fn generic() {
+    let input = quote! {
+        struct Window<T: Clone, const N: usize> {
+            #[tydi(max_len = 4)]
+            samples: Vec<T>,
+            offsets: [u8; N],
+        }
+    };
+
+    let after = tydi_derive_impl(input).to_string();
+    assert!(after.contains("pub struct WindowStreams < T : Clone , const N : usize >"));
+    assert!(after.contains("pub _marker : :: core :: marker :: PhantomData < fn () -> Window < T , N > >"));
+}
@@ -0,0 +1,321 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Field, Fields, Ident, ItemEnum, Variant};
+use crate::bundle::{field_logical_type, has_child_streams};
+use crate::{generics_marker, is_streamed_sequence};
+use crate::packing::{pack_field, unpack_field};
+
+/// Converts a variant name such as `PostCreated` into `post_created`.
+fn to_snake_case(name: &str) -> String {
+    let mut result = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                result.push('_');
+            }
+            result.extend(c.to_lowercase());
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// A field of a variant, along with the names it goes by in the generated code.
+struct VariantField<'a> {
+    field: &'a Field,
+    /// Name of the field in the logical type.
+    name: String,
+    /// Binding of the field in patterns, which cannot clash with the locals of the generated code.
+    binding: Ident,
+    /// Member holding the child streams of the field in the generated stream structs.
+    member: Ident,
+}
+
+fn variant_fields(variant: &Variant) -> Vec<VariantField<'_>> {
+    let variant_snake = to_snake_case(&variant.ident.to_string());
+    let newtype = matches!(&variant.fields, Fields::Unnamed(fields) if fields.unnamed.len() == 1);
+    variant.fields.iter().enumerate().map(|(i, field)| match &field.ident {
+        Some(ident) => {
+            let name = ident.to_string().trim_start_matches("r#").to_string();
+            let member = format_ident!("{}_{}", variant_snake, name);
+            VariantField { field, name, binding: format_ident!("__tydi_field_{}", i), member }
+        }
+        None => {
+            let member = if newtype {
+                format_ident!("{}", variant_snake)
+            } else {
+                format_ident!("{}_{}", variant_snake, i)
+            };
+            VariantField { field, name: format!("field_{}", i), binding: format_ident!("__tydi_field_{}", i), member }
+        }
+    }).collect()
+}
+
+/// Pattern matching [variant], binding the [bound] fields and ignoring the rest.
+fn variant_pattern(enum_name: &Ident, variant: &Variant, fields: &[VariantField], bound: impl Fn(&VariantField) -> bool) -> TokenStream {
+    let variant_name = &variant.ident;
+    let bindings = fields.iter().map(|f| {
+        if bound(f) { f.binding.clone() } else { format_ident!("_") }
+    });
+    match &variant.fields {
+        Fields::Named(_) => {
+            let fields = fields.iter().map(|f| {
+                let name = &f.field.ident;
+                let binding = &f.binding;
+                if bound(f) { quote! { #name: #binding } } else { quote! { #name: _ } }
+            });
+            quote! { #enum_name::#variant_name { #(#fields),* } }
+        }
+        Fields::Unnamed(_) => quote! { #enum_name::#variant_name(#(#bindings),*) },
+        Fields::Unit => quote! { #enum_name::#variant_name },
+    }
+}
+
+/// Generates the conversions to and from binaries, `TydiLogical` and `TydiDrill` for an enum.
+///
+/// The enum is a union of its variants. A unit variant is [TydiLogicalType::Null], a variant with a single unnamed
+/// field is the type of that field, and other variants are a group of their fields. Tuple fields are named
+/// `field_0`, `field_1`, ...
+///
+/// The binary form is the tag, the index of the variant, followed by the packed fields of the variant, zero-filled to
+/// the width of the largest variant. The child streams of the fields of all variants are kept in the generated
+/// stream structs, in which an inactive variant transfers empty sequences.
+pub fn generate_union(input: &ItemEnum) -> TokenStream {
+    let enum_name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let streams_name = format_ident!("{}Streams", enum_name);
+    let binary_streams_name = format_ident!("{}BinaryStreams", enum_name);
+    let (marker, marker_init) = generics_marker(enum_name, &input.generics);
+
+    let variants: Vec<(&Variant, Vec<VariantField>)> = input.variants.iter().map(|v| (v, variant_fields(v))).collect();
+    let variant_count = variants.len();
+
+    let logical_variants = variants.iter().map(|(variant, fields)| {
+        let name = variant.ident.to_string();
+        let logical = match &variant.fields {
+            Fields::Unit => quote! { ::rust_tydi_packages::logical::TydiLogicalType::Null },
+            Fields::Unnamed(_) if fields.len() == 1 => field_logical_type(fields[0].field),
+            _ => {
+                let logical_fields = fields.iter().map(|f| {
+                    let name = &f.name;
+                    let logical = field_logical_type(f.field);
+                    quote! { (#name, #logical) }
+                });
+                quote! { ::rust_tydi_packages::logical::TydiLogicalType::group(vec![#(#logical_fields),*]) }
+            }
+        };
+        quote! { (#name, #logical) }
+    });
+
+    let pack_arms = variants.iter().enumerate().map(|(index, (variant, fields))| {
+        let packed: Vec<&VariantField> = fields.iter().filter(|f| !is_streamed_sequence(f.field)).collect();
+        let pattern = variant_pattern(enum_name, variant, fields, |f| !is_streamed_sequence(f.field));
+        let pack_fields = packed.iter().map(|f| {
+            let binding = &f.binding;
+            pack_field(f.field, quote! { #binding })
+        });
+        quote! {
+            #pattern => {
+                #[allow(unused_mut)]
+                let mut payload = ::rust_tydi_packages::cursor::TydiBitWriter::in_order(order);
+                #(
+                    let binary: ::rust_tydi_packages::binary::TydiBinary = #pack_fields;
+                    payload.write_binary(&binary);
+                )*
+                (#index, payload.finish())
+            }
+        }
+    });
+
+    let unpack_arms = variants.iter().enumerate().map(|(index, (variant, fields))| {
+        let unpack_fields = fields.iter().map(|f| {
+            if is_streamed_sequence(f.field) {
+                let binding = &f.binding;
+                quote! { let #binding = Default::default(); }
+            } else {
+                unpack_field(f.field, &f.binding)
+            }
+        });
+        let pattern = variant_pattern(enum_name, variant, fields, |_| true);
+        quote! {
+            #index => {
+                let mut reader = ::rust_tydi_packages::cursor::TydiBitReader::new(&payload);
+                #(#unpack_fields)*
+                let _ = &mut reader;
+                #pattern
+            }
+        }
+    });
+
+    let stream_fields: Vec<(&Variant, &[VariantField], &VariantField)> = variants.iter()
+        .flat_map(|(variant, fields)| fields.iter()
+            .filter(|f| has_child_streams(f.field))
+            .map(move |f| (*variant, fields.as_slice(), f)))
+        .collect();
+    let members: Vec<&Ident> = stream_fields.iter().map(|(_, _, f)| &f.member).collect();
+    let types: Vec<_> = stream_fields.iter().map(|(_, _, f)| &f.field.ty).collect();
+    let project_arms: Vec<TokenStream> = stream_fields.iter().map(|(variant, fields, field)| {
+        let pattern = variant_pattern(enum_name, variant, fields, |f| f.binding == field.binding);
+        let binding = &field.binding;
+        quote! { #pattern => Some(#binding), }
+    }).collect();
+    let drill_arms = stream_fields.iter().map(|(variant, fields, field)| {
+        let pattern = variant_pattern(enum_name, variant, fields, |f| f.binding == field.binding);
+        let binding = &field.binding;
+        quote! { #pattern => #binding, }
+    });
+    let without_streams_arms = variants.iter().map(|(variant, fields)| {
+        let pattern = variant_pattern(enum_name, variant, fields, |_| true);
+        let without_fields = fields.iter().map(|f| {
+            let binding = &f.binding;
+            let field_type = &f.field.ty;
+            if has_child_streams(f.field) {
+                quote! { let #binding = <#field_type as ::rust_tydi_packages::bundle::TydiDrill>::without_streams(#binding); }
+            } else {
+                quote! { let #binding = #binding.clone(); }
+            }
+        });
+        quote! {
+            #pattern => {
+                #(#without_fields)*
+                #pattern
+            }
+        }
+    });
+
+    quote! {
+        impl #impl_generics ::rust_tydi_packages::logical::TydiLogical for #enum_name #ty_generics #where_clause {
+            fn logical_type() -> ::rust_tydi_packages::logical::TydiLogicalType {
+                ::rust_tydi_packages::logical::TydiLogicalType::union(vec![#(#logical_variants),*])
+            }
+        }
+
+        impl #impl_generics ::rust_tydi_packages::binary::ToTydiBinary for #enum_name #ty_generics #where_clause {
+            fn to_tydi_binary(self, order: ::rust_tydi_packages::order::TydiBitOrder) -> ::rust_tydi_packages::binary::TydiBinary {
+                let (tag, payload): (usize, ::rust_tydi_packages::binary::TydiBinary) = match self {
+                    #(#pack_arms)*
+                };
+                let payload_width = <#enum_name #ty_generics as ::rust_tydi_packages::logical::TydiLogical>::logical_type().data_width()
+                    - ::rust_tydi_packages::logical::union_tag_width(#variant_count);
+                let padding = payload_width - payload.len;
+                let tag = ::rust_tydi_packages::logical::union_tag_to_binary(tag, #variant_count).in_order(order);
+                let mut writer = ::rust_tydi_packages::cursor::TydiBitWriter::in_order(order);
+                writer.write_binary(&tag).write_binary(&payload).write_zeros(padding);
+                writer.finish()
+            }
+        }
+
+        impl #impl_generics From<#enum_name #ty_generics> for ::rust_tydi_packages::binary::TydiBinary #where_clause {
+            fn from(value: #enum_name #ty_generics) -> Self {
+                ::rust_tydi_packages::binary::ToTydiBinary::to_tydi_binary(value, ::rust_tydi_packages::order::TydiBitOrder::PORTABLE)
+            }
+        }
+
+        impl #impl_generics ::rust_tydi_packages::binary::FromTydiBinary for #enum_name #ty_generics #where_clause {
+            fn from_tydi_binary(value: ::rust_tydi_packages::binary::TydiBinary) -> (Self, ::rust_tydi_packages::binary::TydiBinary) {
+                Self::try_from_tydi_binary(value).unwrap_or_else(|e| panic!("{}", e))
+            }
+
+            fn try_from_tydi_binary(value: ::rust_tydi_packages::binary::TydiBinary)
+                -> Result<(Self, ::rust_tydi_packages::binary::TydiBinary), ::rust_tydi_packages::error::TydiError> {
+                let (tag, res) = ::rust_tydi_packages::logical::try_union_tag_from_binary(value, #variant_count)?;
+                let payload_width = <Self as ::rust_tydi_packages::logical::TydiLogical>::logical_type().data_width()
+                    - ::rust_tydi_packages::logical::union_tag_width(#variant_count);
+                let (payload, res) = res.try_split(payload_width)?;
+                let unpacked = match tag {
+                    #(#unpack_arms)*
+                    _ => unreachable!("the tag selects one of the {} variants", #variant_count),
+                };
+                Ok((unpacked, res))
+            }
+        }
+
+        pub struct #streams_name #impl_generics #where_clause {
+            #(pub #members: <#types as ::rust_tydi_packages::bundle::TydiDrill>::Streams,)*
+            #marker
+        }
+
+        pub struct #binary_streams_name #impl_generics #where_clause {
+            #(pub #members: <#types as ::rust_tydi_packages::bundle::TydiDrill>::BinaryStreams,)*
+            #marker
+        }
+
+        impl #impl_generics ::rust_tydi_packages::bundle::TydiDrill for #enum_name #ty_generics #where_clause {
+            type Streams = #streams_name #ty_generics;
+            type BinaryStreams = #binary_streams_name #ty_generics;
+
+            #[allow(unreachable_patterns)]
+            fn drill_streams(stream: &::rust_tydi_packages::TydiStream<&Self>) -> Self::Streams {
+                #streams_name {
+                    #(#members: {
+                        // Inactive variants transfer the empty sequences of a placeholder
+                        let absent = <#types as ::rust_tydi_packages::bundle::TydiDrill>::placeholder();
+                        <#types as ::rust_tydi_packages::bundle::TydiDrill>::drill_streams(&stream.project(|e| match *e {
+                            #drill_arms
+                            _ => &absent,
+                        }))
+                    },)*
+                    #marker_init
+                }
+            }
+
+            fn without_streams(&self) -> Self {
+                match self {
+                    #(#without_streams_arms)*
+                }
+            }
+
+            #[allow(unreachable_patterns)]
+            fn inject_streams(stream: &mut ::rust_tydi_packages::TydiStream<Self>, streams: Self::Streams) {
+                #(
+                    stream.inject_optional_field(|e| match e {
+                        #project_arms
+                        _ => None,
+                    }, |s| <Option<#types> as ::rust_tydi_packages::bundle::TydiDrill>::inject_streams(s, streams.#members));
+                )*
+            }
+
+            fn finish_streams(streams: &Self::Streams, c: ::rust_tydi_packages::complexity::TydiComplexity, order: ::rust_tydi_packages::order::TydiBitOrder)
+                -> Self::BinaryStreams {
+                #binary_streams_name {
+                    #(#members: <#types as ::rust_tydi_packages::bundle::TydiDrill>::finish_streams(&streams.#members, c, order),)*
+                    #marker_init
+                }
+            }
+
+            fn streams_from_binary(binary: Self::BinaryStreams, dim: usize) -> Self::Streams {
+                #streams_name {
+                    #(#members: <#types as ::rust_tydi_packages::bundle::TydiDrill>::streams_from_binary(binary.#members, dim),)*
+                    #marker_init
+                }
+            }
+
+            #[allow(unused_variables)]
+            fn collect_binary_streams<'a>(binary: &'a Self::BinaryStreams, result: &mut Vec<&'a ::rust_tydi_packages::TydiBinaryStream>) {
+                #(<#types as ::rust_tydi_packages::bundle::TydiDrill>::collect_binary_streams(&binary.#members, result);)*
+            }
+
+            #[allow(unused_variables)]
+            fn take_binary_streams<I: Iterator<Item = ::rust_tydi_packages::TydiBinaryStream>>(streams: &mut I) -> Self::BinaryStreams {
+                #binary_streams_name {
+                    #(#members: <#types as ::rust_tydi_packages::bundle::TydiDrill>::take_binary_streams(streams),)*
+                    #marker_init
+                }
+            }
+
+            fn finish_items(items: &::rust_tydi_packages::TydiStream<Self>, c: ::rust_tydi_packages::complexity::TydiComplexity, order: ::rust_tydi_packages::order::TydiBitOrder)
+                -> Option<::rust_tydi_packages::TydiBinaryStream> {
+                ::rust_tydi_packages::bundle::finish_data_items(items, c, order)
+            }
+
+            fn items_from_binary(items: Option<::rust_tydi_packages::TydiBinaryStream>, _streams: &Self::Streams, dim: usize) -> ::rust_tydi_packages::TydiStream<Self> {
+                ::rust_tydi_packages::bundle::data_items_from_binary(items, dim)
+            }
+
+            fn placeholder() -> Self {
+                ::rust_tydi_packages::bundle::data_placeholder()
+            }
+        }
+    }
+}
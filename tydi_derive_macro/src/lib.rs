@@ -3,7 +3,7 @@ extern crate proc_macro;
 use tydi_derive_core::tydi_derive_impl;
 use proc_macro::TokenStream;
 
-#[proc_macro_derive(Tydi)]
+#[proc_macro_derive(Tydi, attributes(tydi))]
 pub fn tydi_derive(input: TokenStream) -> TokenStream {
     tydi_derive_impl(input.into()).into()
 }